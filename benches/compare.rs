@@ -0,0 +1,219 @@
+//! Compare a `FlattenDeserializer` against serde's own `#[serde(flatten)]`,
+//! which buffers every entry of the map before deserializing anything. The
+//! allocations made by each are counted with a global allocator, and printed
+//! once for each configuration before it's timed.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Count the allocations made while running `f`
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+macro_rules! structs {
+    ($($inner:ident { $($inner_field:ident),* })* ; $($outer:ident { $($outer_field:ident),* })*) => {
+        $(
+            #[derive(Deserialize)]
+            #[allow(dead_code)]
+            struct $inner {
+                $($inner_field: u32,)*
+            }
+
+            impl $inner {
+                const FIELDS: &'static [&'static str] = &[$(stringify!($inner_field)),*];
+            }
+        )*
+
+        $(
+            /// Deserialized with serde's buffering flatten
+            #[derive(Deserialize)]
+            #[allow(dead_code)]
+            struct $outer<I> {
+                $($outer_field: u32,)*
+
+                #[serde(flatten)]
+                inner: I,
+            }
+
+            impl<I> $outer<I> {
+                const FIELDS: &'static [&'static str] = &[$(stringify!($outer_field)),*];
+            }
+        )*
+    };
+}
+
+structs! {
+    Inner2 { i0, i1 }
+    Inner16 { i0, i1, i2, i3, i4, i5, i6, i7, i8, i9, i10, i11, i12, i13, i14, i15 }
+    ;
+    Outer2 { s0, s1 }
+    Outer8 { s0, s1, s2, s3, s4, s5, s6, s7 }
+}
+
+/// The bufferless counterpart to an `OuterN`'s sibling fields
+struct Siblings<const N: usize> {
+    fields: &'static [&'static str],
+    values: [Option<u32>; N],
+}
+
+impl<'de, const N: usize> KeyCapture<'de> for &mut Siblings<N> {
+    type Token = usize;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields.iter().position(|field| field.as_bytes() == key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.values[token] = Some(u32::deserialize(value)?);
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Outer")
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    SiblingsFirst,
+    SiblingsLast,
+    Interleaved,
+}
+
+fn object(siblings: &[&str], inner: &[&str], order: Order) -> String {
+    let mut keys: Vec<&str> = match order {
+        Order::SiblingsFirst => siblings.iter().chain(inner).copied().collect(),
+        Order::SiblingsLast => inner.iter().chain(siblings).copied().collect(),
+        Order::Interleaved => Vec::new(),
+    };
+
+    if let Order::Interleaved = order {
+        let (mut siblings, mut inner) = (siblings.iter(), inner.iter());
+        loop {
+            match (siblings.next(), inner.next()) {
+                (None, None) => break,
+                (sibling, field) => keys.extend(sibling.into_iter().chain(field)),
+            }
+        }
+    }
+
+    let entries: Vec<String> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, key)| format!(r#""{key}": {i}"#))
+        .collect();
+
+    format!("{{{}}}", entries.join(", "))
+}
+
+fn bench<O, I, const N: usize>(
+    c: &mut Criterion,
+    siblings: &'static [&'static str],
+    inner: &'static [&'static str],
+) where
+    O: for<'de> Deserialize<'de>,
+    I: for<'de> Deserialize<'de>,
+{
+    let mut group = c.benchmark_group(format!(
+        "{} siblings, {} inner",
+        siblings.len(),
+        inner.len()
+    ));
+
+    for order in [
+        Order::SiblingsFirst,
+        Order::SiblingsLast,
+        Order::Interleaved,
+    ] {
+        let json = object(siblings, inner, order);
+
+        let buffered = || serde_json::from_str::<O>(&json).expect("failed to deserialize");
+        let bufferless = || {
+            let mut capture = Siblings {
+                fields: siblings,
+                values: [None; N],
+            };
+            let mut de = serde_json::Deserializer::from_str(&json);
+            let inner = I::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            (inner, capture.values)
+        };
+
+        // The first run leaks the combined field list that's passed to
+        // `deserialize_struct`, which is reused from then on
+        black_box(bufferless());
+
+        println!(
+            "{} siblings, {} inner, {order:?}: serde flatten made {} allocations, \
+             FlattenDeserializer made {}",
+            siblings.len(),
+            inner.len(),
+            allocations(buffered),
+            allocations(bufferless),
+        );
+
+        group.bench_function(
+            BenchmarkId::new("serde flatten", format!("{order:?}")),
+            |b| b.iter(buffered),
+        );
+        group.bench_function(
+            BenchmarkId::new("FlattenDeserializer", format!("{order:?}")),
+            |b| b.iter(bufferless),
+        );
+    }
+
+    group.finish();
+}
+
+fn compare(c: &mut Criterion) {
+    bench::<Outer2<Inner2>, Inner2, 2>(c, Outer2::<Inner2>::FIELDS, Inner2::FIELDS);
+    bench::<Outer2<Inner16>, Inner16, 2>(c, Outer2::<Inner16>::FIELDS, Inner16::FIELDS);
+    bench::<Outer8<Inner2>, Inner2, 8>(c, Outer8::<Inner2>::FIELDS, Inner2::FIELDS);
+    bench::<Outer8<Inner16>, Inner16, 8>(c, Outer8::<Inner16>::FIELDS, Inner16::FIELDS);
+}
+
+criterion_group!(benches, compare);
+criterion_main!(benches);
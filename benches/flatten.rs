@@ -0,0 +1,260 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::NullCapture,
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{Capture, Field, Inner};
+
+/// An object with the captured and inner fields up front, followed by a long
+/// tail of keys that the inner struct ignores.
+fn wide_object(extra: usize) -> String {
+    let mut json = String::from(r#"{"before": 1.5, "after": true, "integer": 10, "string": "s""#);
+    for i in 0..extra {
+        json.push_str(&format!(r#", "key{i}": {i}"#));
+    }
+    json.push('}');
+    json
+}
+
+fn drain(c: &mut Criterion) {
+    let json = wide_object(64);
+    let mut group = c.benchmark_group("drain");
+
+    group.bench_function("with drain", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner = Inner::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    group.bench_function("without drain", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner =
+                Inner::deserialize(FlattenDeserializer::new(&mut de, &mut capture).without_drain())
+                    .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    group.finish();
+}
+
+/// A wide inner struct, so that most keys are rejected by the capture and
+/// forwarded
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Wide {
+    field0: u32,
+    field1: u32,
+    field2: u32,
+    field3: u32,
+    field4: u32,
+    field5: u32,
+    field6: u32,
+    field7: u32,
+    field8: u32,
+    field9: u32,
+    field10: u32,
+    field11: u32,
+    field12: u32,
+    field13: u32,
+    field14: u32,
+    field15: u32,
+}
+
+fn wide_inner() -> String {
+    let mut json = String::from(r#"{"before": 1.5"#);
+    for i in 0..16 {
+        json.push_str(&format!(r#", "field{i}": {i}"#));
+    }
+    json.push_str(r#", "after": true}"#);
+    json
+}
+
+fn forward(c: &mut Criterion) {
+    let json = wide_inner();
+    let mut group = c.benchmark_group("forward");
+
+    // Keys borrowed from the input arrive as `visit_borrowed_str`
+    group.bench_function("borrowed keys", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner = Wide::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    // Keys read from an `io::Read` arrive as `visit_str`
+    group.bench_function("transient keys", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_reader(black_box(json.as_bytes()));
+            let inner = Wide::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    group.finish();
+}
+
+/// The cost of the flatten machinery alone, with a capture that rejects
+/// every key
+fn overhead(c: &mut Criterion) {
+    let json = wide_inner();
+    let mut group = c.benchmark_group("overhead");
+
+    group.bench_function("direct", |b| {
+        b.iter(|| {
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            black_box(Wide::deserialize(&mut de).expect("failed to deserialize"))
+        })
+    });
+
+    group.bench_function("null capture", |b| {
+        b.iter(|| {
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner = Wide::deserialize(FlattenDeserializer::new(&mut de, NullCapture))
+                .expect("failed to deserialize");
+            black_box(inner)
+        })
+    });
+
+    group.finish();
+}
+
+/// A capture that reports itself complete once both of its fields are
+/// filled
+struct Complete<'a>(&'a mut Capture);
+
+impl<'de> KeyCapture<'de> for Complete<'_> {
+    type Token = Field;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.0.try_send_key(key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.0.send_value(token, value)
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.0.before.is_some() && self.0.after.is_some()
+    }
+}
+
+/// A wide object whose captured fields come first, so that every later key
+/// can skip the capture
+fn complete(c: &mut Criterion) {
+    let mut json = String::from(r#"{"before": 1.5, "after": true"#);
+    for i in 0..16 {
+        json.push_str(&format!(r#", "field{i}": {i}"#));
+    }
+    for i in 0..64 {
+        json.push_str(&format!(r#", "key{i}": {i}"#));
+    }
+    json.push('}');
+
+    let mut group = c.benchmark_group("complete");
+
+    group.bench_function("offer every key", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner = Wide::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    group.bench_function("is_complete", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let inner =
+                Wide::deserialize(FlattenDeserializer::new(&mut de, Complete(&mut capture)))
+                    .expect("failed to deserialize");
+            black_box((inner, capture))
+        })
+    });
+
+    group.finish();
+}
+
+/// Reads the first entry of a map and stops, leaving the rest to be drained
+struct FirstEntry(u32);
+
+impl<'de> Deserialize<'de> for FirstEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = FirstEntry;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<FirstEntry, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let (_, value): (de::IgnoredAny, u32) = map
+                    .next_entry()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                Ok(FirstEntry(value))
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// Many trailing keys left behind by the inner type, with a captured field
+/// at the very end, so that the drain does all of the work
+fn trailing(c: &mut Criterion) {
+    let mut json = String::from(r#"{"first": 1"#);
+    for i in 0..1024 {
+        json.push_str(&format!(r#", "key{i}": {i}"#));
+    }
+    json.push_str(r#", "before": 1.5}"#);
+
+    c.bench_function("drain trailing keys", |b| {
+        b.iter(|| {
+            let mut capture = Capture::default();
+            let mut de = serde_json::Deserializer::from_str(black_box(&json));
+            let first = FirstEntry::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+                .expect("failed to deserialize");
+            assert_eq!(capture.before, Some(1.5));
+            black_box((first.0, capture))
+        })
+    });
+}
+
+criterion_group!(benches, drain, forward, overhead, complete, trailing);
+criterion_main!(benches);
@@ -0,0 +1,402 @@
+//! Compare matching keys with a `match` on byte literals, as generated
+//! captures do, against a binary search of a sorted table with
+//! `match_field`. The fields share a prefix, as fields often do, and the keys
+//! include misses. A second group uses fields of varied lengths, to compare
+//! dispatching on the key's length first, either with a `match` or with
+//! `match_field_by_len`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_bufferless::private::flatten::{match_field, match_field_by_len};
+
+fn match_4(key: &[u8]) -> Option<u32> {
+    match key {
+        b"field00" => Some(0),
+        b"field01" => Some(1),
+        b"field02" => Some(2),
+        b"field03" => Some(3),
+        _ => None,
+    }
+}
+
+fn match_16(key: &[u8]) -> Option<u32> {
+    match key {
+        b"field00" => Some(0),
+        b"field01" => Some(1),
+        b"field02" => Some(2),
+        b"field03" => Some(3),
+        b"field04" => Some(4),
+        b"field05" => Some(5),
+        b"field06" => Some(6),
+        b"field07" => Some(7),
+        b"field08" => Some(8),
+        b"field09" => Some(9),
+        b"field10" => Some(10),
+        b"field11" => Some(11),
+        b"field12" => Some(12),
+        b"field13" => Some(13),
+        b"field14" => Some(14),
+        b"field15" => Some(15),
+        _ => None,
+    }
+}
+
+fn match_64(key: &[u8]) -> Option<u32> {
+    match key {
+        b"field00" => Some(0),
+        b"field01" => Some(1),
+        b"field02" => Some(2),
+        b"field03" => Some(3),
+        b"field04" => Some(4),
+        b"field05" => Some(5),
+        b"field06" => Some(6),
+        b"field07" => Some(7),
+        b"field08" => Some(8),
+        b"field09" => Some(9),
+        b"field10" => Some(10),
+        b"field11" => Some(11),
+        b"field12" => Some(12),
+        b"field13" => Some(13),
+        b"field14" => Some(14),
+        b"field15" => Some(15),
+        b"field16" => Some(16),
+        b"field17" => Some(17),
+        b"field18" => Some(18),
+        b"field19" => Some(19),
+        b"field20" => Some(20),
+        b"field21" => Some(21),
+        b"field22" => Some(22),
+        b"field23" => Some(23),
+        b"field24" => Some(24),
+        b"field25" => Some(25),
+        b"field26" => Some(26),
+        b"field27" => Some(27),
+        b"field28" => Some(28),
+        b"field29" => Some(29),
+        b"field30" => Some(30),
+        b"field31" => Some(31),
+        b"field32" => Some(32),
+        b"field33" => Some(33),
+        b"field34" => Some(34),
+        b"field35" => Some(35),
+        b"field36" => Some(36),
+        b"field37" => Some(37),
+        b"field38" => Some(38),
+        b"field39" => Some(39),
+        b"field40" => Some(40),
+        b"field41" => Some(41),
+        b"field42" => Some(42),
+        b"field43" => Some(43),
+        b"field44" => Some(44),
+        b"field45" => Some(45),
+        b"field46" => Some(46),
+        b"field47" => Some(47),
+        b"field48" => Some(48),
+        b"field49" => Some(49),
+        b"field50" => Some(50),
+        b"field51" => Some(51),
+        b"field52" => Some(52),
+        b"field53" => Some(53),
+        b"field54" => Some(54),
+        b"field55" => Some(55),
+        b"field56" => Some(56),
+        b"field57" => Some(57),
+        b"field58" => Some(58),
+        b"field59" => Some(59),
+        b"field60" => Some(60),
+        b"field61" => Some(61),
+        b"field62" => Some(62),
+        b"field63" => Some(63),
+        _ => None,
+    }
+}
+
+/// 32 fields with names of varied lengths, as a real struct's might be
+const VARIED: [&str; 32] = [
+    "id",
+    "url",
+    "name",
+    "kind",
+    "tags",
+    "email",
+    "owner",
+    "title",
+    "status",
+    "region",
+    "created",
+    "updated",
+    "version",
+    "timezone",
+    "password",
+    "username",
+    "last_name",
+    "first_name",
+    "description",
+    "created_at",
+    "updated_at",
+    "deleted_at",
+    "is_active",
+    "is_verified",
+    "phone_number",
+    "postal_code",
+    "country_code",
+    "display_name",
+    "avatar_url",
+    "permissions",
+    "organization_id",
+    "billing_address",
+];
+
+fn match_varied(key: &[u8]) -> Option<u32> {
+    match key {
+        b"id" => Some(0),
+        b"url" => Some(1),
+        b"name" => Some(2),
+        b"kind" => Some(3),
+        b"tags" => Some(4),
+        b"email" => Some(5),
+        b"owner" => Some(6),
+        b"title" => Some(7),
+        b"status" => Some(8),
+        b"region" => Some(9),
+        b"created" => Some(10),
+        b"updated" => Some(11),
+        b"version" => Some(12),
+        b"timezone" => Some(13),
+        b"password" => Some(14),
+        b"username" => Some(15),
+        b"last_name" => Some(16),
+        b"first_name" => Some(17),
+        b"description" => Some(18),
+        b"created_at" => Some(19),
+        b"updated_at" => Some(20),
+        b"deleted_at" => Some(21),
+        b"is_active" => Some(22),
+        b"is_verified" => Some(23),
+        b"phone_number" => Some(24),
+        b"postal_code" => Some(25),
+        b"country_code" => Some(26),
+        b"display_name" => Some(27),
+        b"avatar_url" => Some(28),
+        b"permissions" => Some(29),
+        b"organization_id" => Some(30),
+        b"billing_address" => Some(31),
+        _ => None,
+    }
+}
+
+/// The same `match`, dispatched on the key's length first
+fn match_varied_by_len(key: &[u8]) -> Option<u32> {
+    match key.len() {
+        2 => match key {
+            b"id" => Some(0),
+            _ => None,
+        },
+        3 => match key {
+            b"url" => Some(1),
+            _ => None,
+        },
+        4 => match key {
+            b"name" => Some(2),
+            b"kind" => Some(3),
+            b"tags" => Some(4),
+            _ => None,
+        },
+        5 => match key {
+            b"email" => Some(5),
+            b"owner" => Some(6),
+            b"title" => Some(7),
+            _ => None,
+        },
+        6 => match key {
+            b"status" => Some(8),
+            b"region" => Some(9),
+            _ => None,
+        },
+        7 => match key {
+            b"created" => Some(10),
+            b"updated" => Some(11),
+            b"version" => Some(12),
+            _ => None,
+        },
+        8 => match key {
+            b"timezone" => Some(13),
+            b"password" => Some(14),
+            b"username" => Some(15),
+            _ => None,
+        },
+        9 => match key {
+            b"last_name" => Some(16),
+            b"is_active" => Some(22),
+            _ => None,
+        },
+        10 => match key {
+            b"first_name" => Some(17),
+            b"created_at" => Some(19),
+            b"updated_at" => Some(20),
+            b"deleted_at" => Some(21),
+            b"avatar_url" => Some(28),
+            _ => None,
+        },
+        11 => match key {
+            b"description" => Some(18),
+            b"is_verified" => Some(23),
+            b"postal_code" => Some(25),
+            b"permissions" => Some(29),
+            _ => None,
+        },
+        12 => match key {
+            b"phone_number" => Some(24),
+            b"country_code" => Some(26),
+            b"display_name" => Some(27),
+            _ => None,
+        },
+        15 => match key {
+            b"organization_id" => Some(30),
+            b"billing_address" => Some(31),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+type Matcher = fn(&[u8]) -> Option<u32>;
+
+/// A sorted table of `count` fields, named as in the `match_*` functions
+fn table(count: u32) -> Vec<(&'static [u8], u32)> {
+    let mut table: Vec<(&'static [u8], u32)> = (0..count)
+        .map(|i| {
+            let name: &'static str = Box::leak(format!("field{i:02}").into_boxed_str());
+            (name.as_bytes(), i)
+        })
+        .collect();
+
+    table.sort_unstable();
+    table
+}
+
+/// Every field's key, followed by as many misses
+fn keys(count: u32) -> Vec<Vec<u8>> {
+    (0..count)
+        .map(|i| format!("field{i:02}").into_bytes())
+        .chain((0..count).map(|i| format!("other{i:02}").into_bytes()))
+        .collect()
+}
+
+fn fields(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match field");
+    let matchers: [(u32, Matcher); 3] = [(4, match_4), (16, match_16), (64, match_64)];
+
+    for (count, matcher) in matchers {
+        let table = table(count);
+        let keys = keys(count);
+
+        for key in &keys {
+            assert_eq!(matcher(key), match_field(&table, key));
+        }
+
+        group.bench_with_input(BenchmarkId::new("match", count), &keys, |b, keys| {
+            b.iter(|| {
+                for key in keys {
+                    black_box(matcher(black_box(key)));
+                }
+            })
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("binary search", count),
+            &keys,
+            |b, keys| {
+                b.iter(|| {
+                    for key in keys {
+                        black_box(match_field(&table, black_box(key)));
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// The `VARIED` fields, bucketed by length for `match_field_by_len`
+fn table_by_len() -> Vec<Vec<(&'static [u8], u32)>> {
+    let mut table = Vec::new();
+
+    for (i, name) in (0..).zip(VARIED) {
+        if table.len() <= name.len() {
+            table.resize_with(name.len() + 1, Vec::new);
+        }
+        table[name.len()].push((name.as_bytes(), i));
+    }
+
+    table
+}
+
+/// Every `VARIED` field's key, followed by misses of the same lengths
+fn varied_keys() -> Vec<Vec<u8>> {
+    let hits = VARIED.iter().map(|name| name.as_bytes().to_vec());
+    let misses = VARIED.iter().map(|name| {
+        let mut miss = name.as_bytes().to_vec();
+        miss[0] = b'x';
+        miss
+    });
+
+    hits.chain(misses).collect()
+}
+
+fn varied_lengths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match field by length");
+
+    let buckets = table_by_len();
+    let table: Vec<&[(&[u8], u32)]> = buckets.iter().map(Vec::as_slice).collect();
+    let mut sorted: Vec<(&[u8], u32)> = buckets.iter().flatten().copied().collect();
+    sorted.sort_unstable();
+    let keys = varied_keys();
+
+    for key in &keys {
+        let expected = match_varied(key);
+        assert_eq!(match_varied_by_len(key), expected);
+        assert_eq!(match_field_by_len(&table, key), expected);
+        assert_eq!(match_field(&sorted, key), expected);
+    }
+
+    let matchers: [(&str, Matcher); 2] = [
+        ("match", match_varied),
+        ("match by length", match_varied_by_len),
+    ];
+
+    for (name, matcher) in matchers {
+        group.bench_with_input(BenchmarkId::new(name, 32), &keys, |b, keys| {
+            b.iter(|| {
+                for key in keys {
+                    black_box(matcher(black_box(key)));
+                }
+            })
+        });
+    }
+
+    group.bench_with_input(
+        BenchmarkId::new("match_field_by_len", 32),
+        &keys,
+        |b, keys| {
+            b.iter(|| {
+                for key in keys {
+                    black_box(match_field_by_len(&table, black_box(key)));
+                }
+            })
+        },
+    );
+
+    group.bench_with_input(BenchmarkId::new("binary search", 32), &keys, |b, keys| {
+        b.iter(|| {
+            for key in keys {
+                black_box(match_field(&sorted, black_box(key)));
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, fields, varied_lengths);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use serde::Deserialize;
+use serde_bufferless::private::{flatten::FlattenDeserializer, upfront::deserialize_upfront};
+use serde_json::Value;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{Capture, Inner};
+
+/// An object with the captured fields at the end, after the inner fields and
+/// a tail of keys that the inner struct ignores.
+fn wide_value(extra: usize) -> Value {
+    let mut json = String::from(r#"{"integer": 10, "string": "s""#);
+    for i in 0..extra {
+        json.push_str(&format!(r#", "key{i}": {i}"#));
+    }
+    json.push_str(r#", "before": 1.5, "after": true}"#);
+    serde_json::from_str(&json).unwrap()
+}
+
+fn upfront(c: &mut Criterion) {
+    let value = wide_value(64);
+    let mut group = c.benchmark_group("value source");
+
+    group.bench_function("streaming", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| {
+                let mut capture = Capture::default();
+                let inner = Inner::deserialize(FlattenDeserializer::new(value, &mut capture))
+                    .expect("failed to deserialize");
+                black_box((inner, capture))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("upfront", |b| {
+        b.iter_batched(
+            || value.clone(),
+            |value| {
+                let mut capture = Capture::default();
+                let inner: Inner =
+                    deserialize_upfront(value, &mut capture).expect("failed to deserialize");
+                black_box((inner, capture))
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, upfront);
+criterion_main!(benches);
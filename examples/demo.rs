@@ -1,7 +1,8 @@
 use anyhow::Context;
 use serde::{de, Deserialize};
-use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+use serde_bufferless::{key_capture, private::flatten::FlattenDeserializer};
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct Inner {
     integer: i32,
@@ -12,6 +13,7 @@ struct Inner {
     float: f32,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 struct Outer {
     float: f32,
@@ -28,60 +30,24 @@ impl<'de> Deserialize<'de> for Outer {
     where
         D: serde::Deserializer<'de>,
     {
-        // The Field enum is generated for each non-flatten field
-        #[allow(non_camel_case_types)]
-        enum Field {
-            float,
-            boolean,
-        }
-
-        // The Capture struct is generated, containing an Option for each
-        // non-capture field
-        struct Capture {
-            float: Option<f32>,
-            boolean: Option<bool>,
-        }
-
-        // KeyCapture is implemented such that `try_send_key` detects
-        // non-flatten keys and returns the matching Field, if it matches,
-        // and the `send_value` deserializes the relevant value based on the
-        // `Field`. The methods are inlined so that control flow analysis
-        // will notice the association between the return value of
-        // `try_send_key` and the `match` in `send_value`
-        impl<'de> KeyCapture<'de> for &mut Capture {
-            type Token = Field;
-
-            #[inline]
-            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
-                match key {
-                    b"float" => Some(Field::float),
-                    b"boolean" => Some(Field::boolean),
-                    _ => None,
-                }
-            }
-
-            #[inline]
-            fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
-            where
-                D: serde::de::Deserializer<'de>,
-            {
-                match field {
-                    Field::float => self.float = Some(Deserialize::deserialize(value)?),
-                    Field::boolean => self.boolean = Some(Deserialize::deserialize(value)?),
-                }
-
-                Ok(())
-            }
-
-            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
-                write!(formatter, "struct Outer")
+        // `key_capture!` generates the Capture struct, containing an Option
+        // for each non-flatten field, along with a Field enum with a variant
+        // for each of them. It implements KeyCapture for `&mut Capture` such
+        // that `try_send_key` detects non-flatten keys and returns the
+        // matching Field, if it matches, and `send_value` deserializes the
+        // relevant value based on the `Field`. The methods are inlined so
+        // that control flow analysis will notice the association between the
+        // return value of `try_send_key` and the `match` in `send_value`.
+        // `for Outer` makes errors describe the outer struct, rather than
+        // the capture
+        key_capture! {
+            Capture for Outer {
+                float: Option<f32>,
+                boolean: Option<bool>,
             }
         }
 
-        let mut capture = Capture {
-            float: None,
-            boolean: None,
-        };
+        let mut capture = Capture::default();
 
         // After the `Capture` is created, we use a `FlattenDeserializer` to
         // deserialize the flattened field. The `FlattenDeserializer` will
@@ -0,0 +1,29 @@
+use anyhow::Context;
+use serde::Deserialize;
+
+#[path = "../tests/common/envelope.rs"]
+mod envelope;
+
+use envelope::Envelope;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Login<'a> {
+    user: &'a str,
+    success: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let data: Envelope<Login> = serde_json::from_str(
+        r#"{
+            "user": "lucretiel",
+            "meta": {"id": 1024, "version": 2},
+            "success": true
+        }"#,
+    )
+    .context("failed to parse json")?;
+
+    println!("{:#?}", data);
+
+    Ok(())
+}
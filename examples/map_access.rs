@@ -0,0 +1,93 @@
+//! An outer type with a hand-written `Visitor` that reads the map itself
+//! through a `FlattenMapAccess`. The capture still receives `version`
+//! wherever it appears, while the visitor handles every other key: keys
+//! starting with `x-` are extensions, which are skipped, and the rest are
+//! collected as settings.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::{de, Deserialize};
+use serde_bufferless::{key_capture, private::flatten::FlattenMapAccess};
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Config {
+    version: u32,
+    settings: BTreeMap<String, String>,
+    skipped: usize,
+}
+
+key_capture! {
+    Capture {
+        version: Option<u32>,
+    }
+}
+
+impl<'de> Deserialize<'de> for Config {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = Config;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("struct Config")
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Config, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut capture = Capture::default();
+                let mut map = FlattenMapAccess::new(map, &mut capture);
+
+                let mut settings = BTreeMap::new();
+                let mut skipped = 0;
+
+                // Only the keys that the capture rejected are returned here
+                while let Some(key) = de::MapAccess::next_key::<String>(&mut map)? {
+                    if key.starts_with("x-") {
+                        de::MapAccess::next_value::<de::IgnoredAny>(&mut map)?;
+                        skipped += 1;
+                    } else {
+                        settings.insert(key, de::MapAccess::next_value(&mut map)?);
+                    }
+                }
+
+                // This loop read the whole map, so there's nothing left to
+                // drain, but the capture still has to be finished
+                let capture = map.finish()?;
+
+                Ok(Config {
+                    version: capture
+                        .version
+                        .ok_or_else(|| de::Error::missing_field("version"))?,
+                    settings,
+                    skipped,
+                })
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let config: Config = serde_json::from_str(
+        r#"{
+            "theme": "dark",
+            "x-editor-state": {"cursor": [10, 4]},
+            "version": 3,
+            "font": "mono"
+        }"#,
+    )
+    .context("failed to parse json")?;
+
+    println!("{:#?}", config);
+
+    Ok(())
+}
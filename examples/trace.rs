@@ -0,0 +1,89 @@
+//! The demo, with its capture wrapped in a `TracingCapture`, which reports
+//! every key the capture is offered, whether it was accepted, and every
+//! value it's sent. For the demo input, the trace (on stderr) is:
+//!
+//! ```text
+//! key "integer": rejected
+//! key "float": accepted
+//! value: ok
+//! key "string": rejected
+//! key "boolean": accepted
+//! value: ok
+//! finish: ok
+//! ```
+
+use anyhow::Context;
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::{Trace, TracingCapture},
+    key_capture,
+    private::flatten::FlattenDeserializer,
+};
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Outer {
+    float: f32,
+    boolean: bool,
+
+    //#[serde(flatten)]
+    inner: Inner,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        key_capture! {
+            Capture {
+                float: Option<f32>,
+                boolean: Option<bool>,
+            }
+        }
+
+        let mut capture = Capture::default();
+
+        let inner = Deserialize::deserialize(FlattenDeserializer::new(
+            deserializer,
+            TracingCapture::new(&mut capture, |trace: Trace<'_>| eprintln!("{trace}")),
+        ))?;
+
+        let float = capture
+            .float
+            .ok_or_else(|| de::Error::missing_field("float"))?;
+
+        let boolean = capture
+            .boolean
+            .ok_or_else(|| de::Error::missing_field("boolean"))?;
+
+        Ok(Self {
+            float,
+            boolean,
+            inner,
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let data: Outer = serde_json::from_str(
+        r#"{
+            "integer": 10,
+            "float": 10.5,
+            "string": "hello",
+            "boolean": true
+        }"#,
+    )
+    .context("failed to parse json")?;
+
+    println!("{:#?}", data);
+
+    Ok(())
+}
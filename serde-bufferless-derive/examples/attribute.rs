@@ -0,0 +1,40 @@
+use anyhow::Context;
+use serde::Deserialize;
+use serde_bufferless_derive::bufferless_flatten;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+// This expands to the struct, without its serde attributes, followed by the
+// same `Deserialize` impl that `examples/demo.rs` builds with `key_capture!`.
+#[allow(dead_code)]
+#[bufferless_flatten]
+#[derive(Debug)]
+struct Outer {
+    float: f32,
+
+    #[serde(default)]
+    boolean: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+fn main() -> anyhow::Result<()> {
+    let data: Outer = serde_json::from_str(
+        r#"{
+            "integer": 10,
+            "float": 10.5,
+            "string": "hello"
+        }"#,
+    )
+    .context("failed to parse json")?;
+
+    println!("{:#?}", data);
+
+    Ok(())
+}
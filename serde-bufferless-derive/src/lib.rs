@@ -0,0 +1,489 @@
+/*!
+Procedural macros for `serde-bufferless`.
+
+[`derive@BufferlessDeserialize`] replaces serde's `#[derive(Deserialize)]`
+for a struct with exactly one `#[serde(flatten)]` field, generating a
+bufferless `Deserialize` impl. [`macro@bufferless_flatten`] is the same thing
+as an attribute, for structs that don't otherwise use serde's derives: it
+removes the serde attributes that nothing else would claim. Either way, the
+impl is the same one `serde_bufferless::codegen::StructSpec` renders for a
+`build.rs`, so it has the same structure as the hand-written impl in
+`examples/demo.rs`.
+
+```ignore
+use serde::Deserialize;
+use serde_bufferless_derive::BufferlessDeserialize;
+
+#[derive(Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[derive(BufferlessDeserialize)]
+struct Outer {
+    float: f32,
+
+    #[serde(default)]
+    boolean: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+```
+
+The generated code refers to `::serde` and `::serde_bufferless`, so both must
+be dependencies of the crate that uses these macros.
+
+With the `buffer-fallback` feature, a struct may have more than one
+`#[serde(flatten)]` field. Its sibling fields are still captured without
+buffering, but every other entry is collected and replayed to each flattened
+field in turn, through a
+`serde_bufferless::private::buffered::BufferedFlattenDeserializer`, which
+follows serde's own rules for which flattened field sees which entry. This
+only happens for structs with more than one flattened field; the
+`serde_bufferless` that the generated code is compiled against needs its own
+`buffer-fallback` feature enabled as well.
+*/
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use serde_bufferless::{codegen::StructSpec, rename::RenameRule};
+use syn::{ext::IdentExt, parse_macro_input, Attribute, Fields, ItemStruct, LitStr};
+
+/// Generate a bufferless `Deserialize` impl for a struct with a single
+/// `#[serde(flatten)]` field.
+///
+/// Sibling fields are deserialized with their own `Deserialize` impls, and
+/// may be marked `#[serde(default)]` or `#[serde(default = "path")]`, or
+/// given `#[serde(alias = "...")]`s; no other serde attributes are
+/// supported. The struct itself may have `#[serde(rename_all = "...")]`, as
+/// with [`derive@BufferlessDeserialize`]. The struct must not also derive
+/// `Deserialize`. If it derives `Serialize`, its serde attributes are left in
+/// place for that derive; otherwise they're removed from the output.
+#[proc_macro_attribute]
+pub fn bufferless_flatten(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(
+            TokenStream2::from(attr)
+                .into_iter()
+                .next()
+                .map_or_else(Span::call_site, |tt| tt.span()),
+            "#[bufferless_flatten] takes no arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let item = parse_macro_input!(item as ItemStruct);
+    expand(item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive a bufferless `Deserialize` impl for a struct with a single
+/// `#[serde(flatten)]` field, in place of serde's own
+/// `#[derive(Deserialize)]`.
+///
+/// This generates the `Field` token enum, the `Capture` struct, its
+/// `KeyCapture` impl, and a `Deserialize` impl that deserializes the
+/// flattened field through a `FlattenDeserializer`. Sibling fields are
+/// required, and reported with `missing_field` if they're absent (an
+/// `Option` is `None` instead), unless they're marked `#[serde(default)]` or
+/// `#[serde(default = "path")]`, and may have any number of
+/// `#[serde(alias = "...")]`s. Fields marked `#[serde(skip)]` aren't captured
+/// at all, and are filled with `Default::default()`, or by calling `path` if
+/// they're also marked `#[serde(default = "path")]`. No other serde
+/// attributes are supported. The serde attributes are shared with
+/// `#[derive(Serialize)]`, if it's also used.
+///
+/// The struct itself may have `#[serde(rename_all = "...")]`, which renames
+/// the sibling fields' keys as serde would; it doesn't affect their aliases,
+/// or the keys of the flattened field, which are up to its own type. Any
+/// other container attribute is rejected.
+#[proc_macro_derive(BufferlessDeserialize, attributes(serde))]
+pub fn derive_bufferless_deserialize(item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemStruct);
+    generate(&item, "#[derive(BufferlessDeserialize)]")
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// How a field was marked with `#[serde(...)]`
+#[derive(Default)]
+struct FieldAttrs {
+    flatten: bool,
+    default: bool,
+    default_path: Option<String>,
+    skip: bool,
+    aliases: Vec<String>,
+}
+
+fn is_serde(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde")
+}
+
+fn field_attrs(attrs: &[Attribute], macro_name: &str) -> syn::Result<FieldAttrs> {
+    let mut parsed = FieldAttrs::default();
+
+    for attr in attrs.iter().filter(|attr| is_serde(attr)) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("flatten") {
+                parsed.flatten = true;
+                Ok(())
+            } else if meta.path.is_ident("default") && meta.input.is_empty() {
+                parsed.default = true;
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                let path: LitStr = meta.value()?.parse()?;
+                parsed.default_path = Some(path.value());
+                Ok(())
+            } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                parsed.skip = true;
+                Ok(())
+            } else if meta.path.is_ident("alias") {
+                let alias: LitStr = meta.value()?.parse()?;
+                parsed.aliases.push(alias.value());
+                Ok(())
+            } else {
+                Err(meta.error(format_args!("unsupported by {macro_name}")))
+            }
+        })?;
+    }
+
+    Ok(parsed)
+}
+
+/// Parse the struct's own `#[serde(...)]` attributes, of which only
+/// `rename_all` is supported
+fn container_attrs(attrs: &[Attribute], macro_name: &str) -> syn::Result<Option<RenameRule>> {
+    let mut rename_all = None;
+
+    for attr in attrs.iter().filter(|attr| is_serde(attr)) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                let rule: LitStr = meta.value()?.parse()?;
+                rename_all = Some(RenameRule::from_name(&rule.value()).ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        &rule,
+                        format_args!("unknown rename rule `{}`", rule.value()),
+                    )
+                })?);
+                Ok(())
+            } else {
+                Err(meta.error(format_args!("unsupported by {macro_name}")))
+            }
+        })?;
+    }
+
+    Ok(rename_all)
+}
+
+/// Check whether the struct has a `#[derive(...)]` that includes `name`
+fn derives(item: &ItemStruct, name: &str) -> syn::Result<Option<Span>> {
+    for attr in item
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+    {
+        let mut found = None;
+        attr.parse_nested_meta(|meta| {
+            if let Some(seg) = meta.path.segments.last().filter(|seg| seg.ident == name) {
+                found = Some(seg.ident.span());
+            }
+            Ok(())
+        })?;
+
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Render the `Deserialize` impl for a struct, checking that it has exactly
+/// one `#[serde(flatten)]` field. `macro_name` is used in error messages.
+fn generate(item: &ItemStruct, macro_name: &str) -> syn::Result<TokenStream2> {
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            format_args!("{macro_name} doesn't support generic structs"),
+        ));
+    }
+
+    let fields = match &item.fields {
+        Fields::Named(fields) => &fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                item.struct_token,
+                format_args!("{macro_name} requires a struct with named fields"),
+            ))
+        }
+    };
+
+    let rename_all = container_attrs(&item.attrs, macro_name)?;
+    let mut spec = StructSpec::new(item.ident.to_string());
+    let mut flatten = Vec::new();
+
+    for field in fields {
+        let attrs = field_attrs(&field.attrs, macro_name)?;
+        let name = field.ident.as_ref().expect("named fields have names");
+        let ty = field.ty.to_token_stream().to_string();
+
+        spec = match attrs {
+            FieldAttrs {
+                flatten: true,
+                ref aliases,
+                ..
+            } if !aliases.is_empty() => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    format_args!("{macro_name} doesn't support aliases on the flatten field"),
+                ))
+            }
+            FieldAttrs {
+                skip: true,
+                default_path: Some(ref path),
+                ..
+            } => spec.skip_field_with(name.to_string(), ty, path),
+            FieldAttrs { skip: true, .. } => spec.skip_field(name.to_string(), ty),
+            FieldAttrs { flatten: true, .. } => {
+                flatten.push(field);
+                spec.flatten(name.to_string(), ty)
+            }
+            FieldAttrs {
+                default_path: Some(ref path),
+                ..
+            } => spec.default_field_with(name.to_string(), ty, path),
+            FieldAttrs { default: true, .. } => spec.default_field(name.to_string(), ty),
+            FieldAttrs { .. } => spec.field(name.to_string(), ty),
+        };
+
+        if !attrs.flatten && !attrs.skip {
+            // The key of `r#type` is `type`, as with serde's derive
+            let key = name.unraw().to_string();
+            spec = match rename_all {
+                Some(rule) => spec.rename(rule.apply(&key)),
+                None => spec.rename(key),
+            };
+            spec = attrs.aliases.into_iter().fold(spec, StructSpec::alias);
+        }
+    }
+
+    match flatten.as_slice() {
+        [_] => {}
+        [_, _, ..] if cfg!(feature = "buffer-fallback") => {}
+        [] => {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                format_args!(
+                    "{macro_name} requires exactly one #[serde(flatten)] field, found none"
+                ),
+            ))
+        }
+        [_, extra, ..] => {
+            return Err(syn::Error::new_spanned(
+                extra,
+                format_args!(
+                    "{macro_name} requires exactly one #[serde(flatten)] field; \
+                     more than one can't be deserialized without buffering \
+                     (enable the `buffer-fallback` feature to allow it)"
+                ),
+            ))
+        }
+    }
+
+    Ok(spec.generate().parse()?)
+}
+
+fn expand(mut item: ItemStruct) -> syn::Result<TokenStream2> {
+    if let Some(span) = derives(&item, "Deserialize")? {
+        return Err(syn::Error::new(
+            span,
+            "#[bufferless_flatten] generates the Deserialize impl; remove this derive",
+        ));
+    }
+
+    let generated = generate(&item, "#[bufferless_flatten]")?;
+
+    // Without a serde derive to claim them, the serde attributes would be
+    // rejected by the compiler.
+    if derives(&item, "Serialize")?.is_none() {
+        item.attrs.retain(|attr| !is_serde(attr));
+        for field in item.fields.iter_mut() {
+            field.attrs.retain(|attr| !is_serde(attr));
+        }
+    }
+
+    Ok(quote! {
+        #item
+        #generated
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(input: TokenStream2) -> Result<String, String> {
+        let item = syn::parse2(input).map_err(|err| err.to_string())?;
+        expand(item)
+            .map(|tokens| tokens.to_string())
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn expansion() {
+        let expanded = expand_str(quote! {
+            #[derive(Debug)]
+            struct Outer {
+                before: f32,
+                #[serde(default)]
+                after: bool,
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap();
+
+        let generated: TokenStream2 = StructSpec::new("Outer")
+            .field("before", "f32")
+            .default_field("after", "bool")
+            .flatten("inner", "Inner")
+            .generate()
+            .parse()
+            .unwrap();
+
+        let expected = quote! {
+            #[derive(Debug)]
+            struct Outer {
+                before: f32,
+                after: bool,
+                inner: Inner,
+            }
+            #generated
+        };
+
+        assert_eq!(expanded, expected.to_string());
+    }
+
+    #[test]
+    fn serialize_keeps_attributes() {
+        let expanded = expand_str(quote! {
+            #[derive(Serialize)]
+            struct Outer {
+                before: f32,
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap();
+
+        assert!(expanded.contains("# [serde (flatten)]"), "{}", expanded);
+    }
+
+    #[test]
+    fn no_flatten() {
+        let err = expand_str(quote! {
+            struct Outer {
+                before: f32,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            "#[bufferless_flatten] requires exactly one #[serde(flatten)] field, found none"
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "buffer-fallback"))]
+    fn two_flatten() {
+        let err = expand_str(quote! {
+            struct Outer {
+                #[serde(flatten)]
+                first: First,
+                #[serde(flatten)]
+                second: Second,
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.contains("more than one"), "{}", err);
+    }
+
+    #[test]
+    fn unsupported_attribute() {
+        let err = expand_str(quote! {
+            struct Outer {
+                #[serde(rename = "b")]
+                before: f32,
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(err, "unsupported by #[bufferless_flatten]");
+    }
+
+    #[test]
+    fn unsupported_container_attribute() {
+        let err = expand_str(quote! {
+            #[serde(deny_unknown_fields)]
+            struct Outer {
+                before: f32,
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(err, "unsupported by #[bufferless_flatten]");
+
+        let err = expand_str(quote! {
+            #[serde(rename_all = "Title Case")]
+            struct Outer {
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(err, "unknown rename rule `Title Case`");
+    }
+
+    #[test]
+    fn flatten_alias() {
+        let err = expand_str(quote! {
+            struct Outer {
+                before: f32,
+                #[serde(flatten, alias = "rest")]
+                inner: Inner,
+            }
+        })
+        .unwrap_err();
+
+        assert_eq!(
+            err,
+            "#[bufferless_flatten] doesn't support aliases on the flatten field"
+        );
+    }
+
+    #[test]
+    fn derived_deserialize() {
+        let err = expand_str(quote! {
+            #[derive(serde::Deserialize)]
+            struct Outer {
+                #[serde(flatten)]
+                inner: Inner,
+            }
+        })
+        .unwrap_err();
+
+        assert!(err.contains("remove this derive"), "{}", err);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_bufferless_derive::BufferlessDeserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Version {
+    major: u32,
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct Outer {
+    before: f32,
+
+    #[serde(flatten)]
+    inner: Inner,
+
+    #[serde(flatten)]
+    version: Version,
+
+    #[serde(flatten)]
+    rest: BTreeMap<String, i32>,
+}
+
+#[test]
+fn several_flattened_fields() {
+    let data: Outer = serde_json::from_str(
+        r#"{"integer": 10, "before": 1.5, "major": 2, "string": "hello", "extra": 3}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data.before, 1.5);
+    assert_eq!(
+        data.inner,
+        Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        }
+    );
+    assert_eq!(data.version, Version { major: 2 });
+    assert_eq!(data.rest, BTreeMap::from([("extra".to_owned(), 3)]));
+}
+
+#[test]
+fn missing_sibling() {
+    let err = serde_json::from_str::<Outer>(r#"{"integer": 10, "major": 2, "string": "hello"}"#)
+        .expect_err("`before` is required");
+
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use serde_bufferless_derive::bufferless_flatten;
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[bufferless_flatten]
+#[derive(Debug, PartialEq)]
+struct Outer {
+    before: f32,
+
+    #[serde(default)]
+    after: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn deserialize() {
+    let data: Outer =
+        serde_json::from_str(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+}
+
+#[test]
+fn default_and_required() {
+    let data: Outer = serde_json::from_str(r#"{"integer": 10, "before": 1.5, "string": "hello"}"#)
+        .expect("`after` has a default");
+    assert!(!data.after);
+
+    let err = serde_json::from_str::<Outer>(r#"{"integer": 10, "string": "hello"}"#)
+        .expect_err("`before` is required");
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
+
+/// A `Serialize` derive alongside the attribute still sees `#[serde(flatten)]`
+#[bufferless_flatten]
+#[derive(Debug, PartialEq, Serialize)]
+struct RoundTrip {
+    id: u64,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn serialize_derive() {
+    let json = r#"{"id":3,"integer":10,"string":"hello"}"#;
+    let data: RoundTrip = serde_json::from_str(json).expect("failed to deserialize");
+
+    assert_eq!(serde_json::to_string(&data).unwrap(), json);
+}
@@ -0,0 +1,255 @@
+//! Bufferless flatten, checked against serde's own buffered flatten as an
+//! oracle. Each shape is declared once, and expands to a serde-derived struct
+//! and a `#[bufferless_flatten]` struct with the same fields; every input in
+//! the table is deserialized into both, and the results are compared by
+//! serializing them back to JSON. Errors are compared by their presence only,
+//! since the two implementations word some of them differently.
+//!
+//! Inputs where the two are known to differ are marked as divergent, with the
+//! reason; the test fails if they start to agree, so that the mark can be
+//! removed.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_bufferless_derive::bufferless_flatten;
+use serde_json::Value;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Nested {
+    point: Point,
+    label: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Kind {
+    Plain,
+    Count(u32),
+}
+
+type Outcome = Result<Value, String>;
+
+/// Deserialize an input into both versions of a shape
+type Check = fn(&str) -> (Outcome, Outcome);
+
+macro_rules! shape {
+    ($name:ident { $($(#[$attr:meta])* $field:ident: $ty:ty,)* }) => {
+        mod $name {
+            use super::*;
+
+            #[derive(Serialize, Deserialize)]
+            pub struct Oracle {
+                $($(#[$attr])* $field: $ty,)*
+            }
+
+            #[bufferless_flatten]
+            #[derive(Serialize)]
+            pub struct Bufferless {
+                $($(#[$attr])* $field: $ty,)*
+            }
+
+            pub fn check(input: &str) -> (Outcome, Outcome) {
+                (
+                    serde_json::from_str::<Oracle>(input)
+                        .map(|data| serde_json::to_value(data).unwrap())
+                        .map_err(|err| err.to_string()),
+                    serde_json::from_str::<Bufferless>(input)
+                        .map(|data| serde_json::to_value(data).unwrap())
+                        .map_err(|err| err.to_string()),
+                )
+            }
+        }
+    };
+}
+
+shape!(basic {
+    before: f32,
+    after: bool,
+    #[serde(flatten)]
+    inner: Inner,
+});
+
+shape!(optional {
+    maybe: Option<i32>,
+    #[serde(flatten)]
+    inner: Inner,
+});
+
+shape!(defaulted {
+    #[serde(default)]
+    count: u32,
+    #[serde(flatten)]
+    inner: Inner,
+});
+
+shape!(nested {
+    id: u64,
+    #[serde(flatten)]
+    inner: Nested,
+});
+
+shape!(enums {
+    kind: Kind,
+    #[serde(flatten)]
+    inner: Inner,
+});
+
+shape!(map {
+    id: u64,
+    #[serde(flatten)]
+    rest: BTreeMap<String, Value>,
+});
+
+enum Expect {
+    Same,
+    // No input is currently known to diverge
+    #[allow(dead_code)]
+    Divergent(&'static str),
+}
+
+use Expect::*;
+
+#[test]
+fn matrix() {
+    let cases: &[(&str, Check, &str, Expect)] = &[
+        (
+            "basic",
+            basic::check,
+            r#"{"before": 1.5, "after": true, "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "basic",
+            basic::check,
+            r#"{"integer": 1, "after": true, "string": "s", "before": 1.5}"#,
+            Same,
+        ),
+        (
+            "basic",
+            basic::check,
+            r#"{"before": 1.5, "after": true, "integer": 1, "string": "s", "junk": [1]}"#,
+            Same,
+        ),
+        (
+            "basic",
+            basic::check,
+            r#"{"after": true, "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "basic",
+            basic::check,
+            r#"{"before": "x", "after": true, "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "basic",
+            basic::check,
+            r#"{"before": 1.5, "after": true, "integer": 1, "string": "s", "before": 2.5}"#,
+            Same,
+        ),
+        ("basic", basic::check, r#"[1.5, true, 1, "s"]"#, Same),
+        (
+            "optional",
+            optional::check,
+            r#"{"maybe": 3, "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "optional",
+            optional::check,
+            r#"{"maybe": null, "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "optional",
+            optional::check,
+            r#"{"integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "defaulted",
+            defaulted::check,
+            r#"{"integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "defaulted",
+            defaulted::check,
+            r#"{"integer": 1, "count": 4, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "nested",
+            nested::check,
+            r#"{"point": {"x": 1, "y": 2}, "id": 7, "label": "l"}"#,
+            Same,
+        ),
+        (
+            "nested",
+            nested::check,
+            r#"{"point": {"x": 1}, "id": 7, "label": "l"}"#,
+            Same,
+        ),
+        (
+            "enums",
+            enums::check,
+            r#"{"kind": "Plain", "integer": 1, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "enums",
+            enums::check,
+            r#"{"integer": 1, "kind": {"Count": 3}, "string": "s"}"#,
+            Same,
+        ),
+        (
+            "enums",
+            enums::check,
+            r#"{"integer": 1, "kind": "Other", "string": "s"}"#,
+            Same,
+        ),
+        (
+            "map",
+            map::check,
+            r#"{"a": 1, "id": 7, "b": [true], "c": {"d": null}}"#,
+            Same,
+        ),
+        ("map", map::check, r#"{"id": 7}"#, Same),
+    ];
+
+    let mut failures = Vec::new();
+
+    for (shape, check, input, expect) in cases {
+        let (oracle, bufferless) = check(input);
+        let agree = match (&oracle, &bufferless) {
+            (Ok(oracle), Ok(bufferless)) => oracle == bufferless,
+            (Err(_), Err(_)) => true,
+            _ => false,
+        };
+
+        match (expect, agree) {
+            (Same, true) | (Divergent(_), false) => {}
+            (Same, false) => failures.push(format!(
+                "{shape} {input}: serde gave {oracle:?}, bufferless gave {bufferless:?}"
+            )),
+            (Divergent(reason), true) => failures.push(format!(
+                "{shape} {input}: marked divergent ({reason}), but both gave {oracle:?}"
+            )),
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use serde_bufferless_derive::BufferlessDeserialize;
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize, Serialize)]
+struct Outer {
+    before: f32,
+
+    #[serde(default)]
+    after: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn deserialize() {
+    let data: Outer =
+        serde_json::from_str(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+}
+
+#[test]
+fn default_and_required() {
+    let data: Outer = serde_json::from_str(r#"{"string": "hello", "before": 1.5, "integer": 10}"#)
+        .expect("`after` has a default");
+    assert!(!data.after);
+
+    let err = serde_json::from_str::<Outer>(r#"{"integer": 10, "string": "hello", "after": true}"#)
+        .expect_err("`before` is required");
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct Optional {
+    maybe: Option<i32>,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+/// As with serde's derive, a missing `Option` sibling is `None`
+#[test]
+fn missing_option() {
+    let data: Optional =
+        serde_json::from_str(r#"{"integer": 10, "string": "hello"}"#).expect("`maybe` is optional");
+    assert_eq!(data.maybe, None);
+}
+
+#[test]
+fn duplicate_sibling() {
+    let err = serde_json::from_str::<Outer>(
+        r#"{"before": 1.5, "integer": 10, "before": 2.5, "string": "hello"}"#,
+    )
+    .expect_err("`before` appears twice");
+    assert!(
+        err.to_string().starts_with("duplicate field `before`"),
+        "{}",
+        err
+    );
+}
+
+/// The serde attributes are shared with `#[derive(Serialize)]`
+#[test]
+fn round_trip() {
+    let json = r#"{"before":1.5,"after":false,"integer":10,"string":"hello"}"#;
+    let data: Outer = serde_json::from_str(json).expect("failed to deserialize");
+
+    assert_eq!(serde_json::to_string(&data).unwrap(), json);
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct Aliased {
+    #[serde(alias = "prior", alias = "previous")]
+    before: f32,
+
+    #[serde(alias = "following")]
+    after: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn aliases() {
+    let data: Aliased = serde_json::from_str(
+        r#"{"integer": 10, "previous": 1.5, "string": "hello", "after": true}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Aliased {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+}
+
+fn default_before() -> f32 {
+    -1.0
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct DefaultPath {
+    #[serde(default = "default_before")]
+    before: f32,
+
+    after: bool,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn default_path() {
+    let data: DefaultPath =
+        serde_json::from_str(r#"{"integer": 10, "string": "hello", "after": true}"#)
+            .expect("`before` has a default");
+    assert_eq!(data.before, -1.0);
+
+    let data: DefaultPath =
+        serde_json::from_str(r#"{"integer": 10, "before": 2.5, "string": "hello", "after": true}"#)
+            .expect("failed to deserialize");
+    assert_eq!(data.before, 2.5);
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct Skipped {
+    before: f32,
+
+    #[serde(skip)]
+    cache: Option<String>,
+
+    #[serde(flatten)]
+    rest: std::collections::BTreeMap<String, serde_json::Value>,
+}
+
+/// A skipped field's key isn't captured; it goes to the flattened field
+#[test]
+fn skip() {
+    let data: Skipped = serde_json::from_str(r#"{"cache": "stale", "before": 1.5, "other": 1}"#)
+        .expect("failed to deserialize");
+
+    assert_eq!(data.before, 1.5);
+    assert_eq!(data.cache, None);
+    assert_eq!(
+        data.rest,
+        std::collections::BTreeMap::from([
+            ("cache".to_owned(), serde_json::json!("stale")),
+            ("other".to_owned(), serde_json::json!(1)),
+        ])
+    );
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+#[serde(rename_all = "camelCase")]
+struct Renamed {
+    created_at: u64,
+
+    #[serde(alias = "modified_at")]
+    updated_at: Option<u64>,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+/// `rename_all` applies to the sibling keys, but not to aliases, and not to
+/// the flattened field's keys
+#[test]
+fn rename_all() {
+    let data: Renamed = serde_json::from_str(
+        r#"{"createdAt": 1, "modified_at": 2, "integer": 10, "string": "hello"}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data.created_at, 1);
+    assert_eq!(data.updated_at, Some(2));
+
+    let err = serde_json::from_str::<Renamed>(r#"{"integer": 10, "string": "hello"}"#)
+        .expect_err("`createdAt` is required");
+    assert!(
+        err.to_string().starts_with("missing field `createdAt`"),
+        "{}",
+        err
+    );
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct Escaped {
+    #[serde(alias = "café", alias = "\"quoted\"")]
+    r#type: u32,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+/// Raw identifiers lose their `r#`, and aliases may be any string
+#[test]
+fn escaped_keys() {
+    for input in [
+        r#"{"type": 1, "integer": 10, "string": "hello"}"#,
+        r#"{"café": 1, "integer": 10, "string": "hello"}"#,
+        r#"{"\"quoted\"": 1, "integer": 10, "string": "hello"}"#,
+    ] {
+        let data: Escaped = serde_json::from_str(input).expect(input);
+        assert_eq!(data.r#type, 1);
+    }
+}
+
+fn make_cache() -> Option<String> {
+    Some("fresh".to_owned())
+}
+
+#[derive(Debug, PartialEq, BufferlessDeserialize)]
+struct SkippedWithPath {
+    before: f32,
+
+    #[serde(skip, default = "make_cache")]
+    cache: Option<String>,
+
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+/// A skipped field with a default path is filled by calling it
+#[test]
+fn skip_default_path() {
+    let data: SkippedWithPath =
+        serde_json::from_str(r#"{"before": 1.5, "integer": 10, "string": "hello"}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(data.cache.as_deref(), Some("fresh"));
+}
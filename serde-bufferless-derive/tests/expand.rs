@@ -0,0 +1,8 @@
+//! Expansion tests: each file in `tests/expand` is expanded with
+//! `cargo expand` and compared against its `.expanded.rs`. These need
+//! `cargo install cargo-expand`; delete an `.expanded.rs` to regenerate it.
+
+#[test]
+fn expand() {
+    macrotest::expand("tests/expand/*.rs");
+}
@@ -0,0 +1,12 @@
+use std::collections::BTreeMap;
+
+use serde_bufferless_derive::bufferless_flatten;
+
+#[bufferless_flatten]
+struct Outer {
+    before: f32,
+    #[serde(default)]
+    after: bool,
+    #[serde(flatten)]
+    rest: BTreeMap<String, i32>,
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+use serde_bufferless_derive::BufferlessDeserialize;
+struct Outer {
+    #[serde(alias = "prior")]
+    before: Option<f32>,
+    #[serde(skip)]
+    cache: u32,
+    #[serde(flatten)]
+    rest: BTreeMap<String, i32>,
+}
+impl<'de> ::serde::Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>
+    where
+        D: ::serde::Deserializer<'de>,
+    {
+        #[allow(non_camel_case_types)]
+        enum Field {
+            before,
+        }
+        struct Capture {
+            before: ::core::option::Option<Option<f32>>,
+        }
+        impl<'de> ::serde_bufferless::private::flatten::KeyCapture<'de>
+        for &mut Capture {
+            type Token = Field;
+            #[inline]
+            fn try_send_key(
+                &mut self,
+                key: &[u8],
+            ) -> ::core::option::Option<Self::Token> {
+                match key {
+                    b"before" | b"prior" => ::core::option::Option::Some(Field::before),
+                    _ => ::core::option::Option::None,
+                }
+            }
+            #[inline]
+            fn send_value<D>(
+                &mut self,
+                field: Self::Token,
+                value: D,
+            ) -> ::core::result::Result<(), D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                match field {
+                    Field::before => {
+                        if self.before.is_some() {
+                            return ::core::result::Result::Err(
+                                ::serde::de::Error::duplicate_field("before"),
+                            );
+                        }
+                        self.before = ::core::option::Option::Some(
+                            ::serde::Deserialize::deserialize(value)?,
+                        );
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+            fn expecting(
+                &self,
+                formatter: &mut ::core::fmt::Formatter,
+            ) -> ::core::fmt::Result {
+                formatter.write_str("struct Outer")
+            }
+            fn fields(&self) -> &'static [&'static str] {
+                &["before"]
+            }
+        }
+        let mut capture = Capture {
+            before: ::core::option::Option::None,
+        };
+        let rest: BTreeMap<String, i32> = ::serde::Deserialize::deserialize(
+            ::serde_bufferless::private::flatten::FlattenDeserializer::new(
+                deserializer,
+                &mut capture,
+            ),
+        )?;
+        let before = match capture.before {
+            ::core::option::Option::Some(before) => before,
+            ::core::option::Option::None => {
+                ::serde_bufferless::private::missing_field::<_, D::Error>("before")?
+            }
+        };
+        let cache: u32 = ::core::default::Default::default();
+        ::core::result::Result::Ok(Self { before, cache, rest })
+    }
+}
@@ -0,0 +1,13 @@
+use std::collections::BTreeMap;
+
+use serde_bufferless_derive::BufferlessDeserialize;
+
+#[derive(BufferlessDeserialize)]
+struct Outer {
+    #[serde(alias = "prior")]
+    before: Option<f32>,
+    #[serde(skip)]
+    cache: u32,
+    #[serde(flatten)]
+    rest: BTreeMap<String, i32>,
+}
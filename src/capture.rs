@@ -0,0 +1,69 @@
+/*!
+Reusable [`KeyCapture`] implementations and adapters. The adapters wrap
+another capture (usually a `&mut Capture` as in the generated code) and alter
+how keys are matched or how values are handled, while delegating the actual
+storage to the wrapped capture. [`RequiredSet`] checks a capture's slots once
+deserialization has finished.
+
+[`KeyCapture`]: crate::private::flatten::KeyCapture
+*/
+
+mod case_insensitive;
+mod chain;
+#[cfg(feature = "content")]
+mod content;
+#[cfg(feature = "alloc")]
+mod duplicate;
+#[cfg(feature = "content")]
+mod dynamic;
+#[cfg(feature = "erased")]
+mod erased;
+mod ignore;
+mod null;
+#[cfg(feature = "content")]
+mod ordered;
+#[cfg(feature = "erased")]
+mod patch;
+#[cfg(feature = "std")]
+mod prefix_map;
+mod prefix_strip;
+mod record_keys;
+mod required;
+#[cfg(feature = "alloc")]
+mod seeded_map;
+#[cfg(feature = "erased")]
+mod struct_capture;
+#[cfg(feature = "content")]
+mod tee;
+mod trace;
+mod validated;
+
+pub use self::case_insensitive::CaseInsensitive;
+pub use self::chain::{ChainCapture, ChainToken};
+#[cfg(feature = "content")]
+pub use self::content::ContentCapture;
+#[cfg(feature = "alloc")]
+pub use self::duplicate::{DuplicateReject, MergeMode};
+#[cfg(feature = "content")]
+pub use self::dynamic::DynamicCapture;
+#[cfg(feature = "erased")]
+pub use self::erased::ErasedKeyCapture;
+pub use self::ignore::IgnoreCapture;
+pub use self::null::NullCapture;
+#[cfg(feature = "content")]
+pub use self::ordered::OrderedCapture;
+#[cfg(feature = "erased")]
+pub use self::patch::{PatchCapture, PatchSetter};
+#[cfg(feature = "std")]
+pub use self::prefix_map::PrefixMapCapture;
+pub use self::prefix_strip::PrefixStrip;
+pub use self::record_keys::RecordKeys;
+pub use self::required::RequiredSet;
+#[cfg(feature = "alloc")]
+pub use self::seeded_map::SeededMapCapture;
+#[cfg(feature = "erased")]
+pub use self::struct_capture::StructCapture;
+#[cfg(feature = "content")]
+pub use self::tee::{TeeCapture, TeeToken};
+pub use self::trace::{OfferedKey, Trace, TracingCapture};
+pub use self::validated::Validated;
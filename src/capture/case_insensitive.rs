@@ -0,0 +1,157 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// Keys that aren't among the wrapped capture's fields, and are up to this
+/// length, are lowercased on the stack
+const BUFFER_LEN: usize = 64;
+
+/// A [`CaseInsensitive`] wraps a [`KeyCapture`] so that it accepts its keys
+/// in any case: `"Before"` or `"BEFORE"` as well as `"before"`. Rejected keys
+/// are forwarded to the inner type exactly as they appeared in the input.
+///
+/// Each key is compared against the wrapped capture's
+/// [`fields`][KeyCapture::fields] without regard to case, and if one of them
+/// matches, its name is offered in the key's place. This compares the key in
+/// place, a byte or a `char` at a time, so it never allocates, whatever the
+/// key's length. ASCII keys are compared with
+/// [`eq_ignore_ascii_case`][slice::eq_ignore_ascii_case], and other UTF-8
+/// keys by their lowercase `char`s.
+///
+/// A key that matches none of the fields, such as an alias, is lowercased
+/// instead, so that the wrapped capture can match it with a lowercase-only
+/// arm. This happens in a buffer on the stack, which only holds ASCII keys of
+/// up to 64 bytes; longer keys, and keys that aren't ASCII, are offered
+/// unchanged.
+pub struct CaseInsensitive<C> {
+    capture: C,
+}
+
+impl<C> CaseInsensitive<C> {
+    #[inline]
+    #[must_use]
+    pub fn new(capture: C) -> Self {
+        Self { capture }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+/// Compare a key to a field name without regard to case
+fn eq_ignore_case(key: &[u8], field: &str) -> bool {
+    if key.is_ascii() {
+        return key.eq_ignore_ascii_case(field.as_bytes());
+    }
+
+    match core::str::from_utf8(key) {
+        Ok(key) => key
+            .chars()
+            .flat_map(char::to_lowercase)
+            .eq(field.chars().flat_map(char::to_lowercase)),
+        Err(_) => false,
+    }
+}
+
+impl<'de, C> KeyCapture<'de> for CaseInsensitive<C>
+where
+    C: KeyCapture<'de>,
+{
+    type Token = C::Token;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        if let Some(field) = self
+            .capture
+            .fields()
+            .iter()
+            .find(|field| eq_ignore_case(key, field))
+        {
+            return self.capture.try_send_key(field.as_bytes());
+        }
+
+        match key.len() {
+            len if len <= BUFFER_LEN
+                && key.is_ascii()
+                && key.iter().any(u8::is_ascii_uppercase) =>
+            {
+                let mut buffer = [0; BUFFER_LEN];
+                let buffer = &mut buffer[..len];
+                buffer.copy_from_slice(key);
+                buffer.make_ascii_lowercase();
+                self.capture.try_send_key(buffer)
+            }
+            _ => self.capture.try_send_key(key),
+        }
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.capture.try_send_index(index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.capture.try_send_bool(key)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.capture.try_send_int(key)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.capture.try_send_float(key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token, value)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.capture.fields()
+    }
+
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        self.capture.merged_fields(inner)
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index()
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.capture.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.capture.finish()
+    }
+}
@@ -0,0 +1,187 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::{merge_fields, KeyCapture};
+
+/// A [`ChainCapture`] combines two captures, offering each key to `A` first
+/// and then to `B`. This makes a struct with two `#[serde(flatten)]` fields
+/// tractable without buffering, as long as their keys are disjoint: one
+/// flattened struct's fields are captured by `B` (alongside the outer
+/// struct's own fields in `A`), and the other is the inner type of the
+/// [`FlattenDeserializer`].
+///
+/// If both captures accept the same key, `A` wins, and `B` never sees it.
+/// Index and other non-string keys are offered in the same order, so two
+/// captures that both use field indices will collide on every index that `A`
+/// accepts.
+///
+/// [`FlattenDeserializer`]: crate::private::flatten::FlattenDeserializer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChainCapture<A, B> {
+    first: A,
+    second: B,
+    fields: Option<&'static [&'static str]>,
+}
+
+/// The token of a [`ChainCapture`], recording which capture accepted the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChainToken<A, B> {
+    First(A),
+    Second(B),
+}
+
+impl<A, B> ChainCapture<A, B> {
+    #[inline]
+    #[must_use]
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            fields: None,
+        }
+    }
+
+    /// Provide the field names of both captures together, in the order
+    /// their `try_send_index` numbers them, to be returned from
+    /// [`fields`][KeyCapture::fields]. This is required when both captures
+    /// have fields, since a `'static` list can't be built from them at
+    /// runtime; debug builds panic without it.
+    #[inline]
+    #[must_use]
+    pub fn with_fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = Some(fields);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    #[inline]
+    fn try_send<'de, T>(
+        &mut self,
+        key: T,
+        mut try_send_a: impl FnMut(&mut A, T) -> Option<A::Token>,
+        mut try_send_b: impl FnMut(&mut B, T) -> Option<B::Token>,
+    ) -> Option<ChainToken<A::Token, B::Token>>
+    where
+        A: KeyCapture<'de>,
+        B: KeyCapture<'de>,
+        T: Copy,
+    {
+        match try_send_a(&mut self.first, key) {
+            Some(token) => Some(ChainToken::First(token)),
+            None => try_send_b(&mut self.second, key).map(ChainToken::Second),
+        }
+    }
+}
+
+impl<'de, A, B> KeyCapture<'de> for ChainCapture<A, B>
+where
+    A: KeyCapture<'de>,
+    B: KeyCapture<'de>,
+{
+    type Token = ChainToken<A::Token, B::Token>;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_key, B::try_send_key)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.try_send(index, A::try_send_index, B::try_send_index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_bool, B::try_send_bool)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_int, B::try_send_int)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_float, B::try_send_float)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match token {
+            ChainToken::First(token) => self.first.send_value(token, value),
+            ChainToken::Second(token) => self.second.send_value(token, value),
+        }
+    }
+
+    /// The outer struct is described by the first capture
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.first.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        combined_fields(self.fields, self.first.fields(), self.second.fields())
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        match token {
+            ChainToken::First(token) => self.first.field_name(token),
+            ChainToken::Second(token) => self.second.field_name(token),
+        }
+    }
+
+    /// Complete once both captures are
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.first.is_complete() && self.second.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.first.finish()?;
+        self.second.finish()
+    }
+}
+
+/// The fields of two combined captures, shared by [`ChainCapture`] and
+/// [`TeeCapture`][super::TeeCapture]: the list given to `with_fields`, or
+/// else whichever of the two captures' lists isn't empty.
+///
+/// A `'static` list can't be built from two non-empty lists at runtime, so
+/// if both captures have fields, `with_fields` must provide them, in the
+/// order the captures' `try_send_index` numbers them. Otherwise, the second
+/// capture's fields would silently vanish from `deserialize_struct`, from
+/// unknown-field errors, and from field indices, so this panics in debug
+/// builds, and only returns the first capture's fields in release builds.
+pub(super) fn combined_fields(
+    fields: Option<&'static [&'static str]>,
+    first: &'static [&'static str],
+    second: &'static [&'static str],
+) -> &'static [&'static str] {
+    fields
+        .or_else(|| merge_fields(first, second))
+        .unwrap_or_else(|| {
+            if cfg!(debug_assertions) {
+                panic!(
+                    "both captures have fields ({first:?} and {second:?}), \
+                    so they must be combined with `with_fields`"
+                );
+            }
+
+            first
+        })
+}
@@ -0,0 +1,84 @@
+use core::{fmt, mem};
+
+use serde::de::{self, Deserialize};
+use serde_value::Value;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`ContentCapture`] accepts a fixed set of fields, but defers deciding
+/// what type their values have: each value is stored as a
+/// [`serde_value::Value`], to be inspected once deserialization has finished
+/// and then deserialized into its final type with
+/// [`Value::deserialize_into`].
+///
+/// Values are collected in the order they appear in the input, paired with
+/// the name of the field they were captured for, and can be taken with
+/// [`take_values`][Self::take_values]. A field that appears more than once
+/// is collected each time.
+///
+/// As with the generated captures, [`KeyCapture`] is implemented for
+/// `&mut ContentCapture`.
+#[derive(Debug, Clone)]
+pub struct ContentCapture {
+    fields: &'static [&'static str],
+    values: Vec<(&'static str, Value)>,
+}
+
+impl ContentCapture {
+    #[inline]
+    #[must_use]
+    pub fn new(fields: &'static [&'static str]) -> Self {
+        Self {
+            fields,
+            values: Vec::new(),
+        }
+    }
+
+    /// The values collected so far
+    #[inline]
+    #[must_use]
+    pub fn values(&self) -> &[(&'static str, Value)] {
+        &self.values
+    }
+
+    /// Take the values collected so far, leaving the capture empty
+    #[inline]
+    pub fn take_values(&mut self) -> Vec<(&'static str, Value)> {
+        mem::take(&mut self.values)
+    }
+}
+
+impl<'de> KeyCapture<'de> for &mut ContentCapture {
+    type Token = &'static str;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields
+            .iter()
+            .copied()
+            .find(|field| field.as_bytes() == key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.values.push((token, Value::deserialize(value)?));
+        Ok(())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        Some(token)
+    }
+}
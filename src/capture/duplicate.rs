@@ -0,0 +1,214 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+
+use serde::de::{self, Deserialize};
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`DuplicateReject`] wraps a [`KeyCapture`] and rejects any field that
+/// appears more than once in the input with `de::Error::duplicate_field`,
+/// rather than letting the second value silently overwrite the first.
+///
+/// It remembers every token the wrapped capture has accepted, so the token
+/// `T` must be `PartialEq` and `Clone`; in practice tokens are fieldless
+/// enums. The field is named in the error by matching the key against the
+/// capture's `fields`; a key that doesn't appear there (such as an alias) is
+/// reported with `de::Error::custom` and the same message.
+///
+/// Rejecting is the default [`MergeMode`]; [`with_mode`][Self::with_mode]
+/// keeps the first or the last value instead, which is useful when a field
+/// may appear under more than one of its aliases.
+///
+/// The wrapped capture's [`is_complete`][KeyCapture::is_complete] isn't
+/// forwarded, since every key has to be seen to find the repeated ones.
+pub struct DuplicateReject<C, T> {
+    capture: C,
+    mode: MergeMode,
+    seen: Vec<T>,
+    duplicate: Option<Duplicate>,
+}
+
+/// What a [`DuplicateReject`] does when a field appears more than once,
+/// whether under the same key or under different aliases of the same token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeMode {
+    /// Keep the first value. Later values are still deserialized, as
+    /// [`IgnoredAny`][de::IgnoredAny], to consume them, but they aren't
+    /// sent to the wrapped capture.
+    FirstWins,
+
+    /// Send every value to the wrapped capture, so that (for captures that
+    /// store values in slots) the last one overwrites the others
+    LastWins,
+
+    /// Reject the repeated field with a `duplicate_field` error. This is the
+    /// default.
+    #[default]
+    Error,
+}
+
+/// What to do with the value of a field that was seen twice, decided when
+/// its key was sent and done when its value is
+enum Duplicate {
+    /// Consume the value without sending it to the capture
+    Skip,
+
+    /// Reject the value, naming the field
+    Field(&'static str),
+
+    /// Reject the value, naming the key, for keys that aren't in `fields`
+    Key(String),
+}
+
+impl<C, T> DuplicateReject<C, T> {
+    #[inline]
+    #[must_use]
+    pub fn new(capture: C) -> Self {
+        Self::with_mode(capture, MergeMode::Error)
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn with_mode(capture: C, mode: MergeMode) -> Self {
+        Self {
+            capture,
+            mode,
+            seen: Vec::new(),
+            duplicate: None,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+impl<C, T> DuplicateReject<C, T>
+where
+    T: PartialEq + Clone,
+{
+    /// Record an accepted token, deciding what to do with its value if it
+    /// has been seen before. `name` names the field for the error.
+    fn check(&mut self, token: Option<T>, name: impl FnOnce() -> Duplicate) -> Option<T> {
+        let token = token?;
+
+        if self.mode == MergeMode::LastWins {
+            return Some(token);
+        }
+
+        if self.seen.contains(&token) {
+            self.duplicate = Some(match self.mode {
+                MergeMode::FirstWins => Duplicate::Skip,
+                _ => name(),
+            });
+        } else {
+            self.seen.push(token.clone());
+        }
+
+        // Accept the key either way, so that the value is consumed (or the
+        // error is raised) by `send_value`
+        Some(token)
+    }
+}
+
+impl<'de, C, T> KeyCapture<'de> for DuplicateReject<C, T>
+where
+    C: KeyCapture<'de, Token = T>,
+    T: PartialEq + Clone,
+{
+    type Token = T;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<T> {
+        let token = self.capture.try_send_key(key);
+        let fields = self.capture.fields();
+
+        self.check(token, || {
+            match fields.iter().find(|field| field.as_bytes() == key) {
+                Some(field) => Duplicate::Field(field),
+                None => Duplicate::Key(String::from_utf8_lossy(key).into_owned()),
+            }
+        })
+    }
+
+    fn try_send_index(&mut self, index: u64) -> Option<T> {
+        let token = self.capture.try_send_index(index);
+        let fields = self.capture.fields();
+
+        self.check(token, || {
+            match usize::try_from(index)
+                .ok()
+                .and_then(|index| fields.get(index))
+            {
+                Some(field) => Duplicate::Field(field),
+                None => Duplicate::Key(index.to_string()),
+            }
+        })
+    }
+
+    fn try_send_bool(&mut self, key: bool) -> Option<T> {
+        let token = self.capture.try_send_bool(key);
+        self.check(token, || Duplicate::Key(key.to_string()))
+    }
+
+    fn try_send_int(&mut self, key: i64) -> Option<T> {
+        let token = self.capture.try_send_int(key);
+        self.check(token, || Duplicate::Key(key.to_string()))
+    }
+
+    fn try_send_float(&mut self, key: f64) -> Option<T> {
+        let token = self.capture.try_send_float(key);
+        self.check(token, || Duplicate::Key(key.to_string()))
+    }
+
+    fn send_value<D>(&mut self, token: T, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match self.duplicate.take() {
+            None => self.capture.send_value(token, value),
+            Some(Duplicate::Skip) => de::IgnoredAny::deserialize(value).map(|_| ()),
+            Some(Duplicate::Field(field)) => Err(de::Error::duplicate_field(field)),
+            Some(Duplicate::Key(key)) => {
+                Err(de::Error::custom(format_args!("duplicate field `{key}`")))
+            }
+        }
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.capture.fields()
+    }
+
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        self.capture.merged_fields(inner)
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.capture.finish()
+    }
+}
@@ -0,0 +1,108 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use serde::de::{self, Deserialize};
+use serde_value::Value;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`DynamicCapture`] captures fields whose names are only known at
+/// runtime, such as fields from a schema loaded from configuration.
+///
+/// Each field is registered with [`field`][Self::field], which assigns it a
+/// slot index. During deserialization, a key matching a registered field is
+/// accepted with that index as its token, and its value is stored in the slot
+/// as a [`serde_value::Value`], since there's no concrete type to
+/// deserialize it into. Afterwards, the slots are read back by index, and
+/// each value can be deserialized into its final type with
+/// [`Value::deserialize_into`].
+///
+/// As with the generated captures, [`KeyCapture`] is implemented for
+/// `&mut DynamicCapture`, so that the capture can still be read once the
+/// [`FlattenDeserializer`] is done with it.
+///
+/// [`FlattenDeserializer`]: crate::private::flatten::FlattenDeserializer
+#[derive(Debug, Clone, Default)]
+pub struct DynamicCapture {
+    fields: HashMap<String, usize>,
+    slots: Vec<Option<Value>>,
+}
+
+impl DynamicCapture {
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a field, returning the index of its slot. Registering the
+    /// same name again returns the slot it already has.
+    pub fn field(&mut self, name: impl Into<String>) -> usize {
+        let next = self.slots.len();
+        let index = *self.fields.entry(name.into()).or_insert(next);
+
+        if index == next {
+            self.slots.push(None);
+        }
+
+        index
+    }
+
+    /// The slot index of a registered field
+    #[inline]
+    #[must_use]
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.fields.get(name).copied()
+    }
+
+    /// The value captured for the field at `index`, if it was present
+    #[inline]
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.slots.get(index)?.as_ref()
+    }
+
+    /// Take the value captured for the field at `index`, leaving its slot
+    /// empty
+    #[inline]
+    pub fn take(&mut self, index: usize) -> Option<Value> {
+        self.slots.get_mut(index)?.take()
+    }
+
+    /// The number of registered fields
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl<'de> KeyCapture<'de> for &mut DynamicCapture {
+    type Token = usize;
+
+    /// Field names are strings, so keys that aren't UTF-8 are always
+    /// rejected
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.index_of(core::str::from_utf8(key).ok()?)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.slots[token] = Some(Value::deserialize(value)?);
+        Ok(())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+}
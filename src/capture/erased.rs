@@ -0,0 +1,227 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// An object-safe version of [`KeyCapture`], for when the capture is only
+/// known at runtime. `send_value` is generic over its deserializer, which
+/// makes [`KeyCapture`] unusable as a trait object; this version takes an
+/// [`erased_serde::Deserializer`] instead.
+///
+/// Every [`KeyCapture`] is an [`ErasedKeyCapture`], and boxed or borrowed
+/// `dyn ErasedKeyCapture` trait objects are [`KeyCapture`]s, so a
+/// runtime-selected capture can be passed straight to a
+/// [`FlattenDeserializer`]. The token type isn't erased, so captures that are
+/// chosen between at runtime need to agree on one (a `usize` field index, for
+/// instance).
+///
+/// Errors from the concrete capture pass through `erased_serde::Error`, and
+/// so reach the original deserializer as `de::Error::custom` with the same
+/// message.
+///
+/// [`FlattenDeserializer`]: crate::private::flatten::FlattenDeserializer
+pub trait ErasedKeyCapture<'de> {
+    type Token;
+
+    fn erased_try_send_key(&mut self, key: &[u8]) -> Option<Self::Token>;
+
+    fn erased_try_send_index(&mut self, index: u64) -> Option<Self::Token>;
+
+    fn erased_try_send_bool(&mut self, key: bool) -> Option<Self::Token>;
+
+    fn erased_try_send_int(&mut self, key: i64) -> Option<Self::Token>;
+
+    fn erased_try_send_float(&mut self, key: f64) -> Option<Self::Token>;
+
+    fn erased_send_value(
+        &mut self,
+        token: Self::Token,
+        value: &mut dyn erased_serde::Deserializer<'de>,
+    ) -> Result<(), erased_serde::Error>;
+
+    fn erased_expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result;
+
+    fn erased_fields(&self) -> &'static [&'static str];
+
+    fn erased_merged_fields(
+        &self,
+        inner: &'static [&'static str],
+    ) -> Option<&'static [&'static str]>;
+
+    fn erased_field_name(&self, token: &Self::Token) -> Option<&'static str>;
+
+    fn erased_flatten_index(&self) -> usize;
+
+    fn erased_is_complete(&self) -> bool;
+
+    fn erased_finish(&mut self) -> Result<(), erased_serde::Error>;
+}
+
+impl<'de, C> ErasedKeyCapture<'de> for C
+where
+    C: KeyCapture<'de>,
+{
+    type Token = C::Token;
+
+    #[inline]
+    fn erased_try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.try_send_key(key)
+    }
+
+    #[inline]
+    fn erased_try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.try_send_index(index)
+    }
+
+    #[inline]
+    fn erased_try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.try_send_bool(key)
+    }
+
+    #[inline]
+    fn erased_try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.try_send_int(key)
+    }
+
+    #[inline]
+    fn erased_try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.try_send_float(key)
+    }
+
+    #[inline]
+    fn erased_send_value(
+        &mut self,
+        token: Self::Token,
+        value: &mut dyn erased_serde::Deserializer<'de>,
+    ) -> Result<(), erased_serde::Error> {
+        self.send_value(token, value)
+    }
+
+    #[inline]
+    fn erased_expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.expecting(formatter)
+    }
+
+    #[inline]
+    fn erased_fields(&self) -> &'static [&'static str] {
+        self.fields()
+    }
+
+    #[inline]
+    fn erased_merged_fields(
+        &self,
+        inner: &'static [&'static str],
+    ) -> Option<&'static [&'static str]> {
+        self.merged_fields(inner)
+    }
+
+    #[inline]
+    fn erased_field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.field_name(token)
+    }
+
+    #[inline]
+    fn erased_flatten_index(&self) -> usize {
+        self.flatten_index()
+    }
+
+    #[inline]
+    fn erased_is_complete(&self) -> bool {
+        self.is_complete()
+    }
+
+    #[inline]
+    fn erased_finish(&mut self) -> Result<(), erased_serde::Error> {
+        self.finish()
+    }
+}
+
+macro_rules! impl_key_capture_for_erased {
+    ($($ty:ty),*) => {$(
+        impl<'a, 'de, T> KeyCapture<'de> for $ty {
+            type Token = T;
+
+            #[inline]
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                (**self).erased_try_send_key(key)
+            }
+
+            #[inline]
+            fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+                (**self).erased_try_send_index(index)
+            }
+
+            #[inline]
+            fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+                (**self).erased_try_send_bool(key)
+            }
+
+            #[inline]
+            fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+                (**self).erased_try_send_int(key)
+            }
+
+            #[inline]
+            fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+                (**self).erased_try_send_float(key)
+            }
+
+            fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                let mut value = <dyn erased_serde::Deserializer>::erase(value);
+                (**self)
+                    .erased_send_value(token, &mut value)
+                    .map_err(de::Error::custom)
+            }
+
+            #[inline]
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                (**self).erased_expecting(formatter)
+            }
+
+            #[inline]
+            fn fields(&self) -> &'static [&'static str] {
+                (**self).erased_fields()
+            }
+
+            #[inline]
+            fn merged_fields(
+                &self,
+                inner: &'static [&'static str],
+            ) -> Option<&'static [&'static str]> {
+                (**self).erased_merged_fields(inner)
+            }
+
+            #[inline]
+            fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+                (**self).erased_field_name(token)
+            }
+
+            #[inline]
+            fn flatten_index(&self) -> usize {
+                (**self).erased_flatten_index()
+            }
+
+            #[inline]
+            fn is_complete(&self) -> bool {
+                (**self).erased_is_complete()
+            }
+
+            #[inline]
+            fn finish<E>(&mut self) -> Result<(), E>
+            where
+                E: de::Error,
+            {
+                (**self).erased_finish().map_err(de::Error::custom)
+            }
+        }
+    )*};
+}
+
+impl_key_capture_for_erased! {
+    Box<dyn ErasedKeyCapture<'de, Token = T> + 'a>,
+    &mut (dyn ErasedKeyCapture<'de, Token = T> + 'a)
+}
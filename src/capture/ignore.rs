@@ -0,0 +1,71 @@
+use core::fmt;
+
+use serde::de::{self, Deserialize};
+
+use crate::private::flatten::KeyCapture;
+
+/// An [`IgnoreCapture`] accepts a fixed set of keys and discards their
+/// values, deserializing them into [`IgnoredAny`][de::IgnoredAny]. The keys
+/// are consumed without being stored or forwarded to the inner type, so an
+/// inner type with `#[serde(deny_unknown_fields)]` doesn't reject them.
+///
+/// On its own it only hides keys; as the second capture of a
+/// [`ChainCapture`], behind the outer struct's own capture, it gives
+/// known-but-ignored fields, such as deprecated ones that are still accepted
+/// in the input:
+///
+/// ```ignore
+/// let capture = ChainCapture::new(&mut capture, IgnoreCapture::new(&["deprecated", "legacy"]))
+///     .with_fields(&["before", "after", "deprecated", "legacy"]);
+/// ```
+///
+/// The ignored keys are reported by [`fields`][KeyCapture::fields], so
+/// formats that check keys against the declared fields accept them too.
+/// Alongside another capture with fields, that means the [`ChainCapture`]
+/// needs both lists from `with_fields`.
+///
+/// [`ChainCapture`]: super::ChainCapture
+#[derive(Debug, Clone, Copy)]
+pub struct IgnoreCapture {
+    fields: &'static [&'static str],
+}
+
+impl IgnoreCapture {
+    #[inline]
+    #[must_use]
+    pub fn new(fields: &'static [&'static str]) -> Self {
+        Self { fields }
+    }
+}
+
+impl<'de> KeyCapture<'de> for IgnoreCapture {
+    type Token = usize;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields.iter().position(|field| field.as_bytes() == key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, _token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::IgnoredAny::deserialize(value).map(|_| ())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.fields.get(*token).copied()
+    }
+}
@@ -0,0 +1,39 @@
+use core::{convert::Infallible, fmt};
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`NullCapture`] captures nothing: it rejects every key, so every entry
+/// is forwarded to the inner type, and a [`FlattenDeserializer`] using it is
+/// a pure pass-through. That makes it a baseline for measuring the overhead
+/// of the flatten machinery itself, and a stand-in capture for types with no
+/// sibling fields.
+///
+/// Its token is [`Infallible`], so `send_value` can never be called.
+///
+/// [`FlattenDeserializer`]: crate::private::flatten::FlattenDeserializer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NullCapture;
+
+impl<'de> KeyCapture<'de> for NullCapture {
+    type Token = Infallible;
+
+    #[inline]
+    fn try_send_key(&mut self, _key: &[u8]) -> Option<Self::Token> {
+        None
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, _value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match token {}
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+}
@@ -0,0 +1,89 @@
+use core::{fmt, mem};
+
+use serde::de::{self, Deserialize};
+use serde_value::Value;
+
+use crate::private::flatten::KeyCapture;
+
+/// An [`OrderedCapture`] records the fields it accepts as
+/// `(field_index, value)` pairs, in the order they appear in the input
+/// rather than the order the fields are declared. The index is the field's
+/// position in the table given to [`new`][Self::new], and each value is kept
+/// as a [`serde_value::Value`] until the caller reconstructs its struct.
+///
+/// This is for callers that need to reproduce the input's layout, such as
+/// formatters and linters. It's much like [`ContentCapture`], but with
+/// indexes instead of names, so the fields can be matched on without string
+/// comparisons and recorded orderings can be compared between inputs.
+///
+/// As with the generated captures, [`KeyCapture`] is implemented for
+/// `&mut OrderedCapture`.
+///
+/// [`ContentCapture`]: super::ContentCapture
+#[derive(Debug, Clone)]
+pub struct OrderedCapture {
+    fields: &'static [&'static str],
+    entries: Vec<(usize, Value)>,
+}
+
+impl OrderedCapture {
+    #[inline]
+    #[must_use]
+    pub fn new(fields: &'static [&'static str]) -> Self {
+        Self {
+            fields,
+            entries: Vec::new(),
+        }
+    }
+
+    /// The entries recorded so far, in input order
+    #[inline]
+    #[must_use]
+    pub fn entries(&self) -> &[(usize, Value)] {
+        &self.entries
+    }
+
+    /// The indexes of the fields recorded so far, in input order
+    #[inline]
+    pub fn order(&self) -> impl Iterator<Item = usize> + '_ {
+        self.entries.iter().map(|&(index, _)| index)
+    }
+
+    /// Take the entries recorded so far, leaving the capture empty
+    #[inline]
+    pub fn take_entries(&mut self) -> Vec<(usize, Value)> {
+        mem::take(&mut self.entries)
+    }
+}
+
+impl<'de> KeyCapture<'de> for &mut OrderedCapture {
+    type Token = usize;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields.iter().position(|field| field.as_bytes() == key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.entries.push((token, Value::deserialize(value)?));
+        Ok(())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.fields.get(*token).copied()
+    }
+}
@@ -0,0 +1,90 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// Writes a field's new value into the target of a [`PatchCapture`]
+pub type PatchSetter<T> = for<'de> fn(
+    &mut T,
+    &mut dyn erased_serde::Deserializer<'de>,
+) -> Result<(), erased_serde::Error>;
+
+/// A [`PatchCapture`] writes captured values straight into an existing
+/// struct, rather than into `Option` slots, through a table of setters
+/// paired with field names. A key that's in the table has its value handed
+/// to the matching setter along with the target; a field whose key is
+/// absent is left as it was. This gives partial-update semantics, as in a
+/// JSON merge patch ([RFC 7386]) applied to a flattened object:
+///
+/// ```ignore
+/// const SETTERS: &[(&str, PatchSetter<Config>)] = &[
+///     ("port", |config, value| {
+///         config.port = erased_serde::deserialize(value)?;
+///         Ok(())
+///     }),
+///     // A `null` clears an optional field
+///     ("nickname", |config, value| {
+///         config.nickname = erased_serde::deserialize(value)?;
+///         Ok(())
+///     }),
+/// ];
+///
+/// let inner = Inner::deserialize(FlattenDeserializer::new(
+///     deserializer,
+///     PatchCapture::new(&mut config, SETTERS),
+/// ))?;
+/// ```
+///
+/// As with a [`StructCapture`][super::StructCapture], the setters can't be
+/// generic over the deserializer, so they're given an
+/// [`erased_serde::Deserializer`], and errors reach the original deserializer
+/// through `de::Error::custom`. The table is searched linearly, and the
+/// first match wins. The field names aren't reported by
+/// [`fields`][KeyCapture::fields], since a `&'static` list can't be
+/// borrowed from the table; provide them with
+/// [`FlattenBuilder::fields`][crate::private::flatten::FlattenBuilder::fields]
+/// if the format needs them.
+///
+/// [RFC 7386]: https://www.rfc-editor.org/rfc/rfc7386
+pub struct PatchCapture<'a, T> {
+    target: &'a mut T,
+    setters: &'a [(&'static str, PatchSetter<T>)],
+}
+
+impl<'a, T> PatchCapture<'a, T> {
+    #[inline]
+    #[must_use]
+    pub fn new(target: &'a mut T, setters: &'a [(&'static str, PatchSetter<T>)]) -> Self {
+        Self { target, setters }
+    }
+}
+
+impl<'de, T> KeyCapture<'de> for PatchCapture<'_, T> {
+    type Token = usize;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.setters
+            .iter()
+            .position(|&(field, _)| field.as_bytes() == key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut value = <dyn erased_serde::Deserializer>::erase(value);
+        (self.setters[token].1)(self.target, &mut value).map_err(de::Error::custom)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.setters.get(*token).map(|&(field, _)| field)
+    }
+}
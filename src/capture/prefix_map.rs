@@ -0,0 +1,54 @@
+use core::fmt;
+use std::collections::HashMap;
+
+use serde::de::{self, Deserialize};
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`PrefixMapCapture`] collects every string key that starts with a
+/// prefix into a `HashMap`, with the prefix stripped, and leaves all other
+/// keys for the inner type. This flattens a namespaced subset of the input,
+/// such as every `meta_*` key, next to a type that takes the rest.
+///
+/// The token is the stripped key, so the map's keys are owned `String`s;
+/// keys that aren't UTF-8 are left for the inner type. A key that appears
+/// more than once replaces the earlier value, as it would in a `HashMap`
+/// deserialized on its own.
+pub struct PrefixMapCapture<'a, T> {
+    prefix: &'a str,
+    map: &'a mut HashMap<String, T>,
+}
+
+impl<'a, T> PrefixMapCapture<'a, T> {
+    #[inline]
+    #[must_use]
+    pub fn new(prefix: &'a str, map: &'a mut HashMap<String, T>) -> Self {
+        Self { prefix, map }
+    }
+}
+
+impl<'de, T> KeyCapture<'de> for PrefixMapCapture<'_, T>
+where
+    T: Deserialize<'de>,
+{
+    type Token = String;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        let key = key.strip_prefix(self.prefix.as_bytes())?;
+        core::str::from_utf8(key).ok().map(String::from)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = T::deserialize(value)?;
+        self.map.insert(token, value);
+        Ok(())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+}
@@ -0,0 +1,130 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`PrefixStrip`] wraps a [`KeyCapture`] whose fields all appear in the
+/// input with a common prefix, such as `meta_created` and `meta_author`. The
+/// prefix is stripped from each string key before it's offered to the
+/// wrapped capture, so the wrapped capture can match the unprefixed names.
+///
+/// Keys that don't start with the prefix aren't offered to the wrapped
+/// capture at all, and every key the wrapped capture rejects is forwarded to
+/// the inner type exactly as it appeared in the input, prefix included.
+/// Integer, boolean, and float keys are offered to the wrapped capture
+/// unchanged.
+///
+/// The wrapped capture's [`fields`][KeyCapture::fields] are its unprefixed
+/// names, which never appear in the input, so they aren't reported; provide
+/// the prefixed names with [`with_fields`][Self::with_fields] instead.
+pub struct PrefixStrip<'p, C> {
+    prefix: &'p [u8],
+    capture: C,
+    fields: &'static [&'static str],
+}
+
+impl<'p, C> PrefixStrip<'p, C> {
+    #[inline]
+    #[must_use]
+    pub fn new(prefix: &'p str, capture: C) -> Self {
+        Self {
+            prefix: prefix.as_bytes(),
+            capture,
+            fields: &[],
+        }
+    }
+
+    /// Provide the wrapped capture's field names as they appear in the
+    /// input, prefix included and in the same order, to be returned from
+    /// [`fields`][KeyCapture::fields]. A `'static` list can't be built by
+    /// prefixing the wrapped capture's names at runtime, so without this, no
+    /// fields are reported.
+    #[inline]
+    #[must_use]
+    pub fn with_fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+impl<'de, C> KeyCapture<'de> for PrefixStrip<'_, C>
+where
+    C: KeyCapture<'de>,
+{
+    type Token = C::Token;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        key.strip_prefix(self.prefix)
+            .and_then(|key| self.capture.try_send_key(key))
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.capture.try_send_index(index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.capture.try_send_bool(key)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.capture.try_send_int(key)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.capture.try_send_float(key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token, value)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    /// The prefixed names given to [`with_fields`][Self::with_fields], if any
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index().min(self.fields.len())
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.capture.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.capture.finish()
+    }
+}
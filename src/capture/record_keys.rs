@@ -0,0 +1,120 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`RecordKeys`] wraps a [`KeyCapture`] and reports the exact key bytes
+/// that each accepted token was matched from.
+///
+/// Adapters that normalize keys before matching them (case folding,
+/// renaming) discard the original spelling, so by the time `send_value` runs
+/// there's no way to know which key triggered it. Placed outside of every
+/// such adapter, [`RecordKeys`] sees the key as it appeared in the input:
+/// whenever the wrapped capture accepts a key, `record` is called with the
+/// original bytes and the token, so that the key can be stored alongside the
+/// value the token will receive (for instance, to re-emit it with its
+/// original spelling).
+///
+/// Only keys sent with `try_send_key` have bytes to record; keys accepted by
+/// `try_send_index` and the other scalar methods are passed through without
+/// calling `record`.
+pub struct RecordKeys<C, F> {
+    capture: C,
+    record: F,
+}
+
+impl<C, F> RecordKeys<C, F> {
+    #[inline]
+    #[must_use]
+    pub fn new(capture: C, record: F) -> Self {
+        Self { capture, record }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+impl<'de, C, F> KeyCapture<'de> for RecordKeys<C, F>
+where
+    C: KeyCapture<'de>,
+    F: FnMut(&[u8], &C::Token),
+{
+    type Token = C::Token;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        let token = self.capture.try_send_key(key)?;
+        (self.record)(key, &token);
+        Some(token)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.capture.try_send_index(index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.capture.try_send_bool(key)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.capture.try_send_int(key)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.capture.try_send_float(key)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token, value)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.capture.fields()
+    }
+
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        self.capture.merged_fields(inner)
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index()
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.capture.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.capture.finish()
+    }
+}
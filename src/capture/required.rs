@@ -0,0 +1,71 @@
+use core::fmt;
+
+use serde::de;
+
+/// A [`RequiredSet`] checks all of a capture's required fields at once, once
+/// deserialization has finished, and reports every missing field in a single
+/// error rather than stopping at the first.
+///
+/// Each entry pairs a field name with whether the capture's slot for it was
+/// populated:
+///
+/// ```ignore
+/// RequiredSet::new(&[
+///     ("before", capture.before.is_some()),
+///     ("after", capture.after.is_some()),
+/// ])
+/// .check()?;
+/// ```
+///
+/// A single missing field is reported with `missing_field`, exactly as it
+/// would be without a [`RequiredSet`]; more than one is reported with
+/// `de::Error::custom`, listing each of them.
+#[derive(Debug, Clone, Copy)]
+pub struct RequiredSet<'a> {
+    fields: &'a [(&'static str, bool)],
+}
+
+impl<'a> RequiredSet<'a> {
+    #[inline]
+    #[must_use]
+    pub fn new(fields: &'a [(&'static str, bool)]) -> Self {
+        Self { fields }
+    }
+
+    /// The names of the fields that weren't populated
+    pub fn missing(&self) -> impl Iterator<Item = &'static str> + 'a {
+        self.fields
+            .iter()
+            .filter(|&&(_, present)| !present)
+            .map(|&(name, _)| name)
+    }
+
+    /// Return an error describing every missing field, if there are any
+    pub fn check<E>(&self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let mut missing = self.missing();
+
+        match (missing.next(), missing.next()) {
+            (None, _) => Ok(()),
+            (Some(name), None) => Err(E::missing_field(name)),
+            (Some(_), Some(_)) => Err(E::custom(self)),
+        }
+    }
+}
+
+impl fmt::Display for RequiredSet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("missing fields ")?;
+
+        for (i, name) in self.missing().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{name}`")?;
+        }
+
+        Ok(())
+    }
+}
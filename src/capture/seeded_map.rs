@@ -0,0 +1,61 @@
+use alloc::string::String;
+use core::fmt;
+
+use serde::de::{self, DeserializeSeed};
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`SeededMapCapture`] collects every string key into a map, deserializing
+/// each value with a fresh [`DeserializeSeed`] from `seed_factory`. The seeds
+/// can share state, such as an interner or an arena that the values are
+/// allocated into, which a plain `Deserialize` map value has no way to reach.
+///
+/// It accepts every key that's valid UTF-8, so it's usually the last capture
+/// in a [`ChainCapture`], collecting the keys that the outer struct's own
+/// capture rejected (as with a `#[serde(flatten)]` catch-all map), and used
+/// with [`deserialize_capture_only`]. Entries are added with [`Extend`] as
+/// each one is read, in input order, and nothing is buffered. Keys that
+/// aren't strings are left for the inner type.
+///
+/// [`ChainCapture`]: crate::capture::ChainCapture
+/// [`deserialize_capture_only`]: crate::private::flatten::deserialize_capture_only
+pub struct SeededMapCapture<'a, M, F> {
+    map: &'a mut M,
+    seed_factory: F,
+}
+
+impl<'a, M, F> SeededMapCapture<'a, M, F> {
+    #[inline]
+    #[must_use]
+    pub fn new(map: &'a mut M, seed_factory: F) -> Self {
+        Self { map, seed_factory }
+    }
+}
+
+impl<'de, M, F, S> KeyCapture<'de> for SeededMapCapture<'_, M, F>
+where
+    M: Extend<(String, S::Value)>,
+    F: FnMut() -> S,
+    S: DeserializeSeed<'de>,
+{
+    type Token = String;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        core::str::from_utf8(key).ok().map(String::from)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let value = (self.seed_factory)().deserialize(value)?;
+        self.map.extend(Some((token, value)));
+        Ok(())
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+}
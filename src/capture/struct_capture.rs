@@ -0,0 +1,94 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`StructCapture`] is a [`KeyCapture`] driven by a table of field names,
+/// for hand-written impls that don't want to spell out a token enum and a
+/// `try_send_key` match. A key is accepted if it's in the table, with its
+/// index as the token, and its value is handed to `send` along with that
+/// index:
+///
+/// ```ignore
+/// let mut before = None;
+/// let mut after = None;
+///
+/// let capture = StructCapture::new(&["before", "after"], |index, value| {
+///     match index {
+///         0 => before = Some(erased_serde::deserialize(value)?),
+///         _ => after = Some(erased_serde::deserialize(value)?),
+///     }
+///     Ok(())
+/// });
+/// ```
+///
+/// A closure can't be generic over the deserializer it's given, so values
+/// are passed to `send` as an [`erased_serde::Deserializer`], and errors
+/// reach the original deserializer through `de::Error::custom`, as with an
+/// [`ErasedKeyCapture`][super::ErasedKeyCapture]. The table is searched
+/// linearly, in order, so it doesn't need to be sorted; the first match
+/// wins. Fields are indexed by their position in the table, which is also
+/// used for `try_send_index`.
+pub struct StructCapture<F> {
+    fields: &'static [&'static str],
+    send: F,
+}
+
+impl<F> StructCapture<F> {
+    /// The bound on `send` is repeated here so that a closure's argument
+    /// types can be inferred from it
+    #[inline]
+    #[must_use]
+    pub fn new<'de>(fields: &'static [&'static str], send: F) -> Self
+    where
+        F: FnMut(
+            usize,
+            &mut dyn erased_serde::Deserializer<'de>,
+        ) -> Result<(), erased_serde::Error>,
+    {
+        Self { fields, send }
+    }
+}
+
+impl<'de, F> KeyCapture<'de> for StructCapture<F>
+where
+    F: FnMut(usize, &mut dyn erased_serde::Deserializer<'de>) -> Result<(), erased_serde::Error>,
+{
+    type Token = usize;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields.iter().position(|field| field.as_bytes() == key)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        usize::try_from(index)
+            .ok()
+            .filter(|&index| index < self.fields.len())
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut value = <dyn erased_serde::Deserializer>::erase(value);
+        (self.send)(token, &mut value).map_err(de::Error::custom)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.fields
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.fields.get(*token).copied()
+    }
+}
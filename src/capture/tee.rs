@@ -0,0 +1,169 @@
+use core::fmt;
+
+use serde::de::{self, Deserialize};
+use serde_value::{Value, ValueDeserializer};
+
+use super::chain::combined_fields;
+use crate::private::flatten::KeyCapture;
+
+/// A [`TeeCapture`] combines two captures that may both want the same key,
+/// such as a `"kind"` discriminator needed by the outer struct and by a
+/// flattened struct captured alongside it. Each key is offered to both
+/// captures; if only one accepts it, the value goes straight to that one, as
+/// with a [`ChainCapture`][super::ChainCapture].
+///
+/// If both accept it, the value is deserialized once into a buffered
+/// [`serde_value::Value`] and replayed into each of them. Only that one value
+/// is buffered, never the whole map, but the replayed value no longer has
+/// any of the original format's quirks: borrowed strings are copied, and
+/// formats that aren't self-describing can't be buffered at all.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TeeCapture<A, B> {
+    first: A,
+    second: B,
+    fields: Option<&'static [&'static str]>,
+}
+
+/// The token of a [`TeeCapture`], recording which captures accepted the key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TeeToken<A, B> {
+    First(A),
+    Second(B),
+    Both(A, B),
+}
+
+impl<A, B> TeeCapture<A, B> {
+    #[inline]
+    #[must_use]
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            fields: None,
+        }
+    }
+
+    /// Provide the field names of both captures together, as with
+    /// [`ChainCapture::with_fields`][super::ChainCapture::with_fields]. A key
+    /// that both captures accept should only be listed once.
+    #[inline]
+    #[must_use]
+    pub fn with_fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = Some(fields);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    #[inline]
+    fn try_send<'de, T>(
+        &mut self,
+        key: T,
+        try_send_a: impl FnOnce(&mut A, T) -> Option<A::Token>,
+        try_send_b: impl FnOnce(&mut B, T) -> Option<B::Token>,
+    ) -> Option<TeeToken<A::Token, B::Token>>
+    where
+        A: KeyCapture<'de>,
+        B: KeyCapture<'de>,
+        T: Copy,
+    {
+        match (
+            try_send_a(&mut self.first, key),
+            try_send_b(&mut self.second, key),
+        ) {
+            (Some(a), Some(b)) => Some(TeeToken::Both(a, b)),
+            (Some(a), None) => Some(TeeToken::First(a)),
+            (None, Some(b)) => Some(TeeToken::Second(b)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl<'de, A, B> KeyCapture<'de> for TeeCapture<A, B>
+where
+    A: KeyCapture<'de>,
+    B: KeyCapture<'de>,
+{
+    type Token = TeeToken<A::Token, B::Token>;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_key, B::try_send_key)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.try_send(index, A::try_send_index, B::try_send_index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_bool, B::try_send_bool)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_int, B::try_send_int)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.try_send(key, A::try_send_float, B::try_send_float)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match token {
+            TeeToken::First(token) => self.first.send_value(token, value),
+            TeeToken::Second(token) => self.second.send_value(token, value),
+            TeeToken::Both(a, b) => {
+                let value = Value::deserialize(value)?;
+
+                self.first
+                    .send_value(a, ValueDeserializer::<D::Error>::new(value.clone()))?;
+                self.second
+                    .send_value(b, ValueDeserializer::<D::Error>::new(value))
+            }
+        }
+    }
+
+    /// The outer struct is described by the first capture
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.first.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        combined_fields(self.fields, self.first.fields(), self.second.fields())
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        match token {
+            TeeToken::First(token) | TeeToken::Both(token, _) => self.first.field_name(token),
+            TeeToken::Second(token) => self.second.field_name(token),
+        }
+    }
+
+    /// Complete once both captures are
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.first.is_complete() && self.second.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.first.finish()?;
+        self.second.finish()
+    }
+}
@@ -0,0 +1,224 @@
+use core::fmt;
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`TracingCapture`] wraps a [`KeyCapture`] and reports everything that
+/// happens to it: every key it's offered and whether the wrapped capture
+/// accepted it, every value sent to it, and the final call to `finish`.
+///
+/// It's meant for diagnosing why a field isn't being captured (usually a key
+/// spelled differently than expected, or offered as an index rather than a
+/// string). Each event is passed to `trace` as a [`Trace`], whose `Display`
+/// impl gives a one-line description, so `|trace| eprintln!("{trace}")` is
+/// enough to see the whole exchange. Otherwise it's transparent: the wrapped
+/// capture's decisions and errors are passed through unchanged. Since it's a
+/// plain wrapper, there's nothing to pay for when it isn't used.
+pub struct TracingCapture<C, F> {
+    capture: C,
+    trace: F,
+}
+
+impl<C, F> TracingCapture<C, F> {
+    #[inline]
+    #[must_use]
+    pub fn new(capture: C, trace: F) -> Self {
+        Self { capture, trace }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+/// A key offered to a [`TracingCapture`], by whichever `try_send_*` method
+/// the key's representation called for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OfferedKey<'a> {
+    Key(&'a [u8]),
+    Index(u64),
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+}
+
+impl fmt::Display for OfferedKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            OfferedKey::Key(key) => match core::str::from_utf8(key) {
+                Ok(key) => write!(f, "{key:?}"),
+                Err(_) => write!(f, "bytes {key:?}"),
+            },
+            OfferedKey::Index(index) => write!(f, "index {index}"),
+            OfferedKey::Bool(key) => write!(f, "{key}"),
+            OfferedKey::Int(key) => write!(f, "{key}"),
+            OfferedKey::Float(key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// An event reported by a [`TracingCapture`]. Fields are identified by
+/// their [`field_name`][KeyCapture::field_name], when the wrapped capture
+/// has one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trace<'a> {
+    /// A key was offered, and was either accepted for `field` or rejected
+    Offered {
+        key: OfferedKey<'a>,
+        accepted: bool,
+        field: Option<&'static str>,
+    },
+
+    /// A value was sent for a previously accepted key, and was either
+    /// deserialized successfully or not
+    Value {
+        field: Option<&'static str>,
+        ok: bool,
+    },
+
+    /// The map has ended, and `finish` was called
+    Finish { ok: bool },
+}
+
+impl fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = |ok| if ok { "ok" } else { "error" };
+
+        match *self {
+            Trace::Offered {
+                key,
+                accepted: false,
+                ..
+            } => write!(f, "key {key}: rejected"),
+            Trace::Offered {
+                key,
+                accepted: true,
+                field: Some(field),
+            } => write!(f, "key {key}: accepted as `{field}`"),
+            Trace::Offered {
+                key,
+                accepted: true,
+                field: None,
+            } => write!(f, "key {key}: accepted"),
+            Trace::Value {
+                field: Some(field),
+                ok,
+            } => write!(f, "value for `{field}`: {}", status(ok)),
+            Trace::Value { field: None, ok } => write!(f, "value: {}", status(ok)),
+            Trace::Finish { ok } => write!(f, "finish: {}", status(ok)),
+        }
+    }
+}
+
+impl<'de, C, F> TracingCapture<C, F>
+where
+    C: KeyCapture<'de>,
+    F: FnMut(Trace<'_>),
+{
+    #[inline]
+    fn offered(&mut self, key: OfferedKey<'_>, token: Option<C::Token>) -> Option<C::Token> {
+        (self.trace)(Trace::Offered {
+            key,
+            accepted: token.is_some(),
+            field: token
+                .as_ref()
+                .and_then(|token| self.capture.field_name(token)),
+        });
+        token
+    }
+}
+
+impl<'de, C, F> KeyCapture<'de> for TracingCapture<C, F>
+where
+    C: KeyCapture<'de>,
+    F: FnMut(Trace<'_>),
+{
+    type Token = C::Token;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        let token = self.capture.try_send_key(key);
+        self.offered(OfferedKey::Key(key), token)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        let token = self.capture.try_send_index(index);
+        self.offered(OfferedKey::Index(index), token)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        let token = self.capture.try_send_bool(key);
+        self.offered(OfferedKey::Bool(key), token)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        let token = self.capture.try_send_int(key);
+        self.offered(OfferedKey::Int(key), token)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        let token = self.capture.try_send_float(key);
+        self.offered(OfferedKey::Float(key), token)
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let field = self.capture.field_name(&token);
+        let result = self.capture.send_value(token, value);
+        (self.trace)(Trace::Value {
+            field,
+            ok: result.is_ok(),
+        });
+        result
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.capture.fields()
+    }
+
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        self.capture.merged_fields(inner)
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index()
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.capture.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let result = self.capture.finish();
+        (self.trace)(Trace::Finish { ok: result.is_ok() });
+        result
+    }
+}
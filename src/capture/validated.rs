@@ -0,0 +1,122 @@
+use core::{fmt, marker::PhantomData};
+
+use serde::de;
+
+use crate::private::flatten::KeyCapture;
+
+/// A [`Validated`] wraps a [`KeyCapture`] and checks each captured value as
+/// soon as it has been deserialized, rather than in a separate pass after
+/// the whole struct is done.
+///
+/// After the wrapped capture's `send_value` succeeds, `validate` is called
+/// with the capture and the token that was just populated; it can inspect the
+/// freshly stored (and already typed) value in the matching slot. If it
+/// returns an error, that error becomes a `de::Error::custom`, so it should
+/// describe the failing field.
+///
+/// The token is needed both by `send_value` and by `validate`, so it must be
+/// `Clone`; in practice tokens are fieldless enums, so this is free.
+pub struct Validated<C, F, M> {
+    capture: C,
+    validate: F,
+    phantom: PhantomData<fn() -> M>,
+}
+
+impl<C, F, M> Validated<C, F, M> {
+    #[inline]
+    #[must_use]
+    pub fn new(capture: C, validate: F) -> Self {
+        Self {
+            capture,
+            validate,
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> C {
+        self.capture
+    }
+}
+
+impl<'de, C, F, M> KeyCapture<'de> for Validated<C, F, M>
+where
+    C: KeyCapture<'de>,
+    C::Token: Clone,
+    F: FnMut(&C, &C::Token) -> Result<(), M>,
+    M: fmt::Display,
+{
+    type Token = C::Token;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.capture.try_send_key(key)
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        self.capture.try_send_index(index)
+    }
+
+    #[inline]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        self.capture.try_send_bool(key)
+    }
+
+    #[inline]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        self.capture.try_send_int(key)
+    }
+
+    #[inline]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        self.capture.try_send_float(key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token.clone(), value)?;
+        (self.validate)(&self.capture, &token).map_err(de::Error::custom)
+    }
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        self.capture.fields()
+    }
+
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        self.capture.merged_fields(inner)
+    }
+
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        self.capture.field_name(token)
+    }
+
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.capture.flatten_index()
+    }
+
+    #[inline]
+    fn is_complete(&self) -> bool {
+        self.capture.is_complete()
+    }
+
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.capture.finish()
+    }
+}
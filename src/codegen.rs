@@ -0,0 +1,467 @@
+/*!
+Build-time generation of bufferless `Deserialize` impls, for projects that
+would rather not depend on a proc-macro. A [`StructSpec`] describes a struct
+with a single `#[serde(flatten)]` field; [`StructSpec::generate`] renders the
+same `Field` enum, `Capture` struct, [`KeyCapture`] impl, and `Deserialize`
+impl that `examples/demo.rs` builds with [`key_capture!`].
+
+The struct itself is still declared normally; only the `Deserialize` impl is
+generated. A typical `build.rs` looks like this:
+
+```no_run
+use serde_bufferless::codegen::StructSpec;
+
+fn main() -> std::io::Result<()> {
+    StructSpec::new("Outer")
+        .field("float", "f32")
+        .default_field("boolean", "bool")
+        .flatten("inner", "Inner")
+        .write_to_out_dir("outer.rs")?;
+
+    Ok(())
+}
+```
+
+And the generated impl is then included next to the struct declaration:
+
+```ignore
+struct Outer {
+    float: f32,
+    boolean: bool,
+    inner: Inner,
+}
+
+include!(concat!(env!("OUT_DIR"), "/outer.rs"));
+```
+
+The generated code refers to `::serde` and `::serde_bufferless`, so both must
+be dependencies of the crate that includes it.
+
+[`KeyCapture`]: crate::private::flatten::KeyCapture
+[`key_capture!`]: crate::key_capture
+*/
+
+use std::{
+    fmt::{self, Write as _},
+    fs, io,
+    path::PathBuf,
+};
+
+/// How a sibling field is filled in when it's absent
+enum FieldDefault {
+    /// It's reported with `missing_field`, unless it's an `Option`, which
+    /// is filled with `None`
+    Required,
+
+    /// `#[serde(default)]`
+    Default,
+
+    /// `#[serde(default = "path")]`
+    Path(String),
+}
+
+struct FieldSpec {
+    name: String,
+    key: String,
+    ty: String,
+    default: FieldDefault,
+    aliases: Vec<String>,
+}
+
+/// Description of a struct with a single `#[serde(flatten)]` field, from
+/// which a bufferless `Deserialize` impl can be generated. Structs with more
+/// than one flattened field are also supported, with buffering; see
+/// [`flatten`][Self::flatten].
+pub struct StructSpec {
+    name: String,
+    fields: Vec<FieldSpec>,
+    skipped: Vec<(String, String, FieldDefault)>,
+    flatten: Vec<(String, String)>,
+}
+
+impl StructSpec {
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            fields: Vec::new(),
+            skipped: Vec::new(),
+            flatten: Vec::new(),
+        }
+    }
+
+    /// Add a required sibling field. Its absence is reported with
+    /// `missing_field`, except that an `Option` field is filled with `None`,
+    /// as serde's derive does. A second value for the field is reported with
+    /// `duplicate_field`.
+    #[must_use]
+    pub fn field(self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.push_field(name.into(), ty.into(), FieldDefault::Required)
+    }
+
+    /// Add a `#[serde(default)]` sibling field. If it's absent, it's filled
+    /// with `Default::default()`.
+    #[must_use]
+    pub fn default_field(self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.push_field(name.into(), ty.into(), FieldDefault::Default)
+    }
+
+    /// Add a `#[serde(default = "path")]` sibling field. If it's absent, it's
+    /// filled by calling `path`, which must be a function with no arguments
+    /// that returns the field's type.
+    #[must_use]
+    pub fn default_field_with(
+        self,
+        name: impl Into<String>,
+        ty: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Self {
+        self.push_field(name.into(), ty.into(), FieldDefault::Path(path.into()))
+    }
+
+    /// Add a `#[serde(skip)]` sibling field. It isn't part of the capture, so
+    /// a key with its name is forwarded to the flattened field like any other,
+    /// and it's always filled with `Default::default()`.
+    #[must_use]
+    pub fn skip_field(mut self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.skipped
+            .push((name.into(), ty.into(), FieldDefault::Default));
+        self
+    }
+
+    /// Add a `#[serde(skip, default = "path")]` sibling field. Like
+    /// [`skip_field`][Self::skip_field], but it's always filled by calling
+    /// `path`.
+    #[must_use]
+    pub fn skip_field_with(
+        mut self,
+        name: impl Into<String>,
+        ty: impl Into<String>,
+        path: impl Into<String>,
+    ) -> Self {
+        self.skipped
+            .push((name.into(), ty.into(), FieldDefault::Path(path.into())));
+        self
+    }
+
+    fn push_field(mut self, name: String, ty: String, default: FieldDefault) -> Self {
+        self.fields.push(FieldSpec {
+            key: name.clone(),
+            name,
+            ty,
+            default,
+            aliases: Vec::new(),
+        });
+        self
+    }
+
+    /// Add an alias to the most recently added sibling field, as with
+    /// `#[serde(alias = "...")]`. The capture accepts the alias as well as
+    /// the field's own name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no sibling field has been added yet.
+    #[must_use]
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.fields
+            .last_mut()
+            .expect("alias must follow a sibling field")
+            .aliases
+            .push(alias.into());
+        self
+    }
+
+    /// Set the key of the most recently added sibling field, as with
+    /// `#[serde(rename = "...")]` or a container's `rename_all`. The capture
+    /// matches the key instead of the field's name, and errors report it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no sibling field has been added yet.
+    #[must_use]
+    pub fn rename(mut self, key: impl Into<String>) -> Self {
+        self.fields
+            .last_mut()
+            .expect("rename must follow a sibling field")
+            .key = key.into();
+        self
+    }
+
+    /// Add a `#[serde(flatten)]` field.
+    ///
+    /// A struct with more than one flattened field can't be deserialized
+    /// without buffering, so its impl reads the entries that the capture
+    /// rejects into a `private::buffered::BufferedFlattenDeserializer` and
+    /// deserializes each flattened field from that, in the order they were
+    /// added. The generated code then needs the `buffer-fallback` feature of
+    /// the `serde_bufferless` it's compiled against.
+    #[must_use]
+    pub fn flatten(mut self, name: impl Into<String>, ty: impl Into<String>) -> Self {
+        self.flatten.push((name.into(), ty.into()));
+        self
+    }
+
+    /// Render the `Deserialize` impl for this struct.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`flatten`][Self::flatten] was never called.
+    #[must_use]
+    pub fn generate(&self) -> String {
+        let mut out = String::new();
+        self.render(&mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Render the `Deserialize` impl into `$OUT_DIR/file_name`, returning the
+    /// path of the written file. This is intended to be called from a
+    /// `build.rs`.
+    pub fn write_to_out_dir(&self, file_name: &str) -> io::Result<PathBuf> {
+        let out_dir = std::env::var_os("OUT_DIR")
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+        let path = PathBuf::from(out_dir).join(file_name);
+        fs::write(&path, self.generate())?;
+        Ok(path)
+    }
+
+    fn render(&self, out: &mut String) -> fmt::Result {
+        let name = &self.name;
+        assert!(!self.flatten.is_empty(), "StructSpec needs a flatten field");
+
+        writeln!(out, "impl<'de> ::serde::Deserialize<'de> for {name} {{")?;
+        writeln!(
+            out,
+            "    fn deserialize<D>(deserializer: D) -> ::core::result::Result<Self, D::Error>"
+        )?;
+        writeln!(out, "    where")?;
+        writeln!(out, "        D: ::serde::Deserializer<'de>,")?;
+        writeln!(out, "    {{")?;
+
+        writeln!(out, "        #[allow(non_camel_case_types)]")?;
+        writeln!(out, "        enum Field {{")?;
+        for field in &self.fields {
+            writeln!(out, "            {},", field.name)?;
+        }
+        writeln!(out, "        }}")?;
+        writeln!(out)?;
+
+        writeln!(out, "        struct Capture {{")?;
+        for field in &self.fields {
+            writeln!(
+                out,
+                "            {}: ::core::option::Option<{}>,",
+                field.name, field.ty
+            )?;
+        }
+        writeln!(out, "        }}")?;
+        writeln!(out)?;
+
+        writeln!(
+            out,
+            "        impl<'de> ::serde_bufferless::private::flatten::KeyCapture<'de> for &mut Capture {{"
+        )?;
+        writeln!(out, "            type Token = Field;")?;
+        writeln!(out)?;
+        writeln!(out, "            #[inline]")?;
+        writeln!(
+            out,
+            "            fn try_send_key(&mut self, key: &[u8]) -> ::core::option::Option<Self::Token> {{"
+        )?;
+        writeln!(out, "                match key {{")?;
+        for field in &self.fields {
+            write!(out, "                    {}", ByteStr(&field.key))?;
+            for alias in &field.aliases {
+                write!(out, " | {}", ByteStr(alias))?;
+            }
+            writeln!(
+                out,
+                " => ::core::option::Option::Some(Field::{}),",
+                field.name
+            )?;
+        }
+        writeln!(
+            out,
+            "                    _ => ::core::option::Option::None,"
+        )?;
+        writeln!(out, "                }}")?;
+        writeln!(out, "            }}")?;
+        writeln!(out)?;
+        writeln!(out, "            #[inline]")?;
+        writeln!(out, "            fn send_value<D>(")?;
+        writeln!(out, "                &mut self,")?;
+        writeln!(out, "                field: Self::Token,")?;
+        writeln!(out, "                value: D,")?;
+        writeln!(out, "            ) -> ::core::result::Result<(), D::Error>")?;
+        writeln!(out, "            where")?;
+        writeln!(out, "                D: ::serde::Deserializer<'de>,")?;
+        writeln!(out, "            {{")?;
+        writeln!(out, "                match field {{")?;
+        for field in &self.fields {
+            writeln!(out, "                    Field::{} => {{", field.name)?;
+            writeln!(
+                out,
+                "                        if self.{}.is_some() {{",
+                field.name
+            )?;
+            writeln!(
+                out,
+                "                            return ::core::result::Result::Err(::serde::de::Error::duplicate_field({:?}));",
+                field.key
+            )?;
+            writeln!(out, "                        }}")?;
+            writeln!(
+                out,
+                "                        self.{} = ::core::option::Option::Some(::serde::Deserialize::deserialize(value)?);",
+                field.name
+            )?;
+            writeln!(out, "                    }}")?;
+        }
+        writeln!(out, "                }}")?;
+        writeln!(out)?;
+        writeln!(out, "                ::core::result::Result::Ok(())")?;
+        writeln!(out, "            }}")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "            fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {{"
+        )?;
+        writeln!(
+            out,
+            "                formatter.write_str(\"struct {name}\")"
+        )?;
+        writeln!(out, "            }}")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "            fn fields(&self) -> &'static [&'static str] {{"
+        )?;
+        write!(out, "                &[")?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i > 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "{:?}", field.key)?;
+        }
+        writeln!(out, "]")?;
+        writeln!(out, "            }}")?;
+        writeln!(out, "        }}")?;
+        writeln!(out)?;
+
+        writeln!(out, "        let mut capture = Capture {{")?;
+        for field in &self.fields {
+            writeln!(
+                out,
+                "            {}: ::core::option::Option::None,",
+                field.name
+            )?;
+        }
+        writeln!(out, "        }};")?;
+        writeln!(out)?;
+
+        match self.flatten.as_slice() {
+            [(flatten_name, flatten_ty)] => {
+                writeln!(
+                    out,
+                    "        let {flatten_name}: {flatten_ty} = ::serde::Deserialize::deserialize("
+                )?;
+                writeln!(
+                    out,
+                    "            ::serde_bufferless::private::flatten::FlattenDeserializer::new(deserializer, &mut capture),"
+                )?;
+                writeln!(out, "        )?;")?;
+            }
+            flatten => {
+                writeln!(
+                    out,
+                    "        let mut buffered = ::serde_bufferless::private::buffered::BufferedFlattenDeserializer::new("
+                )?;
+                writeln!(out, "            deserializer,")?;
+                writeln!(out, "            &mut capture,")?;
+                writeln!(out, "        )?;")?;
+                for (flatten_name, flatten_ty) in flatten {
+                    writeln!(
+                        out,
+                        "        let {flatten_name}: {flatten_ty} = ::serde::Deserialize::deserialize(&mut buffered)?;"
+                    )?;
+                }
+            }
+        }
+        writeln!(out)?;
+
+        for field in &self.fields {
+            match field.default {
+                FieldDefault::Default => writeln!(
+                    out,
+                    "        let {0} = capture.{0}.unwrap_or_default();",
+                    field.name
+                )?,
+                FieldDefault::Path(ref path) => writeln!(
+                    out,
+                    "        let {0} = capture.{0}.unwrap_or_else({path});",
+                    field.name
+                )?,
+                FieldDefault::Required => {
+                    writeln!(out, "        let {0} = match capture.{0} {{", field.name)?;
+                    writeln!(
+                        out,
+                        "            ::core::option::Option::Some({0}) => {0},",
+                        field.name
+                    )?;
+                    writeln!(
+                        out,
+                        "            ::core::option::Option::None => ::serde_bufferless::private::missing_field::<_, D::Error>({:?})?,",
+                        field.key
+                    )?;
+                    writeln!(out, "        }};")?;
+                }
+            }
+        }
+        for (name, ty, default) in &self.skipped {
+            match default {
+                FieldDefault::Path(path) => writeln!(out, "        let {name}: {ty} = {path}();")?,
+                _ => writeln!(
+                    out,
+                    "        let {name}: {ty} = ::core::default::Default::default();"
+                )?,
+            }
+        }
+        writeln!(out)?;
+
+        writeln!(out, "        ::core::result::Result::Ok(Self {{")?;
+        for field in &self.fields {
+            writeln!(out, "            {},", field.name)?;
+        }
+        for (name, _, _) in &self.skipped {
+            writeln!(out, "            {name},")?;
+        }
+        for (flatten_name, _) in &self.flatten {
+            writeln!(out, "            {flatten_name},")?;
+        }
+        writeln!(out, "        }})")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}")?;
+
+        Ok(())
+    }
+}
+
+/// Renders a key as a byte string literal, escaping anything that isn't
+/// printable ASCII. A `str`'s `Debug` output is already a valid string
+/// literal, which is how keys are rendered everywhere else.
+struct ByteStr<'a>(&'a str);
+
+impl fmt::Display for ByteStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("b\"")?;
+        for byte in self.0.bytes() {
+            match byte {
+                b'"' | b'\\' => write!(f, "\\{}", char::from(byte))?,
+                b' '..=b'~' => f.write_char(char::from(byte))?,
+                _ => write!(f, "\\x{byte:02x}")?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
@@ -1,6 +1,52 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+mod macros;
+
+pub mod capture;
+#[cfg(feature = "codegen")]
+pub mod codegen;
 pub mod private;
+#[cfg(feature = "alloc")]
+pub mod rename;
+
+use serde::{de, Deserialize};
+
+use crate::private::flatten::{FlattenDeserializer, KeyCapture};
+
+/// Deserialize a flattened value through a [`FlattenDeserializer`], returning
+/// it along with the populated capture. The capture is taken by value and
+/// lent to the [`FlattenDeserializer`] as a `&mut`, as generated code does,
+/// so it's still available afterwards for the outer struct to be assembled
+/// from:
+///
+/// ```ignore
+/// let (inner, capture) = deserialize_flattened(deserializer, Capture::default())?;
+///
+/// Ok(Outer {
+///     float: capture
+///         .float
+///         .ok_or_else(|| de::Error::missing_field("float"))?,
+///     boolean: capture
+///         .boolean
+///         .ok_or_else(|| de::Error::missing_field("boolean"))?,
+///     inner,
+/// })
+/// ```
+pub fn deserialize_flattened<'de, D, C, F>(
+    deserializer: D,
+    mut capture: C,
+) -> Result<(F, C), D::Error>
+where
+    D: de::Deserializer<'de>,
+    for<'a> &'a mut C: KeyCapture<'de>,
+    F: Deserialize<'de>,
+{
+    let inner = F::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+    Ok((inner, capture))
+}
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,169 @@
+/// Declare a capture struct, along with its token enum and its
+/// [`KeyCapture`] impl, without a proc macro. This generates the same pieces
+/// that `codegen::StructSpec` and `#[derive(BufferlessDeserialize)]` generate
+/// inside of their `Deserialize` impls, leaving the assembly of the outer
+/// struct to the caller:
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_bufferless::{key_capture, private::flatten::FlattenDeserializer};
+///
+/// #[derive(Deserialize)]
+/// struct Inner {
+///     integer: i32,
+/// }
+///
+/// key_capture! {
+///     Capture {
+///         before: Option<f32>,
+///         after: Option<bool>,
+///     }
+/// }
+///
+/// let mut capture = Capture::default();
+/// let inner = Inner::deserialize(FlattenDeserializer::new(
+///     &mut serde_json::Deserializer::from_str(r#"{"before": 1.5, "integer": 10}"#),
+///     &mut capture,
+/// ))
+/// .unwrap();
+///
+/// assert_eq!(capture.before, Some(1.5));
+/// assert_eq!(capture.after, None);
+/// assert_eq!(inner.integer, 10);
+/// ```
+///
+/// Every field must be an `Option`, which is filled in when its key is
+/// found. The struct derives `Default`, and its fields have the same
+/// visibility as the struct itself. As with the generated code, keys are
+/// matched against the field names exactly (without the `r#` of a raw
+/// identifier), fields are numbered in declaration order for
+/// `try_send_index`, and [`KeyCapture`] is implemented for `&mut Capture`.
+/// The token enum is declared inside an anonymous `const`, so it doesn't
+/// conflict with anything else in scope.
+///
+/// The capture's [`expecting`] describes it as `struct Capture`. Since
+/// errors from flattening are reported against the capture, name the outer
+/// struct with `for` to have them describe it instead:
+///
+/// ```
+/// # use serde_bufferless::key_capture;
+/// key_capture! {
+///     Capture for Outer {
+///         before: Option<f32>,
+///     }
+/// }
+/// ```
+///
+/// The generated code refers to `::serde`, so `serde` must be a dependency
+/// of the crate that uses this macro.
+///
+/// [`KeyCapture`]: crate::private::flatten::KeyCapture
+/// [`expecting`]: crate::private::flatten::KeyCapture::expecting
+#[macro_export]
+macro_rules! key_capture {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident {
+            $($field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $crate::key_capture! {
+            $(#[$meta])*
+            $vis $name for $name {
+                $($field: $ty,)*
+            }
+        }
+    };
+
+    (
+        $(#[$meta:meta])*
+        $vis:vis $name:ident for $outer:ident {
+            $($field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Default)]
+        $vis struct $name {
+            $($vis $field: $ty,)*
+        }
+
+        const _: () = {
+            #[allow(non_camel_case_types)]
+            #[derive(Clone, Copy)]
+            $vis enum Field {
+                $($field,)*
+            }
+
+            impl Field {
+                #[inline]
+                const fn key(self) -> &'static str {
+                    match self {
+                        $(
+                            Field::$field => {
+                                $crate::private::unraw(::core::stringify!($field))
+                            }
+                        )*
+                    }
+                }
+            }
+
+            impl<'de> $crate::private::flatten::KeyCapture<'de> for &mut $name {
+                type Token = Field;
+
+                #[inline]
+                fn try_send_key(&mut self, key: &[u8]) -> ::core::option::Option<Self::Token> {
+                    $({
+                        const KEY: &[u8] = Field::$field.key().as_bytes();
+
+                        if key == KEY {
+                            return ::core::option::Option::Some(Field::$field);
+                        }
+                    })*
+
+                    ::core::option::Option::None
+                }
+
+                #[inline]
+                fn try_send_index(&mut self, index: u64) -> ::core::option::Option<Self::Token> {
+                    const TOKENS: &[Field] = &[$(Field::$field),*];
+
+                    TOKENS
+                        .get(<usize as ::core::convert::TryFrom<u64>>::try_from(index).ok()?)
+                        .copied()
+                }
+
+                #[inline]
+                fn send_value<D>(
+                    &mut self,
+                    field: Self::Token,
+                    value: D,
+                ) -> ::core::result::Result<(), D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                {
+                    match field {
+                        $(
+                            Field::$field => {
+                                self.$field = ::core::option::Option::Some(
+                                    ::serde::Deserialize::deserialize(value)?,
+                                )
+                            }
+                        )*
+                    }
+
+                    ::core::result::Result::Ok(())
+                }
+
+                fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                    formatter.write_str(::core::concat!("struct ", ::core::stringify!($outer)))
+                }
+
+                fn fields(&self) -> &'static [&'static str] {
+                    const FIELDS: &[&str] = &[$(Field::$field.key()),*];
+
+                    FIELDS
+                }
+            }
+        };
+    };
+}
@@ -3,14 +3,47 @@ Components that would be added to serde's private module to support
 bufferless deserialization
 */
 
+#[cfg(feature = "buffer-fallback")]
+pub mod buffered;
 pub mod flatten;
+pub mod flatten_ser;
+#[cfg(feature = "alloc")]
+pub mod upfront;
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 use serde::{de, forward_to_deserialize_any};
 
 /// Struct to fuse a MapAccess or a SeqAccess
-struct FusedAccess<A> {
+///
+/// Once the wrapped access reports the end of the map or sequence (by
+/// returning `None` from `next_key_seed`, `next_entry_seed`, or
+/// `next_element_seed`), it's dropped, and every later call reports the end
+/// again without touching it. This makes it safe to drain a map that a
+/// visitor may or may not have already finished reading, since many
+/// `MapAccess` implementations misbehave if they're polled past the end.
+///
+/// There's no value to return from `next_value_seed` once the map has
+/// ended; calling it then is a protocol error by the caller, and is reported
+/// with `de::Error::custom` rather than a panic.
+///
+/// ```
+/// use serde::de::{value::MapDeserializer, IgnoredAny, MapAccess};
+/// use serde_bufferless::private::FusedAccess;
+///
+/// let map = MapDeserializer::<_, serde::de::value::Error>::new([("a", 1), ("b", 2)].into_iter());
+/// let mut map = FusedAccess::new(map);
+///
+/// while let Some((key, value)) = map.next_entry::<String, i32>()? {
+///     println!("{key}: {value}");
+/// }
+///
+/// // The map has ended, so it stays ended
+/// assert!(map.next_key::<IgnoredAny>()?.is_none());
+/// assert!(map.next_key::<IgnoredAny>()?.is_none());
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+pub struct FusedAccess<A> {
     access: Option<A>,
 }
 
@@ -23,6 +56,15 @@ impl<A> FusedAccess<A> {
         }
     }
 
+    /// Once the access has ended, there's nothing left
+    #[inline]
+    fn size_hint_with(&self, size_hint: impl FnOnce(&A) -> Option<usize>) -> Option<usize> {
+        match self.access {
+            None => Some(0),
+            Some(ref access) => size_hint(access),
+        }
+    }
+
     #[inline]
     fn next_item<T, E>(
         &mut self,
@@ -51,6 +93,11 @@ impl<'de, A: de::SeqAccess<'de>> de::SeqAccess<'de> for FusedAccess<A> {
     {
         self.next_item(|access| access.next_element_seed(seed))
     }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        self.size_hint_with(A::size_hint)
+    }
 }
 
 impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for FusedAccess<A> {
@@ -69,10 +116,12 @@ impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for FusedAccess<A> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        self.access
-            .as_mut()
-            .expect("called next_value_seed after next_key_seed returned None")
-            .next_value_seed(seed)
+        match self.access {
+            Some(ref mut access) => access.next_value_seed(seed),
+            None => Err(de::Error::custom(
+                "called next_value_seed after next_key_seed returned None",
+            )),
+        }
     }
 
     #[inline]
@@ -87,6 +136,11 @@ impl<'de, A: de::MapAccess<'de>> de::MapAccess<'de> for FusedAccess<A> {
     {
         self.next_item(|access| access.next_entry_seed(key, value))
     }
+
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        self.size_hint_with(A::size_hint)
+    }
 }
 
 /// Additional versions of IntoDeserializer
@@ -119,10 +173,27 @@ where
         visitor.visit_enum(self.value)
     }
 
+    /// The variant names aren't needed here: the visitor checks the variant
+    /// against its own list when it reads the variant identifier from the
+    /// `EnumAccess`, so an unknown variant is still reported with every
+    /// expected name.
+    #[inline]
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(self.value)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
         bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier ignored_any
+        tuple_struct map struct identifier ignored_any
     }
 }
 
@@ -155,9 +226,23 @@ where
         visitor.visit_newtype_struct(self.deserializer)
     }
 
+    /// The newtype's name was already consumed by the format, and a visitor
+    /// has no way to receive it, so it's ignored here as well
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self.deserializer)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        bytes byte_buf option unit unit_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
 }
@@ -190,22 +275,32 @@ where
         visitor.visit_some(self.deserializer)
     }
 
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self.deserializer)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
         tuple_struct map struct enum identifier ignored_any
     }
 }
 
+#[cfg(feature = "alloc")]
 pub struct ByteBufDeserializer<E> {
-    buf: Vec<u8>,
+    buf: alloc::vec::Vec<u8>,
     phantom: PhantomData<E>,
 }
 
+#[cfg(feature = "alloc")]
 impl<E> ByteBufDeserializer<E> {
     #[inline]
     #[must_use]
-    pub fn new(buf: Vec<u8>) -> Self {
+    pub fn new(buf: alloc::vec::Vec<u8>) -> Self {
         Self {
             buf,
             phantom: PhantomData,
@@ -213,6 +308,7 @@ impl<E> ByteBufDeserializer<E> {
     }
 }
 
+#[cfg(feature = "alloc")]
 impl<'de, E> de::Deserializer<'de> for ByteBufDeserializer<E>
 where
     E: de::Error,
@@ -233,3 +329,125 @@ where
         tuple_struct map struct enum identifier ignored_any
     }
 }
+
+/// The borrowed counterpart of [`ByteBufDeserializer`], for byte slices that
+/// live as long as the input. It calls `visit_borrowed_bytes`, so the
+/// visitor can keep the slice without copying it, and it doesn't need an
+/// allocator.
+#[derive(Clone, Copy)]
+pub struct BorrowedByteBufDeserializer<'de, E> {
+    buf: &'de [u8],
+    phantom: PhantomData<E>,
+}
+
+impl<'de, E> BorrowedByteBufDeserializer<'de, E> {
+    #[inline]
+    #[must_use]
+    pub fn new(buf: &'de [u8]) -> Self {
+        Self {
+            buf,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'de, E> de::Deserializer<'de> for BorrowedByteBufDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.buf)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Fill in a sibling field that was absent from the map, the way serde's
+/// derive does: an `Option` becomes `None`, and anything else is reported
+/// with `missing_field`.
+///
+/// ```
+/// use serde::de::value::Error;
+/// use serde_bufferless::private::missing_field;
+///
+/// assert_eq!(missing_field::<Option<i32>, Error>("maybe")?, None);
+/// assert!(missing_field::<i32, Error>("integer").is_err());
+/// # Ok::<(), Error>(())
+/// ```
+#[inline]
+pub fn missing_field<'de, T, E>(field: &'static str) -> Result<T, E>
+where
+    T: de::Deserialize<'de>,
+    E: de::Error,
+{
+    T::deserialize(MissingFieldDeserializer {
+        field,
+        phantom: PhantomData,
+    })
+}
+
+struct MissingFieldDeserializer<E> {
+    field: &'static str,
+    phantom: PhantomData<E>,
+}
+
+impl<'de, E> de::Deserializer<'de> for MissingFieldDeserializer<E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    #[inline]
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(de::Error::missing_field(self.field))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Strip the `r#` from a raw identifier's name, as given by `stringify!`,
+/// so that a field such as `r#type` matches the key `type`, as it does with
+/// serde's derive. Used by [`key_capture!`][crate::key_capture] in `const`
+/// items, so the name is stripped at compile time.
+///
+/// ```
+/// use serde_bufferless::private::unraw;
+///
+/// assert_eq!(unraw("r#type"), "type");
+/// assert_eq!(unraw("before"), "before");
+/// ```
+#[must_use]
+pub const fn unraw(name: &'static str) -> &'static str {
+    match name.as_bytes() {
+        [b'r', b'#', rest @ ..] => match core::str::from_utf8(rest) {
+            Ok(name) => name,
+            Err(_) => name,
+        },
+        _ => name,
+    }
+}
@@ -0,0 +1,223 @@
+/*!
+A fallback for structs with more than one `#[serde(flatten)]` field, which
+can't be deserialized without buffering: each flattened field may want any
+of the keys the outer struct doesn't, and there's no way to know which one a
+key belongs to until they've all seen it.
+
+[`BufferedFlattenDeserializer::new`] reads the whole map through a
+[`FlattenDeserializer`], so the outer struct's own fields are still captured
+as they stream past and never buffered. Every other entry is collected as a
+pair of [`serde_value::Value`]s, much as serde's own flatten collects them
+into its private `Content`. Each flattened field is then deserialized from
+the [`BufferedFlattenDeserializer`] in turn, with the same rules as serde's
+flatten: a struct (anything that calls `deserialize_struct`) takes the
+entries for its own fields, so the flattened fields after it don't see them,
+and anything else, such as a map, sees every entry that's left.
+
+```ignore
+let mut buffered = BufferedFlattenDeserializer::new(deserializer, &mut capture)?;
+let first: First = Deserialize::deserialize(&mut buffered)?;
+let rest: BTreeMap<String, Value> = Deserialize::deserialize(&mut buffered)?;
+```
+
+This is what the derive and [`codegen`][crate::codegen] fall back to when a
+struct has more than one flattened field, with the `buffer-fallback` feature
+enabled; a struct with a single flattened field never buffers. Since the
+entries are owned `Value`s, the flattened fields can't borrow from the input.
+
+[`FlattenDeserializer`]: super::flatten::FlattenDeserializer
+*/
+
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+
+use serde::{
+    de::{self, value::MapDeserializer, Deserialize},
+    forward_to_deserialize_any,
+};
+use serde_value::{Value, ValueDeserializer};
+
+use super::flatten::{FlattenDeserializer, KeyCapture};
+
+/// The entries of a map that the capture rejected, collected so that they
+/// can be replayed to each of a struct's flattened fields. See the
+/// [module documentation][self].
+///
+/// It's a [`de::Deserializer`] by mutable reference, presenting the
+/// remaining entries as a map each time it's used. `E` is the error type of
+/// the original deserializer, so that errors from the flattened fields are
+/// reported the same way as any other.
+#[derive(Debug, Clone)]
+pub struct BufferedFlattenDeserializer<E> {
+    /// Entries taken by a flattened struct are replaced with `None`
+    entries: Vec<Option<(Value, Value)>>,
+    error: PhantomData<fn() -> E>,
+}
+
+impl<E> BufferedFlattenDeserializer<E>
+where
+    E: de::Error,
+{
+    /// Read the map from `deserializer`, sending the outer struct's fields
+    /// to `capture` and collecting every other entry. The capture's
+    /// [`finish`][KeyCapture::finish] is called once the map has been read.
+    pub fn new<'de, D, C>(deserializer: D, capture: C) -> Result<Self, E>
+    where
+        D: de::Deserializer<'de, Error = E>,
+        C: KeyCapture<'de>,
+    {
+        let Entries(entries) =
+            Entries::deserialize(FlattenDeserializer::new(deserializer, capture))?;
+
+        Ok(Self {
+            entries: entries.into_iter().map(Some).collect(),
+            error: PhantomData,
+        })
+    }
+
+    /// The entries that haven't been taken by a flattened struct yet, in the
+    /// order they appeared in the input
+    #[inline]
+    pub fn entries(&self) -> impl Iterator<Item = &(Value, Value)> {
+        self.entries.iter().flatten()
+    }
+}
+
+/// A [`MapDeserializer`] over owned entries
+fn map_deserializer<'de, I, E>(
+    entries: I,
+) -> MapDeserializer<'de, impl Iterator<Item = ValuePair<E>>, E>
+where
+    I: IntoIterator<Item = (Value, Value)>,
+    E: de::Error,
+{
+    MapDeserializer::new(
+        entries
+            .into_iter()
+            .map(|(key, value)| (ValueDeserializer::new(key), ValueDeserializer::new(value))),
+    )
+}
+
+type ValuePair<E> = (ValueDeserializer<E>, ValueDeserializer<E>);
+
+/// Whether `key` names one of `fields`
+fn is_field(key: &Value, fields: &[&str]) -> bool {
+    let key = match key {
+        Value::String(key) => key.as_bytes(),
+        Value::Bytes(key) => key.as_slice(),
+        _ => return false,
+    };
+
+    fields.iter().any(|field| field.as_bytes() == key)
+}
+
+/// Each flattened field is presented with the remaining entries as a map,
+/// and may leave any of them unread. As with serde's own flatten, `Option`
+/// fields are always `Some`, and unit types accept any map.
+impl<'de, E> de::Deserializer<'de> for &mut BufferedFlattenDeserializer<E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(map_deserializer(self.entries().cloned()))
+    }
+
+    /// The entries for the struct's own fields are taken, so that they
+    /// aren't seen by the flattened fields after this one
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        let taken: Vec<_> = self
+            .entries
+            .iter_mut()
+            .filter(|entry| matches!(entry, Some((key, _)) if is_field(key, fields)))
+            .filter_map(Option::take)
+            .collect();
+
+        visitor.visit_map(map_deserializer(taken))
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    #[inline]
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+/// Collects every entry of a map as a pair of `Value`s
+struct Entries(Vec<(Value, Value)>);
+
+impl<'de> Deserialize<'de> for Entries {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+struct EntriesVisitor;
+
+impl<'de> de::Visitor<'de> for EntriesVisitor {
+    type Value = Entries;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(Entries(entries))
+    }
+}
@@ -14,13 +14,28 @@ can indicate if it "wants" a key or not; it is sent a value for every key it
 wants. Keys it doesn't want are then sent to `F` for ordinary deserialization.
 */
 
-use core::fmt;
+use core::{fmt, marker::PhantomData};
 
-use serde::{de, forward_to_deserialize_any, serde_if_integer128, Deserialize};
+use serde::{de, forward_to_deserialize_any, Deserialize};
 
 use super::{EnumDeserializer, FusedAccess, NewtypeDeserializer, SomeDeserializer};
 
+/// A [`KeyCapture`] receives the fields of the outer struct while the inner,
+/// flattened value is being deserialized.
+///
+/// Values are sent to the capture in the order they appear in the input,
+/// interleaved with the inner type's own fields. The capture only stores
+/// them; the outer struct is assembled from the stored values after
+/// [`FlattenDeserializer`] returns, and generated code does that in field
+/// declaration order, regardless of the input order. Captures with side
+/// effects should therefore do that work during assembly, rather than in
+/// `send_value`, if they need a stable order; see [`FinalizeInOrder`].
 pub trait KeyCapture<'de> {
+    /// Identifies an accepted key's field, from `try_send_*` to
+    /// `send_value`. Usually a fieldless enum, but it can carry data: the key
+    /// bytes don't outlive `try_send_key`, so a token that needs to remember
+    /// which alias matched can hold a `&'static str` for it (or an owned
+    /// copy of the key).
     type Token;
 
     /// Send a key into the KeyCapture, If this method returns a token, it
@@ -28,20 +43,112 @@ pub trait KeyCapture<'de> {
     /// send_value with that token. Otherwise, the key was rejected, and can
     /// be passed into the visitor for the inner flattened struct.
     ///
-    /// Because struct keys are only ever strings or byte slices when `flatten`
-    /// is involved, we only need to have this version accepting a byte slice.
+    /// Because struct keys are almost always strings or byte slices when
+    /// `flatten` is involved, this version accepts a byte slice; see
+    /// `try_send_index` for formats that use numeric field indices. A string
+    /// key is passed as its UTF-8 bytes, so the capture sees the same bytes
+    /// whether the format delivered the key as a borrowed or owned string or
+    /// as bytes, and can match all of them with the same `b"..."` pattern.
     ///
     /// Because the only thing we do with the key in practice is check it
     /// against a list of struct fields, this method doesn't ever return an
     /// error
+    ///
+    /// A field with `#[serde(alias = "...")]` accepts each of its aliases as
+    /// well as its own name, so the match has one pattern per name, all
+    /// producing the same token:
+    ///
+    /// ```ignore
+    /// match key {
+    ///     b"before" | b"prior" => Some(Field::before),
+    ///     b"after" => Some(Field::after),
+    ///     _ => None,
+    /// }
+    /// ```
+    ///
+    /// A field with `#[serde(skip)]` (or `skip_deserializing`) has no
+    /// pattern at all, so a key with its name is rejected and forwarded to
+    /// the inner type like any other; the field isn't part of the capture,
+    /// and is filled with `Default::default()` when the outer struct is
+    /// assembled.
     #[must_use]
     fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token>;
 
+    /// Send a field index into the KeyCapture. Compact formats may identify
+    /// struct fields by their index rather than their name; following serde's
+    /// convention, index `N` refers to the `N`th declared field of the outer
+    /// struct. Like `try_send_key`, returning a token means the key was
+    /// accepted.
+    ///
+    /// Indices are offered to the capture before the inner flattened type
+    /// sees them, so if both use indices, the inner type will never see one
+    /// that the capture accepts. The default implementation rejects every
+    /// index, forwarding them all to the inner type.
+    #[inline]
+    #[must_use]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        let _ = index;
+        None
+    }
+
+    /// Send a boolean key into the KeyCapture, for maps whose keys are
+    /// booleans. Like `try_send_key`, returning a token means the key was
+    /// accepted. The default implementation rejects every boolean key.
+    #[inline]
+    #[must_use]
+    fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+        let _ = key;
+        None
+    }
+
+    /// Send a signed integer key into the KeyCapture. Unsigned keys are
+    /// field indices, and are sent to `try_send_index` instead. The default
+    /// implementation rejects every signed key.
+    ///
+    /// Between them, `try_send_index` and `try_send_int` see every integer
+    /// key that fits in 64 bits, so a capture for a map with integer keys
+    /// should generally implement both; 128-bit keys always go to the inner
+    /// type.
+    #[inline]
+    #[must_use]
+    fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+        let _ = key;
+        None
+    }
+
+    /// Send a floating point key into the KeyCapture. `f32` keys are widened
+    /// to `f64`. The default implementation rejects every floating point key.
+    #[inline]
+    #[must_use]
+    fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+        let _ = key;
+        None
+    }
+
     /// Send a value into the KeyCapture. This should be called anytime
     /// try_send_key returns a token.
     ///
     /// This method always stores the deserialized value locally, inside of
     /// itself, so it never returns a value.
+    ///
+    /// Because a stored value simply overwrites whatever was there before,
+    /// fields with `#[serde(default)]` can be pre-seeded with their default
+    /// when the capture is constructed, rather than resolved with
+    /// `unwrap_or_default` afterwards. Required fields must still start out
+    /// empty, so that their absence can be detected once deserialization is
+    /// finished.
+    ///
+    /// `value` is the underlying format's own deserializer for the value,
+    /// with the same `'de` lifetime, so a capture implemented for
+    /// `&mut Capture<'de>` can store borrowed data such as `&'de str` without
+    /// copying it.
+    ///
+    /// Nothing requires the value to be deserialized with `Deserialize`,
+    /// either: a capture can deserialize it with a `DeserializeSeed` built
+    /// from its own state, such as a field it captured earlier. Values arrive
+    /// in input order, so a field that depends on another can only see it if
+    /// it came first; the capture has to decide what to do otherwise, such
+    /// as returning an error or keeping a less specific form of the value.
     fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
     where
         D: de::Deserializer<'de>;
@@ -50,6 +157,218 @@ pub trait KeyCapture<'de> {
     /// destination for data to be deserialized, so it provides an expecting
     /// as well.
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result;
+
+    /// The names of the fields this capture accepts, analogous to the
+    /// `fields` passed to `deserialize_struct`. The default implementation
+    /// returns an empty list.
+    #[inline]
+    fn fields(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// The capture's [`fields`][Self::fields] followed by `inner`, the field
+    /// names the inner type passed to `deserialize_struct`. If this returns a
+    /// list, it's what's forwarded to the underlying `deserialize_struct`, so
+    /// that formats which validate keys against the declared fields don't
+    /// reject the captured ones; otherwise, the inner type is deserialized
+    /// with `deserialize_map` instead, as serde's own flatten does. Formats
+    /// that serialize structs as sequences need the list too, since they
+    /// only see a struct as a sequence if it's deserialized as one.
+    ///
+    /// `deserialize_struct` requires a `'static` list, which can't be built
+    /// at runtime without leaking it, so the default implementation only
+    /// returns a list when one of the two is empty. A capture that knows the
+    /// inner type's fields ahead of time can return a precomputed list:
+    ///
+    /// ```ignore
+    /// fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+    ///     match inner {
+    ///         ["integer", "string"] => Some(&["before", "after", "integer", "string"]),
+    ///         _ => merge_fields(self.fields(), inner),
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        merge_fields(self.fields(), inner)
+    }
+
+    /// The name of the field that a token was produced for. If this returns
+    /// a name, an error from `send_value` for that token is wrapped to say
+    /// which field it came from, as in ``error deserializing field `before`:
+    /// invalid type: ...``, which helps when the same type appears in several
+    /// fields. The default implementation returns `None`, leaving errors
+    /// unchanged.
+    #[inline]
+    fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+        let _ = token;
+        None
+    }
+
+    /// The position of the flattened field among the outer struct's fields,
+    /// for formats that serialize structs as sequences. The capture's first
+    /// `flatten_index` fields (in the order of `fields`) precede the inner
+    /// value's fields in the sequence, and the rest follow them; each is
+    /// offered to `try_send_index` with its position in `fields`. The
+    /// default implementation puts the flattened field last.
+    #[inline]
+    fn flatten_index(&self) -> usize {
+        self.fields().len()
+    }
+
+    /// Whether the capture has everything it wants, so that the remaining
+    /// keys needn't be offered to it. Once this returns `true`, each
+    /// remaining key is forwarded straight to the inner type, skipping the
+    /// `try_send_*` methods and the seed that wraps the key, which saves the
+    /// work of matching every key of a large object against fields that have
+    /// already been filled. It's checked before each key.
+    ///
+    /// A key the capture would have accepted, such as a repeat of a field it
+    /// already has, then reaches the inner type instead, which will usually
+    /// ignore it (or reject it, if it denies unknown fields). It has no
+    /// effect when the [`FlattenDeserializer`] itself denies unknown fields,
+    /// since those keys still have to be checked against the outer fields.
+    /// The default implementation returns `false`.
+    #[inline]
+    fn is_complete(&self) -> bool {
+        false
+    }
+
+    /// Called once the whole map has been consumed, including the drain, so
+    /// that the capture can check its required fields itself and report a
+    /// missing one with `de::Error::missing_field`. It's only called if
+    /// deserialization succeeded; an earlier error is returned as-is. The
+    /// default implementation does nothing.
+    #[inline]
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        Ok(())
+    }
+}
+
+/// Find `key` in a table of field names sorted by their bytes, returning the
+/// value it's paired with. A capture can pair each name with its token's
+/// discriminant, or with an index into a list of tokens:
+///
+/// ```
+/// use serde_bufferless::private::flatten::match_field;
+///
+/// const FIELDS: &[(&[u8], u32)] = &[(b"after", 1), (b"before", 0), (b"during", 2)];
+///
+/// assert_eq!(match_field(FIELDS, b"before"), Some(0));
+/// assert_eq!(match_field(FIELDS, b"beyond"), None);
+/// ```
+///
+/// Aliases are just additional entries with the same value. If the table
+/// isn't sorted, the result is unspecified (but won't panic).
+///
+/// This is a binary search, but that doesn't make it faster than a `match`
+/// on byte literals: in `benches/match_field.rs`, the compiler's decision
+/// tree for a `match` wins by an order of magnitude even at 64 fields. It's
+/// meant for tables that can't be written as a `match`, such as ones built
+/// from a list of names by a macro, rather than as a replacement for one.
+#[inline]
+#[must_use]
+pub fn match_field(sorted_table: &[(&[u8], u32)], key: &[u8]) -> Option<u32> {
+    sorted_table
+        .binary_search_by(|&(name, _)| name.cmp(key))
+        .ok()
+        .map(|index| sorted_table[index].1)
+}
+
+/// Find `key` in a table of field names bucketed by length, returning the
+/// value it's paired with. `table_by_len[n]` holds the fields whose names are
+/// `n` bytes long, in any order, so a key is only ever compared against names
+/// of its own length:
+///
+/// ```
+/// use serde_bufferless::private::flatten::match_field_by_len;
+///
+/// const FIELDS: &[&[(&[u8], u32)]] = &[
+///     &[],
+///     &[],
+///     &[(b"id", 0)],
+///     &[],
+///     &[(b"name", 1), (b"kind", 2)],
+/// ];
+///
+/// assert_eq!(match_field_by_len(FIELDS, b"kind"), Some(2));
+/// assert_eq!(match_field_by_len(FIELDS, b"size"), None);
+/// assert_eq!(match_field_by_len(FIELDS, b"identifier"), None);
+/// ```
+///
+/// In `benches/match_field.rs`, at 32 fields of varied lengths, this is
+/// several times faster than [`match_field`]'s binary search, but still about
+/// half the speed of a `match` on byte literals. Generated code can bucket
+/// a `match` the same way, with a `match key.len()` around a `match` for each
+/// length, but it gains little from it: comparing a slice against a literal
+/// already checks the length first, and the length-first `match` was within
+/// 10% of the plain one. Like [`match_field`], this is for tables that can't
+/// be written as a `match`.
+#[inline]
+#[must_use]
+pub fn match_field_by_len(table_by_len: &[&[(&[u8], u32)]], key: &[u8]) -> Option<u32> {
+    table_by_len
+        .get(key.len())?
+        .iter()
+        .find(|&&(name, _)| name == key)
+        .map(|&(_, value)| value)
+}
+
+/// A check that spans the captured fields and the deserialized inner value,
+/// such as a checksum field that must match the inner content. The inner
+/// value only exists once [`FlattenDeserializer`] has returned, so this runs
+/// during assembly, after deserialization but before the outer struct is
+/// built:
+///
+/// ```ignore
+/// let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+/// capture.finalize_with_inner(&inner)?;
+/// ```
+pub trait FinalizeWithInner<T> {
+    /// Check the captured fields against `inner`, returning an error if
+    /// they're inconsistent.
+    fn finalize_with_inner<E>(&mut self, inner: &T) -> Result<(), E>
+    where
+        E: de::Error;
+}
+
+/// Assembly of the captured fields in declaration order, for captures whose
+/// fields have side effects when they're resolved. Values are still
+/// deserialized in input order, as they arrive at
+/// [`send_value`][KeyCapture::send_value]; this only orders what happens to
+/// the stored values afterwards:
+///
+/// ```ignore
+/// let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+/// capture.finalize_in_order()?;
+/// ```
+pub trait FinalizeInOrder {
+    /// The capture's field token
+    type Field: Copy + 'static;
+
+    /// Every field, in the order the outer struct declares them
+    const FIELDS: &'static [Self::Field];
+
+    /// Resolve one field from its slot, such as by checking that a required
+    /// field is present.
+    fn finalize_field<E>(&mut self, field: Self::Field) -> Result<(), E>
+    where
+        E: de::Error;
+
+    /// Resolve every field, in the order of [`FIELDS`][Self::FIELDS],
+    /// stopping at the first error.
+    #[inline]
+    fn finalize_in_order<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        Self::FIELDS
+            .iter()
+            .try_for_each(|&field| self.finalize_field(field))
+    }
 }
 
 /// A [`FlattenDeserializer`] assists with deserializing a struct with a single
@@ -57,9 +376,117 @@ pub trait KeyCapture<'de> {
 /// value, but while running, it also captures the the other fields into
 /// `capture`, only forwarding them to the flattened value of `capture` doesn't
 /// want them.
+///
+/// Because the flattened value shares its keys with the outer struct, the
+/// input must be map-shaped. Most `deserialize_*` methods are answered with a
+/// `deserialize_map` (or, for `deserialize_struct`, a `deserialize_struct`)
+/// on the underlying deserializer, so that formats that aren't
+/// self-describing still work. Positional inner types, such as tuple
+/// structs, are read from a sequence instead, with a
+/// [`FlattenSeqDeserializer`]. `deserialize_any` is passed through as-is, so
+/// an inner type that dispatches on the shape of the data sees whatever the
+/// format has, but only a map or a sequence gets any further. The other
+/// exception is `deserialize_option`, which lets a flattened `Option` be
+/// `None` when the value is null.
+///
+/// A sequence is read positionally, as described by
+/// [`KeyCapture::flatten_index`], and one that's too short is reported with
+/// an `invalid length` error. Any other shape, such as a string or a number,
+/// is rejected with an `invalid type` error. Both use the capture's
+/// `expecting`, so they name the outer struct rather than the inner type.
+///
+/// The inner type only ever sees the keys that the capture rejected, so an
+/// inner type with `#[serde(deny_unknown_fields)]` never counts the outer
+/// struct's fields against it; it rejects only keys that neither it nor the
+/// capture recognize. Its error lists only its own fields, though; see
+/// [`deny_unknown_fields`][Self::deny_unknown_fields] for an error that lists
+/// the outer fields too.
+///
+/// If the capture and the inner type declare a field with the same name, the
+/// capture always wins: it's offered every key first, so the inner type never
+/// sees a key the capture accepts. The inner field is then left to its own
+/// default, or reported as missing if it has none. This matches serde's own
+/// flatten, where the outer struct's fields take precedence.
+///
+/// Inner types that buffer their input, such as `#[serde(untagged)]` enums,
+/// work as they would anywhere else, but the buffer only ever holds the
+/// entries that the capture rejected; the outer struct's fields are captured
+/// as they stream past, and never buffered. The remaining entries are still
+/// buffered in full before any variant is tried.
+///
+/// [`FlattenDeserializer`]s nest: if the inner type itself has a flattened
+/// field, its own [`FlattenDeserializer`] wraps this one, and each key is
+/// offered to the outermost capture first, then to each inner capture in
+/// turn, before reaching the innermost type. Each level drains its own map
+/// access when its inner visitor returns, so leftover entries are still seen
+/// by every capture.
+///
+/// If the inner type returns an error partway through the map, that error is
+/// returned as-is: the map isn't drained, the capture isn't `finish`ed, and
+/// nothing more is read from the underlying map access, not even the value
+/// of a key the inner type had just read. The underlying deserializer is left
+/// wherever the inner type stopped.
 pub struct FlattenDeserializer<D, C> {
     deserializer: D,
     capture: C,
+    options: Options,
+}
+
+/// What to do with a key that the capture rejects and that the inner type
+/// doesn't declare
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownPolicy {
+    /// Forward the key to the inner type, which decides for itself whether
+    /// to ignore it or reject it. This is the default.
+    #[default]
+    Forward,
+
+    /// Reject the key with an `unknown_field` error that lists both the
+    /// capture's fields and the inner type's. This requires the inner type's
+    /// field names, which are taken from its call to `deserialize_struct` or
+    /// from [`FlattenBuilder::fields`]; without them, keys are forwarded.
+    /// [`FlattenDeserializer::new_deny_unknown`] provides the full list of
+    /// allowed keys instead.
+    Deny,
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    drain: bool,
+    unknown: UnknownPolicy,
+    outer_fields: Option<&'static [&'static str]>,
+    inner_fields: Option<&'static [&'static str]>,
+    allowed: Option<&'static [&'static str]>,
+    context: Option<&'static str>,
+}
+
+impl Default for Options {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            drain: true,
+            unknown: UnknownPolicy::Forward,
+            outer_fields: None,
+            inner_fields: None,
+            allowed: None,
+            context: None,
+        }
+    }
+}
+
+impl<D> FlattenDeserializer<D, ()> {
+    /// Start building a [`FlattenDeserializer`] with non-default options.
+    /// The capture is set with [`FlattenBuilder::capture`]; [`new`][Self::new]
+    /// is a shorthand for a builder with only a capture.
+    #[inline]
+    #[must_use]
+    pub fn builder(deserializer: D) -> FlattenBuilder<D, ()> {
+        FlattenBuilder {
+            deserializer,
+            capture: (),
+            options: Options::default(),
+        }
+    }
 }
 
 impl<'de, D, C> FlattenDeserializer<D, C>
@@ -73,6 +500,296 @@ where
         Self {
             deserializer,
             capture,
+            options: Options::default(),
+        }
+    }
+
+    /// Create a [`FlattenDeserializer`] that rejects every key that neither
+    /// the capture accepts nor appears in `allowed`, as an outer struct with
+    /// `#[serde(deny_unknown_fields)]` would. `allowed` is the union of the
+    /// outer and inner field names; it's used as-is in the `unknown_field`
+    /// error, and unlike [`deny_unknown_fields`][Self::deny_unknown_fields],
+    /// it applies whether or not the inner type declares its own fields.
+    ///
+    /// Unknown keys are rejected as they're read, including any found by the
+    /// drain after the inner type is done.
+    #[inline]
+    #[must_use]
+    pub fn new_deny_unknown(deserializer: D, capture: C, allowed: &'static [&'static str]) -> Self {
+        let mut this = Self::new(deserializer, capture);
+        this.options.unknown = UnknownPolicy::Deny;
+        this.options.allowed = Some(allowed);
+        this
+    }
+
+    /// Don't drain the map after the inner type's visitor returns. Normally,
+    /// any entries the inner type left behind are pulled from the map so
+    /// that captured fields among them still reach the capture; skipping this
+    /// saves a pass over the map when the inner type is known to consume
+    /// every entry itself (as derived structs do).
+    ///
+    /// If the inner type stops early anyway, any captured fields after that
+    /// point are silently lost, and most formats will report an error about
+    /// the unconsumed entries.
+    #[inline]
+    #[must_use]
+    pub fn without_drain(mut self) -> Self {
+        self.options.drain = false;
+        self
+    }
+
+    /// Deserialize the inner type, then hand whatever it left of the map to
+    /// `remainder`, instead of draining it. The remaining entries still pass
+    /// through the capture, so captured fields among them are captured as
+    /// usual, and the access is fused, so a remainder can read until it
+    /// sees the end of the map even if the inner type already did. Anything
+    /// the remainder leaves is then drained, and the capture is `finish`ed.
+    ///
+    /// The map access can't outlive the underlying `deserialize_map` call,
+    /// so it can't be returned; the remainder is a visitor for it instead.
+    /// The inner type is read with `deserialize_map`, so field names for
+    /// [`deny_unknown_fields`][Self::deny_unknown_fields] have to come from
+    /// [`FlattenBuilder::fields`].
+    pub fn deserialize_without_drain<T, R>(self, remainder: R) -> Result<(T, R::Value), D::Error>
+    where
+        T: Deserialize<'de>,
+        R: MapRemainder<'de>,
+    {
+        let context = self.options.context;
+        let (deserializer, visitor) = self.visitor(PhantomData::<T>, None);
+
+        with_context(
+            context,
+            deserializer.deserialize_map(RemainderVisitor {
+                remainder,
+                inner: visitor.visitor,
+                capture: visitor.capture,
+                deny: visitor.deny,
+                keys: visitor.keys,
+            }),
+        )
+    }
+
+    /// Reject keys that neither the capture nor the inner type knows about,
+    /// as `#[serde(deny_unknown_fields)]` would. The inner type's field names
+    /// are taken from its call to `deserialize_struct`, so this has no effect
+    /// on inner types that use `deserialize_map`, unless their fields are
+    /// provided with [`FlattenBuilder::fields`].
+    ///
+    /// Unknown keys are reported with `unknown_field`, listing the capture's
+    /// [`fields`][KeyCapture::fields] along with the inner type's, so that a
+    /// misspelled outer field isn't reported as though only the inner fields
+    /// were valid.
+    #[inline]
+    #[must_use]
+    pub fn deny_unknown_fields(mut self) -> Self {
+        self.options.unknown = UnknownPolicy::Deny;
+        self
+    }
+
+    #[inline]
+    fn outer_fields(&self) -> &'static [&'static str] {
+        self.options
+            .outer_fields
+            .unwrap_or_else(|| self.capture.fields())
+    }
+
+    /// The outer fields followed by `inner`, if there's a `'static` list of
+    /// them; see [`KeyCapture::merged_fields`]
+    #[inline]
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        match self.options.outer_fields {
+            Some(outer) => merge_fields(outer, inner),
+            None => self.capture.merged_fields(inner),
+        }
+    }
+
+    fn visitor<V>(
+        self,
+        visitor: V,
+        inner_fields: Option<&'static [&'static str]>,
+    ) -> (D, FlattenVisitor<V, C>) {
+        let inner_fields = inner_fields.or(self.options.inner_fields);
+
+        let deny = match (self.options.unknown, self.options.allowed, inner_fields) {
+            (UnknownPolicy::Forward, _, _) | (UnknownPolicy::Deny, None, None) => None,
+            (UnknownPolicy::Deny, Some(allowed), _) => Some(DenyUnknown {
+                known: allowed,
+                expected: Ok(allowed),
+            }),
+            (UnknownPolicy::Deny, None, Some(inner)) => Some(DenyUnknown {
+                known: inner,
+                expected: self.merged_fields(inner).ok_or(ExpectedKeys {
+                    outer: self.outer_fields(),
+                    inner,
+                }),
+            }),
+        };
+
+        let keys = match self.options.allowed {
+            Some(allowed) => ExpectedKeys {
+                outer: allowed,
+                inner: &[],
+            },
+            None => ExpectedKeys {
+                outer: self.outer_fields(),
+                inner: inner_fields.unwrap_or(&[]),
+            },
+        };
+
+        (
+            self.deserializer,
+            FlattenVisitor {
+                visitor,
+                capture: self.capture,
+                drain: self.options.drain,
+                deny,
+                keys,
+            },
+        )
+    }
+}
+
+/// A builder for a [`FlattenDeserializer`], created with
+/// [`FlattenDeserializer::builder`]. A capture must be provided with
+/// [`capture`][Self::capture] before the deserializer can be used.
+pub struct FlattenBuilder<D, C> {
+    deserializer: D,
+    capture: C,
+    options: Options,
+}
+
+impl<D, C> FlattenBuilder<D, C> {
+    /// Set the capture that receives the outer struct's fields
+    #[inline]
+    #[must_use]
+    pub fn capture<C2>(self, capture: C2) -> FlattenBuilder<D, C2> {
+        FlattenBuilder {
+            deserializer: self.deserializer,
+            capture,
+            options: self.options,
+        }
+    }
+
+    /// Set whether to drain the map after the inner type's visitor returns;
+    /// see [`FlattenDeserializer::without_drain`]. The default is `true`.
+    #[inline]
+    #[must_use]
+    pub fn drain(mut self, drain: bool) -> Self {
+        self.options.drain = drain;
+        self
+    }
+
+    /// Set what happens to keys that neither the capture nor the inner type
+    /// declares. The default is [`UnknownPolicy::Forward`].
+    #[inline]
+    #[must_use]
+    pub fn unknown_policy(mut self, policy: UnknownPolicy) -> Self {
+        self.options.unknown = policy;
+        self
+    }
+
+    /// Provide the field names explicitly. `outer` replaces the capture's
+    /// [`fields`][KeyCapture::fields], and `inner` is used for the inner
+    /// type's fields if it doesn't declare them itself with
+    /// `deserialize_struct` (a hand-written type that uses
+    /// `deserialize_map`, for instance).
+    #[inline]
+    #[must_use]
+    pub fn fields(
+        mut self,
+        outer: &'static [&'static str],
+        inner: &'static [&'static str],
+    ) -> Self {
+        self.options.outer_fields = Some(outer);
+        self.options.inner_fields = Some(inner);
+        self
+    }
+
+    /// Name what's being deserialized, such as `"struct Outer"`, so that any
+    /// error from the flattened map is prefixed with it, as in ``error
+    /// deserializing struct Outer: missing field `integer` ``. This helps
+    /// when the same inner type is flattened into several outer structs.
+    #[inline]
+    #[must_use]
+    pub fn context(mut self, context: &'static str) -> Self {
+        self.options.context = Some(context);
+        self
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn build(self) -> FlattenDeserializer<D, C> {
+        FlattenDeserializer {
+            deserializer: self.deserializer,
+            capture: self.capture,
+            options: self.options,
+        }
+    }
+
+    /// Build the [`FlattenDeserializer`] and deserialize the inner type
+    /// with it
+    #[inline]
+    pub fn deserialize<'de, T>(self) -> Result<T, D::Error>
+    where
+        D: de::Deserializer<'de>,
+        C: KeyCapture<'de>,
+        T: Deserialize<'de>,
+    {
+        T::deserialize(self.build())
+    }
+}
+
+/// The known field names, when unknown fields are being denied
+#[derive(Clone, Copy)]
+struct DenyUnknown {
+    /// The keys that may be forwarded to the inner type
+    known: &'static [&'static str],
+
+    /// Every field, including the capture's, for error messages. If
+    /// there's no `'static` list of them, the outer and inner fields are
+    /// listed separately, in the same words as `unknown_field`.
+    expected: Result<&'static [&'static str], ExpectedKeys>,
+}
+
+/// The field names listed when a key is of a type that can't be a field at
+/// all. These are reported as-is, so a field the capture and the inner type
+/// share is listed twice.
+#[derive(Clone, Copy, Default)]
+struct ExpectedKeys {
+    outer: &'static [&'static str],
+    inner: &'static [&'static str],
+}
+
+impl fmt::Display for ExpectedKeys {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let mut names = self.outer.iter().chain(self.inner);
+
+        match (names.next(), names.next(), names.next()) {
+            (None, _, _) => formatter.write_str("field identifier"),
+            (Some(name), None, _) => write!(formatter, "`{name}`"),
+            (Some(first), Some(second), None) => write!(formatter, "`{first}` or `{second}`"),
+            (Some(first), Some(second), Some(third)) => {
+                write!(formatter, "one of `{first}`, `{second}`, `{third}`")?;
+                names.try_for_each(|name| write!(formatter, ", `{name}`"))
+            }
+        }
+    }
+}
+
+impl DenyUnknown {
+    /// Check a key that the capture rejected against the known fields
+    fn check<E: de::Error>(&self, key: &[u8]) -> Result<(), E> {
+        if self.known.iter().any(|field| field.as_bytes() == key) {
+            Ok(())
+        } else {
+            Err(match core::str::from_utf8(key) {
+                Ok(key) => match self.expected {
+                    Ok(expected) => E::unknown_field(key, expected),
+                    Err(keys) => E::custom(format_args!("unknown field `{key}`, expected {keys}")),
+                },
+                Err(_) => E::invalid_value(de::Unexpected::Bytes(key), &"a field name"),
+            })
         }
     }
 }
@@ -84,35 +801,669 @@ where
 {
     type Error = D::Error;
 
+    /// An inner type that calls `deserialize_any` is trusted to know that the
+    /// format is self-describing, so the underlying deserializer's
+    /// `deserialize_any` is used, and it can accept either a map or a
+    /// sequence. Any other shape is still an `invalid type` error.
     #[inline]
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserializer.deserialize_map(FlattenVisitor {
-            visitor,
-            capture: self.capture,
-        })
+        let context = self.options.context;
+        let (deserializer, visitor) = self.visitor(visitor, None);
+        with_context(context, deserializer.deserialize_any(visitor))
     }
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map struct enum identifier
+        bytes byte_buf unit unit_struct newtype_struct enum identifier
+    }
+
+    #[inline]
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let context = self.options.context;
+        let (deserializer, visitor) = self.visitor(visitor, None);
+        with_context(context, deserializer.deserialize_map(visitor))
+    }
+
+    /// A positional inner type is flattened with a
+    /// [`FlattenSeqDeserializer`]
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        with_context(
+            self.options.context,
+            FlattenSeqDeserializer::new(self.deserializer, self.capture).deserialize_seq(visitor),
+        )
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        with_context(
+            self.options.context,
+            FlattenSeqDeserializer::new(self.deserializer, self.capture)
+                .deserialize_tuple(len, visitor),
+        )
+    }
+
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        with_context(
+            self.options.context,
+            FlattenSeqDeserializer::new(self.deserializer, self.capture)
+                .deserialize_tuple_struct(name, len, visitor),
+        )
+    }
+
+    /// A flattened `Option` is `None` if the underlying deserializer has no
+    /// value at all (a JSON `null`, for instance), in which case the capture
+    /// never sees anything, and isn't `finish`ed. Otherwise, the map is
+    /// flattened as usual inside of `visit_some`.
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        with_context(
+            self.options.context,
+            self.deserializer.deserialize_option(FlattenOptionVisitor {
+                visitor,
+                capture: self.capture,
+                options: self.options,
+            }),
+        )
+    }
+
+    /// If the inner type is a struct, forward its field names, along with the
+    /// capture's, to the underlying deserializer. Unless the capture provides
+    /// the combined list with [`KeyCapture::merged_fields`], or one of the
+    /// lists is empty, this falls back to `deserialize_map`.
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let context = self.options.context;
+        let merged = self.merged_fields(fields);
+        let (deserializer, visitor) = self.visitor(visitor, Some(fields));
+
+        with_context(
+            context,
+            match merged {
+                Some(fields) => deserializer.deserialize_struct(name, fields, visitor),
+                None => deserializer.deserialize_map(visitor),
+            },
+        )
     }
 
+    /// Even if the inner value is being ignored, the map still has to be
+    /// visited so that the capture receives its fields.
     #[inline]
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
-        V: de::Visitor<'de>,
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        self.deserializer.is_human_readable()
+    }
+}
+
+/// Prefix an error with the [`FlattenBuilder::context`], if there is one
+#[inline]
+fn with_context<T, E>(context: Option<&'static str>, result: Result<T, E>) -> Result<T, E>
+where
+    E: de::Error,
+{
+    match context {
+        None => result,
+        Some(context) => {
+            result.map_err(|err| E::custom(format_args!("error deserializing {context}: {err}")))
+        }
+    }
+}
+
+/// Reads the entries of a map that a flattened inner type left behind; see
+/// [`FlattenDeserializer::deserialize_without_drain`]
+pub trait MapRemainder<'de> {
+    type Value;
+
+    /// Read as much of `map` as needed. Captured fields are removed from it
+    /// as they're found, and once it has ended it stays ended.
+    fn visit_remainder<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>;
+}
+
+/// Deserializes the inner type `T` from a map, then passes the map to a
+/// [`MapRemainder`]
+struct RemainderVisitor<T, R, C> {
+    remainder: R,
+    inner: PhantomData<T>,
+    capture: C,
+    deny: Option<DenyUnknown>,
+    keys: ExpectedKeys,
+}
+
+impl<'de, T, R, C> de::Visitor<'de> for RemainderVisitor<T, R, C>
+where
+    T: Deserialize<'de>,
+    R: MapRemainder<'de>,
+    C: KeyCapture<'de>,
+{
+    type Value = (T, R::Value);
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut map = FlattenMapAccess {
+            map: FusedAccess::new(map),
+            capture: self.capture,
+            deny: self.deny,
+            keys: self.keys,
+            captured: 0,
+        };
+
+        let value = T::deserialize(de::value::MapAccessDeserializer::new(&mut map))?;
+        let rest = self.remainder.visit_remainder(&mut map)?;
+        map.finish()?;
+
+        Ok((value, rest))
+    }
+}
+
+/// A [`FlattenSeqDeserializer`] is the positional counterpart of a
+/// [`FlattenDeserializer`], for an inner type that's deserialized from a
+/// sequence, such as a tuple struct, alongside named sibling fields in a
+/// format that writes the whole outer struct as a sequence. The capture's
+/// fields are offered to `try_send_index` by position, with the inner
+/// type's elements at [`flatten_index`][KeyCapture::flatten_index] among
+/// them:
+///
+/// ```ignore
+/// // [before, after, 10, "hello"]
+/// let inner = InnerTuple::deserialize(FlattenSeqDeserializer::new(deserializer, &mut capture))?;
+/// ```
+///
+/// A tuple asks the underlying deserializer for a tuple of the inner type's
+/// length plus the capture's field count, so formats that aren't
+/// self-describing know how many elements to read. A [`FlattenDeserializer`]
+/// switches to this automatically when the inner type calls
+/// `deserialize_seq`, `deserialize_tuple`, or `deserialize_tuple_struct`.
+pub struct FlattenSeqDeserializer<D, C> {
+    deserializer: D,
+    capture: C,
+}
+
+impl<'de, D, C> FlattenSeqDeserializer<D, C>
+where
+    D: de::Deserializer<'de>,
+    C: KeyCapture<'de>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(deserializer: D, capture: C) -> Self {
+        Self {
+            deserializer,
+            capture,
+        }
+    }
+
+    #[inline]
+    fn visitor<V>(self, visitor: V) -> (D, FlattenVisitor<V, C>) {
+        (
+            self.deserializer,
+            FlattenVisitor {
+                visitor,
+                capture: self.capture,
+                drain: true,
+                deny: None,
+                keys: ExpectedKeys::default(),
+            },
+        )
+    }
+}
+
+impl<'de, D, C> de::Deserializer<'de> for FlattenSeqDeserializer<D, C>
+where
+    D: de::Deserializer<'de>,
+    C: KeyCapture<'de>,
+{
+    type Error = D::Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+
+    #[inline]
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let (deserializer, visitor) = self.visitor(visitor);
+        deserializer.deserialize_seq(visitor)
+    }
+
+    #[inline]
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = len + self.capture.fields().len();
+        let (deserializer, visitor) = self.visitor(visitor);
+        deserializer.deserialize_tuple(len, visitor)
+    }
+
+    /// The name is the inner type's, not the outer struct's, so this is
+    /// read as a plain tuple
+    #[inline]
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        self.deserializer.is_human_readable()
+    }
+}
+
+struct FlattenOptionVisitor<V, C> {
+    visitor: V,
+    capture: C,
+    options: Options,
+}
+
+impl<'de, V, C> de::Visitor<'de> for FlattenOptionVisitor<V, C>
+where
+    V: de::Visitor<'de>,
+    C: KeyCapture<'de>,
+{
+    type Value = V::Value;
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    #[inline]
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_none()
+    }
+
+    #[inline]
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_none()
+    }
+
+    #[inline]
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        // The context is added once, around the whole option
+        self.visitor.visit_some(FlattenDeserializer {
+            deserializer,
+            capture: self.capture,
+            options: Options {
+                context: None,
+                ..self.options
+            },
+        })
+    }
+}
+
+/// Deserialize only the captured fields, skipping everything else. This is
+/// equivalent to flattening an [`IgnoredAny`][de::IgnoredAny]: every key the
+/// capture rejects is ignored without being deserialized into anything, which
+/// makes this an efficient way to pull a few fields out of a large document.
+pub fn deserialize_capture_only<'de, D, C>(deserializer: D, capture: C) -> Result<(), D::Error>
+where
+    D: de::Deserializer<'de>,
+    C: KeyCapture<'de>,
+{
+    de::IgnoredAny::deserialize(FlattenDeserializer::new(deserializer, capture)).map(|_| ())
+}
+
+/// Deserialize every key the capture rejects into an existing map, as with a
+/// `#[serde(flatten)]` catch-all map field. Entries are added with
+/// [`Extend`] one at a time, in input order, as soon as each one is read, so
+/// a map that preserves insertion order (or a `Vec` of pairs) ends up in the
+/// same order as the input, and nothing is buffered beyond the map itself.
+pub fn deserialize_into_map<'de, D, C, M, K, V>(
+    deserializer: D,
+    capture: C,
+    map: &mut M,
+) -> Result<(), D::Error>
+where
+    D: de::Deserializer<'de>,
+    C: KeyCapture<'de>,
+    M: Extend<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    de::Deserializer::deserialize_map(
+        FlattenDeserializer::new(deserializer, capture),
+        ExtendVisitor {
+            map,
+            entry: PhantomData,
+        },
+    )
+}
+
+struct ExtendVisitor<'a, M, K, V> {
+    map: &'a mut M,
+    entry: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'de, M, K, V> de::Visitor<'de> for ExtendVisitor<'_, M, K, V>
+where
+    M: Extend<(K, V)>,
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<(), A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        while let Some(entry) = map.next_entry()? {
+            self.map.extend(Some(entry));
+        }
+
+        Ok(())
+    }
+}
+
+/// An internally tagged enum (`#[serde(tag = "...")]`) that can be
+/// deserialized by [`deserialize_tagged`]. serde's own implementation buffers
+/// the whole map to find the tag; instead, this selects the variant by name,
+/// once the tag has been read, and deserializes it from the rest of the map.
+pub trait TaggedEnum<'de>: Sized {
+    /// The name of the tag field
+    const TAG: &'static str;
+
+    /// The names of the variants, as they appear as values of the tag
+    const VARIANTS: &'static [&'static str];
+
+    /// Deserialize the fields of the named variant. `variant` is always one
+    /// of `VARIANTS`.
+    fn deserialize_variant<D>(variant: &'static str, deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>;
+}
+
+/// Deserialize an internally tagged enum that has been flattened alongside
+/// the fields of `capture`. Keys are offered to the capture until the tag is
+/// found; the tag is then used to select the variant, which is deserialized
+/// through a [`FlattenDeserializer`] over the remainder of the map, so the
+/// capture keeps receiving its fields.
+///
+/// Nothing is buffered, so the tag must appear before any of the variant's
+/// own fields (though it may come after the capture's fields). A variant
+/// field that appears before the tag is an error.
+pub fn deserialize_tagged<'de, D, C, T>(deserializer: D, capture: C) -> Result<T, D::Error>
+where
+    D: de::Deserializer<'de>,
+    C: KeyCapture<'de>,
+    T: TaggedEnum<'de>,
+{
+    deserializer.deserialize_map(TaggedVisitor {
+        capture,
+        value: PhantomData,
+    })
+}
+
+struct TaggedVisitor<C, T> {
+    capture: C,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<'de, C, T> de::Visitor<'de> for TaggedVisitor<C, T>
+where
+    C: KeyCapture<'de>,
+    T: TaggedEnum<'de>,
+{
+    type Value = T;
+
+    #[inline]
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    fn visit_map<A>(mut self, mut map: A) -> Result<T, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let seed = TagKeySeed { tag: T::TAG };
+
+        // Send keys to the capture until we find the tag
+        loop {
+            let keys = ExpectedKeys {
+                outer: self.capture.fields(),
+                inner: &[],
+            };
+
+            match map.next_key_seed(FlattenKeySeed::new(seed, &mut self.capture, None, keys))? {
+                None => return Err(de::Error::missing_field(T::TAG)),
+                Some(FlattenKeySeedOutcome::Rejected(())) => break,
+                Some(FlattenKeySeedOutcome::Accepted(_, token)) => {
+                    map.next_value_seed(FlattenValueSeed {
+                        token,
+                        capture: &mut self.capture,
+                    })?
+                }
+            }
+        }
+
+        let variant = map.next_value_seed(VariantSeed {
+            variants: T::VARIANTS,
+        })?;
+
+        T::deserialize_variant(
+            variant,
+            FlattenDeserializer::new(de::value::MapAccessDeserializer::new(map), self.capture),
+        )
+    }
+}
+
+/// Seed for a key that the capture rejected before the tag was found. Only
+/// the tag itself is allowed.
+#[derive(Clone, Copy)]
+struct TagKeySeed {
+    tag: &'static str,
+}
+
+impl<'de> de::DeserializeSeed<'de> for TagKeySeed {
+    type Value = ();
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for TagKeySeed {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "the `{}` tag", self.tag)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        if v == self.tag.as_bytes() {
+            Ok(())
+        } else {
+            Err(E::custom(format_args!(
+                "the `{}` tag must appear before the variant's fields",
+                self.tag
+            )))
+        }
+    }
+}
+
+/// Seed for the tag's value, which must be one of the variant names. Tag
+/// values of the wrong type are reported as expecting the variant names, as
+/// unknown variants are.
+struct VariantSeed {
+    variants: &'static [&'static str],
+}
+
+impl<'de> de::DeserializeSeed<'de> for VariantSeed {
+    type Value = &'static str;
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for VariantSeed {
+    type Value = &'static str;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.variants {
+            [] => formatter.write_str("variant identifier"),
+            variants => fmt::Display::fmt(
+                &ExpectedKeys {
+                    outer: variants,
+                    inner: &[],
+                },
+                formatter,
+            ),
+        }
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.variants
+            .iter()
+            .copied()
+            .find(|&variant| variant == v)
+            .ok_or_else(|| E::unknown_variant(v, self.variants))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
     {
-        self.deserializer.deserialize_ignored_any(visitor)
+        match core::str::from_utf8(v) {
+            Ok(v) => self.visit_str(v),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+/// Combine two lists of field names, if that can be done without building a
+/// new list: that is, if one of them is empty. This is the default
+/// [`KeyCapture::merged_fields`].
+///
+/// ```
+/// use serde_bufferless::private::flatten::merge_fields;
+///
+/// assert_eq!(merge_fields(&[], &["a", "b"]), Some(&["a", "b"][..]));
+/// assert_eq!(merge_fields(&["a"], &["b"]), None);
+/// ```
+#[inline]
+#[must_use]
+pub fn merge_fields(
+    first: &'static [&'static str],
+    second: &'static [&'static str],
+) -> Option<&'static [&'static str]> {
+    if first.is_empty() {
+        Some(second)
+    } else if second.is_empty() {
+        Some(first)
+    } else {
+        None
     }
 }
 
+/// Only `visit_map` and `visit_seq` are implemented; every other `visit_*`
+/// falls back to serde's default, which reports an `invalid type` against
+/// [`expecting`][de::Visitor::expecting] and so against the capture's.
 struct FlattenVisitor<V, C> {
     visitor: V,
     capture: C,
+    drain: bool,
+    deny: Option<DenyUnknown>,
+    keys: ExpectedKeys,
 }
 
 impl<'de, V, C> de::Visitor<'de> for FlattenVisitor<V, C>
@@ -138,21 +1489,151 @@ where
         let mut map = FlattenMapAccess {
             map: FusedAccess::new(map),
             capture: self.capture,
+            deny: self.deny,
+            keys: self.keys,
+            captured: 0,
         };
 
         let value = self.visitor.visit_map(&mut map)?;
 
         // Drain remaining values from the map. This ensures that, if the
         // visitor left any behind, they're still propagated to the capture.
-        let _ = de::IgnoredAny::deserialize(de::value::MapAccessDeserializer::new(&mut map))?;
+        // `IgnoredAny`'s `visit_map` is the same `next_key`/`next_value`
+        // loop we'd write by hand, and the `MapAccessDeserializer` around it
+        // inlines away: a hand-written loop measured the same in the `drain
+        // trailing keys` benchmark.
+        if self.drain {
+            map.drain()?;
+        }
+
+        map.capture.finish()?;
+        Ok(value)
+    }
+
+    /// Formats that serialize structs as sequences identify fields by their
+    /// position, so the capture's fields are taken from the sequence before
+    /// and after the inner value's, as described by
+    /// [`KeyCapture::flatten_index`].
+    fn visit_seq<A>(mut self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let flatten_index = self.capture.flatten_index();
+        let len = self.capture.fields().len();
+
+        for index in 0..flatten_index {
+            send_element(&mut self.capture, &mut seq, index)?;
+        }
 
+        let value = self.visitor.visit_seq(&mut seq)?;
+
+        for index in flatten_index..len {
+            send_element(&mut self.capture, &mut seq, index)?;
+        }
+
+        self.capture.finish()?;
         Ok(value)
     }
 }
 
-struct FlattenMapAccess<M, C> {
+/// Send the next element of a sequence to the capture, as the field at
+/// `index`
+fn send_element<'de, C, A>(capture: &mut C, seq: &mut A, index: usize) -> Result<(), A::Error>
+where
+    C: KeyCapture<'de>,
+    A: de::SeqAccess<'de>,
+{
+    let token = capture.try_send_index(index as u64).ok_or_else(|| {
+        de::Error::custom(format_args!(
+            "the capture rejected its own field at index {index}"
+        ))
+    })?;
+
+    seq.next_element_seed(FlattenValueSeed { token, capture })?
+        .ok_or_else(|| de::Error::invalid_length(index, &CaptureExpecting(capture)))
+}
+
+/// Adapts a capture's `expecting` into a [`de::Expected`]
+struct CaptureExpecting<'a, C>(&'a C);
+
+impl<'de, C> de::Expected for CaptureExpecting<'_, C>
+where
+    C: KeyCapture<'de>,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+}
+
+/// A [`de::MapAccess`] that offers each key to a capture before returning
+/// it, returning only the keys the capture rejects. This is what a
+/// [`FlattenDeserializer`] passes to the inner type's `visit_map`; it's
+/// public for outer types with a hand-written `Visitor` that need the map
+/// access itself, to handle some keys specially, while still forwarding the
+/// captured ones.
+///
+/// Whoever reads from it must finish the map: if they stop before the end,
+/// the remaining entries (which may include captured fields) are never seen.
+/// [`finish`][Self::finish] drains whatever is left through the capture and
+/// then calls the capture's [`finish`][KeyCapture::finish], as the
+/// [`FlattenDeserializer`] does once the inner type returns.
+///
+/// ```ignore
+/// fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+/// where
+///     A: de::MapAccess<'de>,
+/// {
+///     let mut map = FlattenMapAccess::new(map, &mut capture);
+///     let inner = Inner::deserialize(MapAccessDeserializer::new(&mut map))?;
+///     map.finish()?;
+///     // assemble the outer struct from `capture` and `inner`
+/// }
+/// ```
+pub struct FlattenMapAccess<M, C> {
     map: FusedAccess<M>,
     capture: C,
+    deny: Option<DenyUnknown>,
+    keys: ExpectedKeys,
+
+    /// The number of entries sent to the capture so far
+    captured: usize,
+}
+
+impl<'de, M, C> FlattenMapAccess<M, C>
+where
+    M: de::MapAccess<'de>,
+    C: KeyCapture<'de>,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(map: M, capture: C) -> Self {
+        let keys = ExpectedKeys {
+            outer: capture.fields(),
+            inner: &[],
+        };
+
+        Self {
+            map: FusedAccess::new(map),
+            capture,
+            deny: None,
+            keys,
+            captured: 0,
+        }
+    }
+
+    /// Read the rest of the map, sending captured fields to the capture and
+    /// ignoring everything else
+    pub fn drain(&mut self) -> Result<(), M::Error> {
+        de::IgnoredAny::deserialize(de::value::MapAccessDeserializer::new(self)).map(|_| ())
+    }
+
+    /// [`drain`][Self::drain] the map, then call the capture's
+    /// [`finish`][KeyCapture::finish], returning the capture
+    pub fn finish(mut self) -> Result<C, M::Error> {
+        self.drain()?;
+        self.capture.finish()?;
+        Ok(self.capture)
+    }
 }
 
 impl<'de, M, C> de::MapAccess<'de> for FlattenMapAccess<M, C>
@@ -172,12 +1653,20 @@ where
         // only returning it if the capture didn't want it. We do this
         // repeatedly until we can return something.
         loop {
-            seed = match self.map.next_key_seed(FlattenKeySeed { seed, capture })? {
+            if self.deny.is_none() && capture.is_complete() {
+                return self.map.next_key_seed(seed);
+            }
+
+            seed = match self
+                .map
+                .next_key_seed(FlattenKeySeed::new(seed, capture, self.deny, self.keys))?
+            {
                 None => return Ok(None),
                 Some(FlattenKeySeedOutcome::Rejected(value)) => return Ok(Some(value)),
                 Some(FlattenKeySeedOutcome::Accepted(seed, token)) => {
                     self.map
                         .next_value_seed(FlattenValueSeed { token, capture })?;
+                    self.captured += 1;
                     seed
                 }
             }
@@ -191,11 +1680,126 @@ where
     {
         self.map.next_value_seed(seed)
     }
+
+    /// The underlying map's remaining entries include the capture's fields
+    /// that haven't been seen yet, which the inner type will never receive,
+    /// so those are subtracted. This assumes each of the capture's fields
+    /// appears at most once.
+    #[inline]
+    fn size_hint(&self) -> Option<usize> {
+        let uncaptured = self.capture.fields().len().saturating_sub(self.captured);
+        self.map
+            .size_hint()
+            .map(|hint| hint.saturating_sub(uncaptured))
+    }
 }
 
-struct FlattenKeySeed<'a, S, C> {
+struct FlattenKeySeed<'a, S, C, N = TopLevelKey> {
     seed: S,
     capture: &'a mut C,
+    deny: Option<DenyUnknown>,
+    keys: ExpectedKeys,
+    nesting: PhantomData<N>,
+}
+
+impl<'a, S, C> FlattenKeySeed<'a, S, C> {
+    #[inline]
+    #[must_use]
+    fn new(seed: S, capture: &'a mut C, deny: Option<DenyUnknown>, keys: ExpectedKeys) -> Self {
+        Self {
+            seed,
+            capture,
+            deny,
+            keys,
+            nesting: PhantomData,
+        }
+    }
+}
+
+/// Some formats may wrap a key in an `Option`, so `FlattenKeySeed` looks
+/// through one layer of `visit_some` to offer the contained key to the
+/// capture. This is tracked at the type level, so that a nested `Some`
+/// doesn't instantiate an infinitely recursive chain of seeds.
+trait KeyNesting: Sized {
+    fn visit_some<'a, 'de, S, C, D>(
+        seed: FlattenKeySeed<'a, S, C, Self>,
+        deserializer: D,
+    ) -> Result<FlattenKeySeedOutcome<'de, C::Token, S>, D::Error>
+    where
+        S: de::DeserializeSeed<'de>,
+        C: KeyCapture<'de>,
+        D: de::Deserializer<'de>;
+}
+
+/// The key hasn't been unwrapped from a `Some`
+struct TopLevelKey;
+
+/// The key has already been unwrapped from a `Some`
+struct SomeKey;
+
+impl KeyNesting for TopLevelKey {
+    fn visit_some<'a, 'de, S, C, D>(
+        seed: FlattenKeySeed<'a, S, C, Self>,
+        deserializer: D,
+    ) -> Result<FlattenKeySeedOutcome<'de, C::Token, S>, D::Error>
+    where
+        S: de::DeserializeSeed<'de>,
+        C: KeyCapture<'de>,
+        D: de::Deserializer<'de>,
+    {
+        let seed = FlattenKeySeed {
+            seed: SomeSeed { seed: seed.seed },
+            capture: seed.capture,
+            deny: seed.deny,
+            keys: seed.keys,
+            nesting: PhantomData::<SomeKey>,
+        };
+
+        deserializer
+            .deserialize_identifier(seed)
+            .map(|outcome| match outcome {
+                FlattenKeySeedOutcome::Accepted(SomeSeed { seed }, token) => {
+                    FlattenKeySeedOutcome::Accepted(seed, token)
+                }
+                FlattenKeySeedOutcome::Rejected(value) => FlattenKeySeedOutcome::Rejected(value),
+            })
+    }
+}
+
+impl KeyNesting for SomeKey {
+    #[inline]
+    fn visit_some<'a, 'de, S, C, D>(
+        seed: FlattenKeySeed<'a, S, C, Self>,
+        deserializer: D,
+    ) -> Result<FlattenKeySeedOutcome<'de, C::Token, S>, D::Error>
+    where
+        S: de::DeserializeSeed<'de>,
+        C: KeyCapture<'de>,
+        D: de::Deserializer<'de>,
+    {
+        seed.send_to_seed(SomeDeserializer::new(deserializer))
+    }
+}
+
+/// Seed that rewraps a deserializer in a `Some` before passing it to `seed`,
+/// for keys that were unwrapped in the hope the capture wanted them.
+struct SomeSeed<S> {
+    seed: S,
+}
+
+impl<'de, S> de::DeserializeSeed<'de> for SomeSeed<S>
+where
+    S: de::DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.seed.deserialize(SomeDeserializer::new(deserializer))
+    }
 }
 
 enum FlattenKeySeedOutcome<'de, T, S: de::DeserializeSeed<'de>> {
@@ -208,10 +1812,11 @@ enum FlattenKeySeedOutcome<'de, T, S: de::DeserializeSeed<'de>> {
     Rejected(S::Value),
 }
 
-impl<'a, 'de, S, C> de::DeserializeSeed<'de> for FlattenKeySeed<'a, S, C>
+impl<'a, 'de, S, C, N> de::DeserializeSeed<'de> for FlattenKeySeed<'a, S, C, N>
 where
     S: de::DeserializeSeed<'de>,
     C: KeyCapture<'de>,
+    N: KeyNesting,
 {
     type Value = FlattenKeySeedOutcome<'de, C::Token, S>;
 
@@ -224,7 +1829,7 @@ where
     }
 }
 
-impl<'a, 'de, S, C> FlattenKeySeed<'a, S, C>
+impl<'a, 'de, S, C, N> FlattenKeySeed<'a, S, C, N>
 where
     S: de::DeserializeSeed<'de>,
     C: KeyCapture<'de>,
@@ -264,33 +1869,47 @@ where
     {
         match self.capture.try_send_key(key.as_ref()) {
             Some(token) => Ok(FlattenKeySeedOutcome::Accepted(self.seed, token)),
-            None => self.send_to_seed(into_de(key)),
+            None => {
+                if let Some(deny) = self.deny {
+                    deny.check(key.as_ref())?;
+                }
+
+                self.send_to_seed(into_de(key))
+            }
         }
     }
 
+    /// Offer a non-string key to the capture with one of its `try_send_*`
+    /// methods, forwarding it to the seed if the capture rejects it.
     #[inline]
-    fn send_into_to_capture<T, E>(
+    fn send_scalar_to_capture<T, E>(
         self,
+        try_send: impl FnOnce(&mut C) -> Option<C::Token>,
         key: T,
     ) -> Result<FlattenKeySeedOutcome<'de, C::Token, S>, E>
     where
-        T: AsRef<[u8]>,
         T: de::IntoDeserializer<'de, E>,
         E: de::Error,
     {
-        self.send_to_capture(key, |key| key.into_deserializer())
+        match try_send(self.capture) {
+            Some(token) => Ok(FlattenKeySeedOutcome::Accepted(self.seed, token)),
+            None => self.send_into_to_seed(key),
+        }
     }
 }
 
-impl<'a, 'de, S, C> de::Visitor<'de> for FlattenKeySeed<'a, S, C>
+impl<'a, 'de, S, C, N> de::Visitor<'de> for FlattenKeySeed<'a, S, C, N>
 where
     S: de::DeserializeSeed<'de>,
     C: KeyCapture<'de>,
+    N: KeyNesting,
 {
     type Value = FlattenKeySeedOutcome<'de, C::Token, S>;
 
+    /// Formats that reject a key outright report it as one of the capture's
+    /// fields or the inner type's, when the inner type's are known
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "field identifier")
+        fmt::Display::fmt(&self.keys, formatter)
     }
 
     #[inline]
@@ -298,7 +1917,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_bool(v), v)
     }
 
     #[inline]
@@ -306,7 +1925,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_int(i64::from(v)), v)
     }
 
     #[inline]
@@ -314,7 +1933,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_int(i64::from(v)), v)
     }
 
     #[inline]
@@ -322,7 +1941,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_int(i64::from(v)), v)
     }
 
     #[inline]
@@ -330,25 +1949,23 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_int(v), v)
     }
 
-    serde_if_integer128! {
-        #[inline]
-        fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            self.send_into_to_seed(v)
-        }
+    #[inline]
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_into_to_seed(v)
+    }
 
-        #[inline]
-        fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
-        where
-            E: de::Error,
-        {
-            self.send_into_to_seed(v)
-        }
+    #[inline]
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_into_to_seed(v)
     }
 
     #[inline]
@@ -356,7 +1973,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_index(u64::from(v)), v)
     }
 
     #[inline]
@@ -364,7 +1981,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_index(u64::from(v)), v)
     }
 
     #[inline]
@@ -372,7 +1989,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_index(u64::from(v)), v)
     }
 
     #[inline]
@@ -380,7 +1997,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_index(v), v)
     }
 
     #[inline]
@@ -388,7 +2005,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_float(f64::from(v)), v)
     }
 
     #[inline]
@@ -396,7 +2013,7 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_seed(v)
+        self.send_scalar_to_capture(|capture| capture.try_send_float(v), v)
     }
 
     #[inline]
@@ -412,16 +2029,16 @@ where
     where
         E: de::Error,
     {
-        self.send_into_to_capture(v)
+        self.send_to_capture(v, de::value::StrDeserializer::new)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
-    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    fn visit_string<E>(self, v: alloc::string::String) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        self.send_into_to_capture(v)
+        self.send_to_capture(v, de::value::StringDeserializer::new)
     }
 
     #[inline]
@@ -440,9 +2057,9 @@ where
         self.send_to_capture(v, de::value::BytesDeserializer::new)
     }
 
-    #[cfg(feature = "std")]
+    #[cfg(feature = "alloc")]
     #[inline]
-    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    fn visit_byte_buf<E>(self, v: alloc::vec::Vec<u8>) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
@@ -472,7 +2089,7 @@ where
     where
         D: serde::Deserializer<'de>,
     {
-        self.send_to_seed(SomeDeserializer::new(deserializer))
+        N::visit_some(self, deserializer)
     }
 
     #[inline]
@@ -507,12 +2124,242 @@ where
         self.send_to_seed(de::value::MapAccessDeserializer::new(map))
     }
 
-    #[inline]
+    /// Some formats emit a key as a unit variant, such as a field identifier
+    /// enum that was serialized as-is. The variant's name (or index) is
+    /// offered to the capture like any other key, and if the capture accepts
+    /// it, the variant must be a unit variant. A rejected key is replayed to
+    /// the seed as the same enum.
     fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
     where
         A: de::EnumAccess<'de>,
     {
-        self.send_to_seed(EnumDeserializer::new(data))
+        let (key, variant) = data.variant_seed(VariantKeySeed {
+            capture: &mut *self.capture,
+            deny: self.deny,
+        })?;
+
+        match key {
+            VariantKeyOutcome::Accepted(token) => {
+                de::VariantAccess::unit_variant(variant)?;
+                Ok(FlattenKeySeedOutcome::Accepted(self.seed, token))
+            }
+            VariantKeyOutcome::Rejected(key) => {
+                self.send_to_seed(EnumDeserializer::new(ReplayEnumAccess { key, variant }))
+            }
+        }
+    }
+}
+
+/// The identifier of an enum-shaped key that the capture rejected, kept so
+/// that it can be replayed to the seed. Identifiers that don't borrow from
+/// the input are copied, which needs `alloc`.
+enum VariantKey<'de> {
+    Str(&'de str),
+    Bytes(&'de [u8]),
+    Index(u64),
+    #[cfg(feature = "alloc")]
+    String(alloc::string::String),
+    #[cfg(feature = "alloc")]
+    ByteBuf(alloc::vec::Vec<u8>),
+}
+
+enum VariantKeyOutcome<'de, T> {
+    Accepted(T),
+    Rejected(VariantKey<'de>),
+}
+
+/// Seed for the identifier of an enum-shaped key. Like `FlattenKeySeed`,
+/// it offers the identifier to the capture while the deserializer still
+/// holds it, so a transient name is only copied when the capture rejects
+/// it and it has to be replayed.
+struct VariantKeySeed<'a, C> {
+    capture: &'a mut C,
+    deny: Option<DenyUnknown>,
+}
+
+impl<'a, C> VariantKeySeed<'a, C> {
+    /// Offer a name to the capture, checking it against the known fields if
+    /// it's rejected. `keep` turns a rejected name into something that can
+    /// be replayed.
+    #[inline]
+    fn send_key<'de, E>(
+        self,
+        key: &[u8],
+        keep: impl FnOnce() -> Result<VariantKey<'de>, E>,
+    ) -> Result<VariantKeyOutcome<'de, C::Token>, E>
+    where
+        C: KeyCapture<'de>,
+        E: de::Error,
+    {
+        match self.capture.try_send_key(key) {
+            Some(token) => Ok(VariantKeyOutcome::Accepted(token)),
+            None => {
+                if let Some(deny) = self.deny {
+                    deny.check(key)?;
+                }
+
+                keep().map(VariantKeyOutcome::Rejected)
+            }
+        }
+    }
+}
+
+impl<'a, 'de, C> de::DeserializeSeed<'de> for VariantKeySeed<'a, C>
+where
+    C: KeyCapture<'de>,
+{
+    type Value = VariantKeyOutcome<'de, C::Token>;
+
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'a, 'de, C> de::Visitor<'de> for VariantKeySeed<'a, C>
+where
+    C: KeyCapture<'de>,
+{
+    type Value = VariantKeyOutcome<'de, C::Token>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a variant identifier")
+    }
+
+    #[inline]
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(match self.capture.try_send_index(v) {
+            Some(token) => VariantKeyOutcome::Accepted(token),
+            None => VariantKeyOutcome::Rejected(VariantKey::Index(v)),
+        })
+    }
+
+    #[inline]
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_key(v.as_bytes(), || Ok(VariantKey::Str(v)))
+    }
+
+    #[inline]
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_key(v, || Ok(VariantKey::Bytes(v)))
+    }
+
+    #[inline]
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_key(v.as_bytes(), || {
+            #[cfg(feature = "alloc")]
+            return Ok(VariantKey::String(v.into()));
+
+            #[cfg(not(feature = "alloc"))]
+            return Err(E::invalid_type(
+                de::Unexpected::Str(v),
+                &"a borrowed variant identifier",
+            ));
+        })
+    }
+
+    #[inline]
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.send_key(v, || {
+            #[cfg(feature = "alloc")]
+            return Ok(VariantKey::ByteBuf(v.into()));
+
+            #[cfg(not(feature = "alloc"))]
+            return Err(E::invalid_type(
+                de::Unexpected::Bytes(v),
+                &"a borrowed variant identifier",
+            ));
+        })
+    }
+}
+
+impl<'de, E> de::IntoDeserializer<'de, E> for VariantKey<'de>
+where
+    E: de::Error,
+{
+    type Deserializer = VariantKeyDeserializer<'de, E>;
+
+    #[inline]
+    fn into_deserializer(self) -> Self::Deserializer {
+        VariantKeyDeserializer {
+            key: self,
+            error: PhantomData,
+        }
+    }
+}
+
+struct VariantKeyDeserializer<'de, E> {
+    key: VariantKey<'de>,
+    error: PhantomData<E>,
+}
+
+impl<'de, E> de::Deserializer<'de> for VariantKeyDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.key {
+            VariantKey::Str(key) => visitor.visit_borrowed_str(key),
+            VariantKey::Bytes(key) => visitor.visit_borrowed_bytes(key),
+            VariantKey::Index(key) => visitor.visit_u64(key),
+            #[cfg(feature = "alloc")]
+            VariantKey::String(key) => visitor.visit_string(key),
+            #[cfg(feature = "alloc")]
+            VariantKey::ByteBuf(key) => visitor.visit_byte_buf(key),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// An enum whose variant identifier has already been read, for replaying a
+/// rejected enum-shaped key to the inner type
+struct ReplayEnumAccess<'de, V> {
+    key: VariantKey<'de>,
+    variant: V,
+}
+
+impl<'de, V> de::EnumAccess<'de> for ReplayEnumAccess<'de, V>
+where
+    V: de::VariantAccess<'de>,
+{
+    type Error = V::Error;
+    type Variant = V;
+
+    #[inline]
+    fn variant_seed<S>(self, seed: S) -> Result<(S::Value, V), V::Error>
+    where
+        S: de::DeserializeSeed<'de>,
+    {
+        let key = seed.deserialize(de::IntoDeserializer::into_deserializer(self.key))?;
+        Ok((key, self.variant))
     }
 }
 
@@ -532,6 +2379,15 @@ where
     where
         D: serde::Deserializer<'de>,
     {
-        self.capture.send_value(self.token, deserializer)
+        let name = self.capture.field_name(&self.token);
+
+        self.capture
+            .send_value(self.token, deserializer)
+            .map_err(|err| match name {
+                None => err,
+                Some(name) => {
+                    de::Error::custom(format_args!("error deserializing field `{name}`: {err}"))
+                }
+            })
     }
 }
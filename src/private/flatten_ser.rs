@@ -0,0 +1,268 @@
+/*!
+The serialization counterpart of [`flatten`][super::flatten]. An outer
+struct with a `#[serde(flatten)]` field serializes itself as a map, emitting
+its own fields with `serialize_entry`, and serializes the flattened value
+into the same map through a [`FlattenSerializer`], which turns each of the
+inner value's fields into another entry of the outer map as it's produced.
+Nothing is collected first, so the entries appear in the output in exactly
+the order the outer struct emits them.
+
+```ignore
+let mut map = serializer.serialize_map(None)?;
+map.serialize_entry("before", &self.before)?;
+map.serialize_entry("after", &self.after)?;
+self.inner.serialize(FlattenSerializer::new(&mut map))?;
+map.end()
+```
+*/
+
+use core::fmt::Display;
+
+use serde::ser::{self, Error as _, Impossible, Serialize, SerializeMap};
+
+/// A [`FlattenSerializer`] serializes a flattened value into an outer
+/// struct's [`SerializeMap`]. Structs and maps have their entries forwarded
+/// to the outer map; `None`, `()`, and unit structs add nothing, as with
+/// serde's own flatten; and newtype variants are added as a single entry
+/// keyed by the variant name.
+///
+/// Anything else can't be flattened, and is rejected with a custom error.
+/// That includes struct and tuple variants, which serde only supports by
+/// buffering the variant's fields.
+pub struct FlattenSerializer<'a, M> {
+    map: &'a mut M,
+}
+
+impl<'a, M> FlattenSerializer<'a, M>
+where
+    M: SerializeMap,
+{
+    #[inline]
+    #[must_use]
+    pub fn new(map: &'a mut M) -> Self {
+        Self { map }
+    }
+
+    fn unsupported(what: impl Display) -> M::Error {
+        M::Error::custom(format_args!(
+            "can only flatten structs and maps (got {what})"
+        ))
+    }
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($ty:ty),*) = $what:literal,)*) => {$(
+        #[inline]
+        fn $method(self, $(_: $ty),*) -> Result<Self::Ok, Self::Error> {
+            Err(Self::unsupported($what))
+        }
+    )*};
+}
+
+impl<'a, M> ser::Serializer for FlattenSerializer<'a, M>
+where
+    M: SerializeMap,
+{
+    type Ok = ();
+    type Error = M::Error;
+
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = FlattenSerializeMap<'a, M>;
+    type SerializeStruct = FlattenSerializeMap<'a, M>;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    unsupported! {
+        serialize_bool(bool) = "a boolean",
+        serialize_i8(i8) = "an integer",
+        serialize_i16(i16) = "an integer",
+        serialize_i32(i32) = "an integer",
+        serialize_i64(i64) = "an integer",
+        serialize_i128(i128) = "an integer",
+        serialize_u8(u8) = "an integer",
+        serialize_u16(u16) = "an integer",
+        serialize_u32(u32) = "an integer",
+        serialize_u64(u64) = "an integer",
+        serialize_u128(u128) = "an integer",
+        serialize_f32(f32) = "a float",
+        serialize_f64(f64) = "a float",
+        serialize_char(char) = "a char",
+        serialize_str(&str) = "a string",
+        serialize_bytes(&[u8]) = "a byte array",
+        serialize_unit_variant(&'static str, u32, &'static str) = "a unit variant",
+    }
+
+    #[inline]
+    fn collect_str<T>(self, _value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Display,
+    {
+        Err(Self::unsupported("a string"))
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_entry(variant, value)
+    }
+
+    #[inline]
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(Self::unsupported("a sequence"))
+    }
+
+    #[inline]
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(Self::unsupported("a tuple"))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(Self::unsupported("a tuple struct"))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(Self::unsupported("a tuple variant"))
+    }
+
+    #[inline]
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, M::Error> {
+        Ok(FlattenSerializeMap { map: self.map })
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, M::Error> {
+        Ok(FlattenSerializeMap { map: self.map })
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(Self::unsupported("a struct variant"))
+    }
+}
+
+/// Forwards the flattened value's entries to the outer map
+pub struct FlattenSerializeMap<'a, M> {
+    map: &'a mut M,
+}
+
+impl<M> SerializeMap for FlattenSerializeMap<'_, M>
+where
+    M: SerializeMap,
+{
+    type Ok = ();
+    type Error = M::Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_key(key)
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_value(value)
+    }
+
+    #[inline]
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), M::Error>
+    where
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
+    {
+        self.map.serialize_entry(key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<M> ser::SerializeStruct for FlattenSerializeMap<'_, M>
+where
+    M: SerializeMap,
+{
+    type Ok = ();
+    type Error = M::Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.map.serialize_entry(key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+/*!
+An alternative to [`FlattenDeserializer`] for sources that hold all of their
+entries in memory before deserialization begins, such as a
+`serde_json::Value`.
+
+serde doesn't expose whether a deserializer has all of its keys available
+up front, and without specialization there's no way to detect it, so this
+path is opt-in per source: the source implements [`EntrySource`], and the
+caller uses [`deserialize_upfront`] in place of [`FlattenDeserializer`].
+Every entry is offered to the capture first, in a single pass, and the inner
+type is then deserialized from only the entries the capture rejected. The
+inner type never sees a captured key, and no routing happens per key while
+it's being deserialized.
+
+This isn't a speedup in practice: the rejected entries have to be collected
+before the inner type can see them, and for a `serde_json::Value` that makes
+this path roughly twice as slow as streaming the same value through a
+[`FlattenDeserializer`] (see `benches/upfront.rs`). It's useful when the inner
+type must only see its own keys.
+
+[`FlattenDeserializer`]: super::flatten::FlattenDeserializer
+*/
+
+use alloc::vec::Vec;
+
+use serde::de::{self, value::MapDeserializer, Deserialize, IntoDeserializer};
+
+use super::flatten::KeyCapture;
+
+/// A map-shaped source that can hand over all of its entries at once.
+pub trait EntrySource<'de> {
+    type Error: de::Error;
+
+    /// The key type. Keys are offered to the capture as bytes, and rejected
+    /// keys are deserialized by the inner type as usual.
+    type Key: AsRef<[u8]> + IntoDeserializer<'de, Self::Error>;
+    type Value: IntoDeserializer<'de, Self::Error>;
+    type Entries: IntoIterator<Item = (Self::Key, Self::Value)>;
+
+    /// Take all of the entries in this source. This should fail with an
+    /// `invalid_type` error if the source isn't a map.
+    fn into_entries(self) -> Result<Self::Entries, Self::Error>;
+}
+
+/// Deserialize a flattened `T` from an [`EntrySource`], sending the outer
+/// struct's fields to `capture`. This is equivalent to deserializing `T`
+/// from a [`FlattenDeserializer`] wrapping the same source, except that all
+/// of the captured values are sent before `T` begins deserializing.
+///
+/// [`FlattenDeserializer`]: super::flatten::FlattenDeserializer
+pub fn deserialize_upfront<'de, T, S, C>(source: S, mut capture: C) -> Result<T, S::Error>
+where
+    T: Deserialize<'de>,
+    S: EntrySource<'de>,
+    C: KeyCapture<'de>,
+{
+    let entries = source.into_entries()?.into_iter();
+    let mut rejected = Vec::with_capacity(entries.size_hint().0);
+
+    for (key, value) in entries {
+        match capture.try_send_key(key.as_ref()) {
+            Some(token) => capture.send_value(token, value.into_deserializer())?,
+            None => rejected.push((key, value)),
+        }
+    }
+
+    let value = T::deserialize(MapDeserializer::new(rejected.into_iter()))?;
+    capture.finish()?;
+    Ok(value)
+}
+
+#[cfg(feature = "json")]
+mod json {
+    use serde::de::{Error as _, Unexpected};
+    use serde_json::{Error, Map, Value};
+
+    use super::EntrySource;
+
+    impl<'de> EntrySource<'de> for Map<String, Value> {
+        type Error = Error;
+        type Key = String;
+        type Value = Value;
+        type Entries = Self;
+
+        #[inline]
+        fn into_entries(self) -> Result<Self, Error> {
+            Ok(self)
+        }
+    }
+
+    impl<'de> EntrySource<'de> for Value {
+        type Error = Error;
+        type Key = String;
+        type Value = Value;
+        type Entries = Map<String, Value>;
+
+        fn into_entries(self) -> Result<Self::Entries, Error> {
+            let unexpected = match self {
+                Value::Object(map) => return Ok(map),
+                Value::Null => Unexpected::Unit,
+                Value::Bool(b) => Unexpected::Bool(b),
+                Value::Number(_) => Unexpected::Other("number"),
+                Value::String(ref s) => Unexpected::Str(s),
+                Value::Array(_) => Unexpected::Seq,
+            };
+
+            Err(Error::invalid_type(unexpected, &"a map"))
+        }
+    }
+}
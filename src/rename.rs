@@ -0,0 +1,184 @@
+/*!
+The standard serde rename rules, applied at runtime. With
+`#[serde(rename_all = "...")]` on the outer struct, a capture has to match
+the renamed keys rather than the field names; these produce them, so that a
+hand-written or dynamic capture can build its match keys (or normalize the
+incoming ones) the same way serde's derive does.
+
+Each function takes a field name as it's written in Rust, in `snake_case`,
+and returns the renamed key, borrowing the original if the rule leaves it
+unchanged.
+
+```
+use serde_bufferless::rename::{rename_camel_case, RenameRule};
+
+assert_eq!(rename_camel_case("created_at"), "createdAt");
+assert_eq!(RenameRule::from_name("kebab-case"), Some(RenameRule::KebabCase));
+```
+*/
+
+use alloc::{borrow::Cow, string::String};
+
+/// One of the rules accepted by `#[serde(rename_all = "...")]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RenameRule {
+    /// `lowercase`
+    LowerCase,
+    /// `UPPERCASE`
+    UpperCase,
+    /// `PascalCase`
+    PascalCase,
+    /// `camelCase`
+    CamelCase,
+    /// `snake_case`
+    SnakeCase,
+    /// `SCREAMING_SNAKE_CASE`
+    ScreamingSnakeCase,
+    /// `kebab-case`
+    KebabCase,
+    /// `SCREAMING-KEBAB-CASE`
+    ScreamingKebabCase,
+}
+
+impl RenameRule {
+    const ALL: [(&'static str, RenameRule); 8] = [
+        ("lowercase", RenameRule::LowerCase),
+        ("UPPERCASE", RenameRule::UpperCase),
+        ("PascalCase", RenameRule::PascalCase),
+        ("camelCase", RenameRule::CamelCase),
+        ("snake_case", RenameRule::SnakeCase),
+        ("SCREAMING_SNAKE_CASE", RenameRule::ScreamingSnakeCase),
+        ("kebab-case", RenameRule::KebabCase),
+        ("SCREAMING-KEBAB-CASE", RenameRule::ScreamingKebabCase),
+    ];
+
+    /// Look up a rule by the name used in `rename_all`
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|&&(rule_name, _)| rule_name == name)
+            .map(|&(_, rule)| rule)
+    }
+
+    /// The name of this rule, as used in `rename_all`
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|&&(_, rule)| rule == self)
+            .map(|&(name, _)| name)
+            .expect("every rule is listed")
+    }
+
+    /// Apply this rule to a `snake_case` field name
+    #[must_use]
+    pub fn apply(self, field: &str) -> Cow<'_, str> {
+        match self {
+            RenameRule::LowerCase | RenameRule::SnakeCase => Cow::Borrowed(field),
+            RenameRule::UpperCase => rename_uppercase(field),
+            RenameRule::PascalCase => rename_pascal_case(field),
+            RenameRule::CamelCase => rename_camel_case(field),
+            RenameRule::ScreamingSnakeCase => rename_screaming_snake_case(field),
+            RenameRule::KebabCase => rename_kebab_case(field),
+            RenameRule::ScreamingKebabCase => rename_screaming_kebab_case(field),
+        }
+    }
+}
+
+/// Borrow `field` if `is_unchanged` holds for every character, and otherwise
+/// build the renamed version with `rename`
+fn rename_if(
+    field: &str,
+    is_unchanged: impl Fn(char) -> bool,
+    rename: impl FnOnce(&str) -> String,
+) -> Cow<'_, str> {
+    if field.chars().all(is_unchanged) {
+        Cow::Borrowed(field)
+    } else {
+        Cow::Owned(rename(field))
+    }
+}
+
+/// `snake_case` is the identity for field names
+#[inline]
+#[must_use]
+pub fn rename_snake_case(field: &str) -> Cow<'_, str> {
+    Cow::Borrowed(field)
+}
+
+/// `created_at` becomes `CREATED_AT`
+#[must_use]
+pub fn rename_uppercase(field: &str) -> Cow<'_, str> {
+    rename_if(
+        field,
+        |c| !c.is_ascii_lowercase(),
+        |field| field.to_ascii_uppercase(),
+    )
+}
+
+/// `created_at` becomes `CreatedAt`
+#[must_use]
+pub fn rename_pascal_case(field: &str) -> Cow<'_, str> {
+    let mut renamed = String::with_capacity(field.len());
+    let mut capitalize = true;
+
+    for c in field.chars() {
+        if c == '_' {
+            capitalize = true;
+        } else if capitalize {
+            renamed.push(c.to_ascii_uppercase());
+            capitalize = false;
+        } else {
+            renamed.push(c);
+        }
+    }
+
+    if renamed == field {
+        Cow::Borrowed(field)
+    } else {
+        Cow::Owned(renamed)
+    }
+}
+
+/// `created_at` becomes `createdAt`
+#[must_use]
+pub fn rename_camel_case(field: &str) -> Cow<'_, str> {
+    match rename_pascal_case(field) {
+        Cow::Borrowed(_) => Cow::Borrowed(field),
+        Cow::Owned(mut renamed) => {
+            if let Some(first) = renamed.get_mut(..1) {
+                first.make_ascii_lowercase();
+            }
+
+            if renamed == field {
+                Cow::Borrowed(field)
+            } else {
+                Cow::Owned(renamed)
+            }
+        }
+    }
+}
+
+/// `created_at` becomes `CREATED_AT`
+#[inline]
+#[must_use]
+pub fn rename_screaming_snake_case(field: &str) -> Cow<'_, str> {
+    rename_uppercase(field)
+}
+
+/// `created_at` becomes `created-at`
+#[must_use]
+pub fn rename_kebab_case(field: &str) -> Cow<'_, str> {
+    rename_if(field, |c| c != '_', |field| field.replace('_', "-"))
+}
+
+/// `created_at` becomes `CREATED-AT`
+#[must_use]
+pub fn rename_screaming_kebab_case(field: &str) -> Cow<'_, str> {
+    rename_if(
+        field,
+        |c| c != '_' && !c.is_ascii_lowercase(),
+        |field| field.replace('_', "-").to_ascii_uppercase(),
+    )
+}
@@ -0,0 +1,136 @@
+//! A capture whose token carries the key it was matched from. The key bytes
+//! passed to `try_send_key` don't outlive the call, but when the possible
+//! spellings are known ahead of time, the token can hold the matching
+//! `&'static str` instead, and so record which alias the input used.
+
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    color: String,
+
+    /// Which of `color`'s aliases appeared in the input
+    color_alias: &'static str,
+
+    size: u32,
+
+    // #[serde(flatten)]
+    inner: Inner,
+}
+
+enum Field {
+    /// `color`, under the alias it was matched from
+    Color(&'static str),
+    Size,
+}
+
+#[derive(Default)]
+struct AliasRecordingCapture {
+    color: Option<(String, &'static str)>,
+    size: Option<u32>,
+}
+
+impl<'de> KeyCapture<'de> for &mut AliasRecordingCapture {
+    type Token = Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Field> {
+        match key {
+            b"color" => Some(Field::Color("color")),
+            b"colour" => Some(Field::Color("colour")),
+            b"size" => Some(Field::Size),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, token: Field, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match token {
+            Field::Color(alias) => self.color = Some((String::deserialize(value)?, alias)),
+            Field::Size => self.size = Some(u32::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct Outer")
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        &["color", "size"]
+    }
+
+    fn field_name(&self, token: &Field) -> Option<&'static str> {
+        Some(match *token {
+            Field::Color(_) => "color",
+            Field::Size => "size",
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = AliasRecordingCapture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        let (color, color_alias) = capture
+            .color
+            .ok_or_else(|| de::Error::missing_field("color"))?;
+
+        Ok(Outer {
+            color,
+            color_alias,
+            size: capture
+                .size
+                .ok_or_else(|| de::Error::missing_field("size"))?,
+            inner,
+        })
+    }
+}
+
+#[test]
+fn recorded_alias() {
+    for alias in ["color", "colour"] {
+        let outer: Outer = serde_json::from_str(&format!(
+            r#"{{"name": "box", "{alias}": "red", "size": 3}}"#
+        ))
+        .expect("failed to deserialize");
+
+        assert_eq!(
+            outer,
+            Outer {
+                color: "red".to_owned(),
+                color_alias: alias,
+                size: 3,
+                inner: Inner {
+                    name: "box".to_owned(),
+                },
+            }
+        );
+    }
+}
+
+/// Errors are reported against the field, whichever alias it was under
+#[test]
+fn alias_error() {
+    let err = serde_json::from_str::<Outer>(r#"{"name": "box", "colour": 1, "size": 3}"#)
+        .expect_err("colour isn't a string");
+
+    assert!(
+        err.to_string()
+            .starts_with("error deserializing field `color`: invalid type: integer `1`"),
+        "{}",
+        err
+    );
+}
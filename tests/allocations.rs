@@ -0,0 +1,149 @@
+//! Flattening shouldn't allocate anything of its own. In particular, a key
+//! that the capture rejects is forwarded to the inner type as whatever the
+//! format produced, without being copied: a `&str` is passed on as a `&str`,
+//! and an owned `String` is moved rather than cloned.
+
+mod common;
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use common::{Capture, Inner};
+use serde::Deserialize;
+use serde_bufferless::private::flatten::FlattenDeserializer;
+use serde_json::Value;
+
+/// Counts allocations made by the current thread, so that tests running in
+/// parallel don't count each other's
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    drop(f());
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+const INPUT: &str =
+    r#"{"integer": 10, "before": 1.5, "unknown": [1, 2], "string": "hello", "after": true}"#;
+
+fn flattened<'de, D>(deserializer: D) -> (Inner, Capture)
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))
+        .expect("failed to deserialize");
+    (inner, capture)
+}
+
+// Each test compares a flattened deserialization against deserializing the
+// inner type directly from the same input. The first flattened run is
+// discarded, because it leaks the combined field list that's passed to
+// `deserialize_struct`.
+
+/// Keys borrowed from the input
+#[test]
+fn borrowed_keys() {
+    flattened(&mut serde_json::Deserializer::from_str(INPUT));
+
+    let direct = allocations(|| {
+        Inner::deserialize(&mut serde_json::Deserializer::from_str(INPUT))
+            .expect("failed to deserialize")
+    });
+    let flattened = allocations(|| flattened(&mut serde_json::Deserializer::from_str(INPUT)));
+
+    assert_eq!(flattened, direct);
+}
+
+/// Keys that only live as long as the reader's scratch buffer
+#[test]
+fn transient_keys() {
+    flattened(&mut serde_json::Deserializer::from_reader(INPUT.as_bytes()));
+
+    let direct = allocations(|| {
+        Inner::deserialize(&mut serde_json::Deserializer::from_reader(INPUT.as_bytes()))
+            .expect("failed to deserialize")
+    });
+    let flattened =
+        allocations(|| flattened(&mut serde_json::Deserializer::from_reader(INPUT.as_bytes())));
+
+    assert_eq!(flattened, direct);
+}
+
+/// Keys that the format owns, and hands over with `visit_string`
+#[test]
+fn owned_keys() {
+    let value: Value = serde_json::from_str(INPUT).expect("failed to parse");
+    flattened(value.clone());
+
+    let input = value.clone();
+    let direct = allocations(|| Inner::deserialize(input).expect("failed to deserialize"));
+
+    let input = value.clone();
+    let flattened = allocations(|| flattened(input));
+
+    assert_eq!(flattened, direct);
+}
+
+#[derive(Deserialize)]
+struct BorrowedInner<'a> {
+    integer: i32,
+    string: &'a str,
+}
+
+/// A borrowed field of the inner type, whose key the capture was offered and
+/// rejected, still borrows its value from the input, and nothing is
+/// allocated along the way
+#[test]
+fn borrowed_inner_field() {
+    let input = INPUT;
+    let deserialize = || {
+        let mut capture = Capture::default();
+        let inner = BorrowedInner::deserialize(FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(input),
+            &mut capture,
+        ))
+        .expect("failed to deserialize");
+        (inner, capture)
+    };
+
+    deserialize();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    let (inner, capture) = deserialize();
+    assert_eq!(ALLOCATIONS.with(Cell::get) - before, 0);
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert!(input
+        .as_bytes()
+        .as_ptr_range()
+        .contains(&inner.string.as_ptr()));
+}
@@ -0,0 +1,101 @@
+use std::borrow::Cow;
+
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner<'a> {
+    #[serde(borrow)]
+    string: &'a str,
+    integer: i32,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer<'a> {
+    name: &'a str,
+    label: Cow<'a, str>,
+
+    // #[serde(flatten)]
+    inner: Inner<'a>,
+}
+
+#[derive(Default)]
+struct Capture<'a> {
+    name: Option<&'a str>,
+    label: Option<Cow<'a, str>>,
+}
+
+enum Field {
+    Name,
+    Label,
+}
+
+impl<'de> KeyCapture<'de> for &mut Capture<'de> {
+    type Token = Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Field> {
+        match key {
+            b"name" => Some(Field::Name),
+            b"label" => Some(Field::Label),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, token: Field, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match token {
+            Field::Name => self.name = Some(Deserialize::deserialize(value)?),
+            Field::Label => {
+                // `Cow`'s own impl always allocates, so borrow explicitly
+                let label: &'de str = Deserialize::deserialize(value)?;
+                self.label = Some(Cow::Borrowed(label));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct Outer")
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Outer<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Outer {
+            name: capture
+                .name
+                .ok_or_else(|| de::Error::missing_field("name"))?,
+            label: capture
+                .label
+                .ok_or_else(|| de::Error::missing_field("label"))?,
+            inner,
+        })
+    }
+}
+
+/// Captured values and inner values are both borrowed from the input
+#[test]
+fn zero_copy() {
+    let input =
+        String::from(r#"{"string": "hello", "name": "world", "integer": 10, "label": "tag"}"#);
+    let outer: Outer = serde_json::from_str(&input).expect("failed to deserialize");
+
+    assert_eq!(outer.name, "world");
+    assert_eq!(outer.inner.string, "hello");
+    assert!(matches!(outer.label, Cow::Borrowed("tag")));
+
+    // Every string points into the input
+    let input_range = input.as_bytes().as_ptr_range();
+    for borrowed in [outer.name, outer.inner.string, &outer.label] {
+        assert!(input_range.contains(&borrowed.as_ptr()));
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Capture, Inner};
+use serde::Deserialize;
+use serde_bufferless::private::buffered::BufferedFlattenDeserializer;
+use serde_value::Value;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Version {
+    major: u32,
+
+    #[serde(default)]
+    integer: Option<i32>,
+}
+
+#[test]
+fn several_flattened_fields() {
+    let mut capture = Capture::default();
+    let mut buffered = BufferedFlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "major": 2, "string": "hello", "after": true, "extra": null}"#,
+        ),
+        &mut capture,
+    )
+    .expect("failed to deserialize");
+
+    // The captured fields aren't buffered
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(buffered.entries().count(), 4);
+
+    // A struct takes its own fields, so `integer` isn't seen again
+    let inner = Inner::deserialize(&mut buffered).expect("failed to deserialize Inner");
+    let version = Version::deserialize(&mut buffered).expect("failed to deserialize Version");
+    let rest: BTreeMap<String, Value> =
+        BTreeMap::deserialize(&mut buffered).expect("failed to deserialize the rest");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+    assert_eq!(
+        version,
+        Version {
+            major: 2,
+            integer: None,
+        }
+    );
+    assert_eq!(rest, BTreeMap::from([("extra".to_owned(), Value::Unit)]));
+}
+
+/// Maps see every entry that's left, without taking them
+#[test]
+fn maps_share_entries() {
+    let mut capture = Capture::default();
+    let mut buffered = BufferedFlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"a": 1, "before": 1.5, "b": 2}"#),
+        &mut capture,
+    )
+    .expect("failed to deserialize");
+
+    let first: BTreeMap<String, i32> = BTreeMap::deserialize(&mut buffered).unwrap();
+    let second: BTreeMap<String, i32> = BTreeMap::deserialize(&mut buffered).unwrap();
+
+    assert_eq!(
+        first,
+        BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+    );
+    assert_eq!(first, second);
+}
+
+#[test]
+fn errors() {
+    let mut capture = Capture::default();
+    let mut buffered = BufferedFlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "string": "hello"}"#),
+        &mut capture,
+    )
+    .expect("failed to deserialize");
+
+    let err = Version::deserialize(&mut buffered).expect_err("`major` is missing");
+    assert_eq!(err.to_string(), "missing field `major`");
+
+    let err = BufferedFlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str("[]"),
+        &mut capture,
+    )
+    .expect_err("not a map");
+    assert!(
+        err.to_string()
+            .starts_with("invalid type: sequence, expected struct Outer"),
+        "{}",
+        err
+    );
+}
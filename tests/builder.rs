@@ -0,0 +1,149 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Capture, Inner};
+use serde::Deserialize;
+use serde_bufferless::private::flatten::{FlattenDeserializer, UnknownPolicy};
+
+const INPUT: &str = r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#;
+
+#[test]
+fn capture_only() {
+    let mut capture = Capture::default();
+    let inner: Inner = FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(INPUT))
+        .capture(&mut capture)
+        .deserialize()
+        .expect("failed to deserialize");
+
+    let mut expected_capture = Capture::default();
+    let expected = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(INPUT),
+        &mut expected_capture,
+    ))
+    .unwrap();
+
+    assert_eq!(inner, expected);
+    assert_eq!(capture.before, expected_capture.before);
+    assert_eq!(capture.after, expected_capture.after);
+}
+
+#[test]
+fn deny_without_drain() {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(
+        FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(INPUT))
+            .unknown_policy(UnknownPolicy::Deny)
+            .drain(false)
+            .capture(&mut capture)
+            .build(),
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(capture.after, Some(true));
+
+    let err = FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(
+        r#"{"integer": 10, "befor": 1.5, "string": "hello"}"#,
+    ))
+    .capture(&mut Capture::default())
+    .unknown_policy(UnknownPolicy::Deny)
+    .drain(false)
+    .deserialize::<Inner>()
+    .expect_err("misspelled outer field should be rejected");
+
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `befor`, expected one of `before`, `after`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+}
+
+/// A map has no field list of its own, so denying unknown keys needs the
+/// inner fields to be provided explicitly.
+#[test]
+fn explicit_fields() {
+    let deserialize = |input: &str| {
+        let mut capture = Capture::default();
+        let map: BTreeMap<String, i32> =
+            FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(input))
+                .capture(&mut capture)
+                .fields(&["before"], &["x", "y"])
+                .unknown_policy(UnknownPolicy::Deny)
+                .deserialize()?;
+
+        Ok::<_, serde_json::Error>((capture.before, map))
+    };
+
+    let (before, map) = deserialize(r#"{"x": 1, "before": 2.5, "y": 2}"#).unwrap();
+    assert_eq!(before, Some(2.5));
+    assert_eq!(map, BTreeMap::from([("x".into(), 1), ("y".into(), 2)]));
+
+    let err = deserialize(r#"{"x": 1, "z": 3}"#).expect_err("`z` isn't a declared field");
+    assert!(
+        err.to_string()
+            .starts_with("unknown field `z`, expected one of `before`, `x`, `y`"),
+        "{}",
+        err
+    );
+
+    // Without the policy, the map gets everything the capture rejects
+    let mut capture = Capture::default();
+    let map: BTreeMap<String, i32> = FlattenDeserializer::builder(
+        &mut serde_json::Deserializer::from_str(r#"{"x": 1, "z": 3}"#),
+    )
+    .capture(&mut capture)
+    .fields(&["before"], &["x", "y"])
+    .deserialize()
+    .unwrap();
+    assert_eq!(map.len(), 2);
+}
+
+/// Errors from anywhere in the map are prefixed with the context
+#[test]
+fn context() {
+    let deserialize = |input: &str| {
+        FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(input))
+            .capture(&mut Capture::default())
+            .context("struct Outer")
+            .unknown_policy(UnknownPolicy::Deny)
+            .deserialize::<Inner>()
+    };
+
+    deserialize(INPUT).expect("failed to deserialize");
+
+    let err = deserialize(r#"{"integer": 10, "befor": 1.5, "string": "hello"}"#)
+        .expect_err("misspelled outer field should be rejected");
+    assert!(
+        err.to_string().starts_with(
+            "error deserializing struct Outer: unknown field `befor`, expected one of"
+        ),
+        "{}",
+        err
+    );
+
+    let err = deserialize(r#"{"integer": 10}"#).expect_err("`string` is missing");
+    assert!(
+        err.to_string()
+            .starts_with("error deserializing struct Outer: missing field `string`"),
+        "{}",
+        err
+    );
+
+    // A flattened `Option` is only prefixed once
+    let err = FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(
+        r#"{"integer": "ten"}"#,
+    ))
+    .capture(&mut Capture::default())
+    .context("struct Outer")
+    .deserialize::<Option<Inner>>()
+    .expect_err("`integer` isn't a number");
+    assert!(
+        err.to_string()
+            .starts_with("error deserializing struct Outer: invalid type: string"),
+        "{}",
+        err
+    );
+}
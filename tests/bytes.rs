@@ -0,0 +1,148 @@
+//! Binary blobs captured with `#[serde(with = "serde_bytes")]` must reach
+//! `deserialize_byte_buf` on the value deserializer, rather than being
+//! deserialized as a sequence of integers.
+
+use serde::{de, Deserialize, Serialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+use serde_test::{assert_de_tokens, Token};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    // #[serde(with = "serde_bytes")]
+    blob: Vec<u8>,
+
+    // #[serde(flatten)]
+    inner: Inner,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[allow(non_camel_case_types)]
+        enum Field {
+            blob,
+        }
+
+        #[derive(Default)]
+        struct Capture {
+            blob: Option<Vec<u8>>,
+        }
+
+        impl<'de> KeyCapture<'de> for &mut Capture {
+            type Token = Field;
+
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                match key {
+                    b"blob" => Some(Field::blob),
+                    _ => None,
+                }
+            }
+
+            fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                match field {
+                    Field::blob => self.blob = Some(serde_bytes::deserialize(value)?),
+                }
+
+                Ok(())
+            }
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "struct Outer")
+            }
+        }
+
+        let mut capture = Capture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Outer {
+            blob: capture
+                .blob
+                .ok_or_else(|| de::Error::missing_field("blob"))?,
+            inner,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Wire<'a> {
+    name: &'a str,
+    #[serde(with = "serde_bytes")]
+    blob: &'a [u8],
+}
+
+fn expected() -> Outer {
+    Outer {
+        blob: vec![0, 1, 2, 254, 255],
+        inner: Inner {
+            name: "blob".to_owned(),
+        },
+    }
+}
+
+#[test]
+fn cbor() {
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(
+        &Wire {
+            name: "blob",
+            blob: &[0, 1, 2, 254, 255],
+        },
+        &mut cbor,
+    )
+    .expect("failed to serialize");
+
+    // CBOR byte strings are major type 2; make sure the blob was written as
+    // one, rather than as an array
+    assert!(cbor
+        .windows(6)
+        .any(|window| window == [0x45, 0, 1, 2, 254, 255]));
+
+    let data: Outer = ciborium::de::from_reader(cbor.as_slice()).expect("failed to deserialize");
+    assert_eq!(data, expected());
+}
+
+#[test]
+fn byte_buf_tokens() {
+    // A plain `Vec<u8>` would reject a byte buffer token, so this only passes
+    // if serde_bytes' `deserialize_byte_buf` reached the source deserializer
+    assert_de_tokens(
+        &expected(),
+        &[
+            Token::Map { len: Some(2) },
+            Token::Str("blob"),
+            Token::ByteBuf(&[0, 1, 2, 254, 255]),
+            Token::Str("name"),
+            Token::Str("blob"),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// A borrowed byte slice can be captured without copying it
+#[test]
+fn borrowed_byte_buf() {
+    use serde_bufferless::private::BorrowedByteBufDeserializer;
+
+    let input = b"some bytes".to_vec();
+    let captured: &[u8] =
+        Deserialize::deserialize(BorrowedByteBufDeserializer::<de::value::Error>::new(&input))
+            .expect("failed to deserialize");
+
+    assert_eq!(captured, b"some bytes");
+    assert_eq!(captured.as_ptr(), input.as_ptr());
+
+    let captured: &serde_bytes::Bytes =
+        Deserialize::deserialize(BorrowedByteBufDeserializer::<de::value::Error>::new(&input))
+            .expect("failed to deserialize");
+    assert_eq!(captured.as_ptr(), input.as_ptr());
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Capture, Inner};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::CaseInsensitive,
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+
+#[test]
+fn mixed_case_keys() {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "Before": 1.5, "string": "hello", "AFTER": true}"#,
+        ),
+        CaseInsensitive::new(&mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// Rejected keys reach the inner type with their original case
+#[test]
+fn rejected_keys_unchanged() {
+    let mut capture = Capture::default();
+    let rest: BTreeMap<String, i32> = BTreeMap::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"Other": 1, "bEfOrE": 2.5, "ÉTÉ": 2, "a_very_long_key_that_does_not_fit_in_the_stack_buffer_AT_ALL": 3}"#,
+        ),
+        CaseInsensitive::new(&mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(2.5));
+    assert_eq!(
+        rest,
+        BTreeMap::from([
+            ("Other".to_owned(), 1),
+            ("ÉTÉ".to_owned(), 2),
+            (
+                "a_very_long_key_that_does_not_fit_in_the_stack_buffer_AT_ALL".to_owned(),
+                3
+            ),
+        ])
+    );
+}
+
+const LONG: &str = "a_very_long_field_name_that_does_not_fit_in_the_stack_buffer_at_all";
+
+/// Accepts exactly its field names, recording each one it's sent
+#[derive(Default)]
+struct Exact {
+    seen: Vec<&'static str>,
+}
+
+impl<'de> KeyCapture<'de> for &mut Exact {
+    type Token = &'static str;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.fields()
+            .iter()
+            .copied()
+            .find(|field| field.as_bytes() == key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        de::IgnoredAny::deserialize(value)?;
+        self.seen.push(token);
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        &[LONG, "été", "createdAt"]
+    }
+}
+
+/// Fields are matched in place, so long and non-ASCII keys need no buffer,
+/// and a field whose own name isn't lowercase still matches
+#[test]
+fn matched_against_fields() {
+    let mut capture = Exact::default();
+    let rest: BTreeMap<String, i32> = BTreeMap::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"A_VERY_LONG_FIELD_NAME_THAT_DOES_NOT_FIT_IN_THE_STACK_BUFFER_AT_ALL": 1, "ÉTÉ": 2, "createdat": 3, "other": 4}"#,
+        ),
+        CaseInsensitive::new(&mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.seen, [LONG, "été", "createdAt"]);
+    assert_eq!(rest, BTreeMap::from([("other".to_owned(), 4)]));
+}
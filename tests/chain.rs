@@ -0,0 +1,231 @@
+//! Two flattened structs: the first is captured field by field alongside the
+//! outer struct's own fields, and the second is the inner type.
+
+mod common;
+
+use common::Inner;
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::{ChainCapture, IgnoreCapture},
+    private::flatten::{FlattenDeserializer, KeyCapture, UnknownPolicy},
+};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Settings {
+    flag: bool,
+    count: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    id: u64,
+    name: String,
+
+    // #[serde(flatten)]
+    inner: Inner,
+
+    // #[serde(flatten)]
+    settings: Settings,
+}
+
+/// The outer struct's own fields
+#[derive(Default)]
+struct OuterCapture {
+    id: Option<u64>,
+    name: Option<String>,
+}
+
+impl<'de> KeyCapture<'de> for &mut OuterCapture {
+    type Token = &'static str;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"id" => Some("id"),
+            b"name" => Some("name"),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            "id" => self.id = Some(Deserialize::deserialize(value)?),
+            _ => self.name = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "struct Outer")
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        &["id", "name"]
+    }
+}
+
+/// The fields of the first flattened struct, `Inner`. `id` collides with the
+/// outer struct, so it's never seen here.
+#[derive(Default)]
+struct InnerCapture {
+    integer: Option<i32>,
+    string: Option<String>,
+    id: Option<u64>,
+}
+
+impl<'de> KeyCapture<'de> for &mut InnerCapture {
+    type Token = &'static str;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"integer" => Some("integer"),
+            b"string" => Some("string"),
+            b"id" => Some("id"),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            "integer" => self.integer = Some(Deserialize::deserialize(value)?),
+            "string" => self.string = Some(Deserialize::deserialize(value)?),
+            _ => self.id = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "struct Inner")
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        &["integer", "string"]
+    }
+}
+
+const FIELDS: &[&str] = &["id", "name", "integer", "string"];
+
+fn deserialize(input: &str) -> Result<(Outer, InnerCapture), serde_json::Error> {
+    let mut outer = OuterCapture::default();
+    let mut inner = InnerCapture::default();
+
+    let settings = Settings::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        ChainCapture::new(&mut outer, &mut inner).with_fields(FIELDS),
+    ))?;
+
+    let data = Outer {
+        id: outer.id.ok_or_else(|| de::Error::missing_field("id"))?,
+        name: outer.name.ok_or_else(|| de::Error::missing_field("name"))?,
+        inner: Inner {
+            integer: inner
+                .integer
+                .take()
+                .ok_or_else(|| de::Error::missing_field("integer"))?,
+            string: inner
+                .string
+                .take()
+                .ok_or_else(|| de::Error::missing_field("string"))?,
+        },
+        settings,
+    };
+
+    Ok((data, inner))
+}
+
+#[test]
+fn two_flattened() {
+    let (data, _) = deserialize(
+        r#"{"count": 3, "integer": 10, "id": 7, "flag": true, "string": "s", "name": "n"}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            id: 7,
+            name: "n".to_owned(),
+            inner: Inner {
+                integer: 10,
+                string: "s".to_owned(),
+            },
+            settings: Settings {
+                flag: true,
+                count: 3,
+            },
+        }
+    );
+}
+
+#[test]
+fn collision() {
+    let (data, inner) = deserialize(
+        r#"{"id": 7, "name": "n", "integer": 10, "string": "s", "flag": false, "count": 0}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data.id, 7);
+    assert_eq!(inner.id, None);
+}
+
+#[test]
+fn fields() {
+    let mut outer = OuterCapture::default();
+    let mut inner = InnerCapture::default();
+
+    assert_eq!(
+        ChainCapture::new(&mut outer, &mut inner)
+            .with_fields(FIELDS)
+            .fields(),
+        FIELDS
+    );
+    assert_eq!(
+        ChainCapture::new(&mut outer, IgnoreCapture::new(&[])).fields(),
+        ["id", "name"]
+    );
+    assert_eq!(
+        ChainCapture::new(IgnoreCapture::new(&[]), &mut inner).fields(),
+        ["integer", "string"]
+    );
+}
+
+/// Both captures' fields are reported in unknown-field errors
+#[test]
+fn unknown_field() {
+    let mut outer = OuterCapture::default();
+    let mut inner = InnerCapture::default();
+
+    let err = FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(
+        r#"{"id": 7, "integr": 10, "flag": true, "count": 3}"#,
+    ))
+    .capture(ChainCapture::new(&mut outer, &mut inner).with_fields(FIELDS))
+    .unknown_policy(UnknownPolicy::Deny)
+    .deserialize::<Settings>()
+    .expect_err("misspelled field should be rejected");
+
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `integr`, expected one of `id`, `name`, `integer`, `string`, `flag`, `count`"
+        ),
+        "{}",
+        err
+    );
+}
+
+/// Two captures with fields can't be combined without `with_fields`
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "must be combined with `with_fields`")]
+fn missing_fields() {
+    let mut outer = OuterCapture::default();
+    let mut inner = InnerCapture::default();
+
+    let _ = ChainCapture::new(&mut outer, &mut inner).fields();
+}
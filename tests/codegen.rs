@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use serde_bufferless::codegen::StructSpec;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    integer: i32,
+    string: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    before: f32,
+    after: bool,
+    inner: Inner,
+}
+
+// This is what a build.rs would have written into OUT_DIR
+include!("golden/outer.rs");
+
+fn spec() -> StructSpec {
+    StructSpec::new("Outer")
+        .field("before", "f32")
+        .default_field("after", "bool")
+        .flatten("inner", "Inner")
+}
+
+#[test]
+fn matches_golden() {
+    assert_eq!(spec().generate(), include_str!("golden/outer.rs"));
+}
+
+#[test]
+fn generated_impl() {
+    let data: Outer = serde_json::from_str(r#"{"integer": 10, "before": 10.5, "string": "hello"}"#)
+        .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 10.5,
+            after: false,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+
+    let err = serde_json::from_str::<Outer>(r#"{"integer": 10, "string": "hello"}"#)
+        .expect_err("`before` is required");
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn aliases() {
+    let generated = StructSpec::new("Outer")
+        .field("before", "f32")
+        .alias("prior")
+        .alias("previous")
+        .field("after", "bool")
+        .flatten("inner", "Inner")
+        .generate();
+
+    assert!(
+        generated.contains(
+            r#"b"before" | b"prior" | b"previous" => ::core::option::Option::Some(Field::before),"#
+        ),
+        "{}",
+        generated
+    );
+    assert!(
+        generated.contains(r#"b"after" => ::core::option::Option::Some(Field::after),"#),
+        "{}",
+        generated
+    );
+}
+
+#[test]
+fn default_path() {
+    let generated = StructSpec::new("Outer")
+        .default_field_with("before", "f32", "crate::default_before")
+        .field("after", "bool")
+        .flatten("inner", "Inner")
+        .generate();
+
+    assert!(
+        generated.contains("let before = capture.before.unwrap_or_else(crate::default_before);"),
+        "{}",
+        generated
+    );
+}
+
+#[test]
+fn duplicate_sibling() {
+    let err = serde_json::from_str::<Outer>(
+        r#"{"before": 1.5, "integer": 10, "string": "hello", "before": 2.5}"#,
+    )
+    .expect_err("`before` appears twice");
+    assert!(
+        err.to_string().starts_with("duplicate field `before`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn rename() {
+    let generated = StructSpec::new("Outer")
+        .field("created_at", "u64")
+        .rename("createdAt")
+        .alias("created")
+        .flatten("inner", "Inner")
+        .generate();
+
+    assert!(
+        generated.contains(
+            r#"b"createdAt" | b"created" => ::core::option::Option::Some(Field::created_at),"#
+        ),
+        "{}",
+        generated
+    );
+    assert!(
+        generated.contains(r#"missing_field::<_, D::Error>("createdAt")"#),
+        "{}",
+        generated
+    );
+}
+
+/// Keys are escaped, so any string can be a key or an alias
+#[test]
+fn escaped_keys() {
+    let generated = StructSpec::new("Outer")
+        .field("quoted", "u32")
+        .rename("say \"hi\"")
+        .alias("back\\slash")
+        .alias("café")
+        .flatten("inner", "Inner")
+        .generate();
+
+    assert!(
+        generated.contains(
+            r#"b"say \"hi\"" | b"back\\slash" | b"caf\xc3\xa9" => ::core::option::Option::Some(Field::quoted),"#
+        ),
+        "{}",
+        generated
+    );
+    assert!(generated.contains(r#"&["say \"hi\""]"#), "{}", generated);
+}
@@ -0,0 +1,78 @@
+//! A generic wrapper with a flattened type parameter, shared by
+//! `examples/envelope.rs` and `tests/envelope.rs`. The capture handles the
+//! fixed fields, and the payload is any `T: Deserialize<'de>`, with no
+//! `'static` or other extra bounds.
+
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct Meta {
+    pub id: u64,
+    pub version: u32,
+}
+
+/// A generic wrapper that attaches the same metadata to any payload
+#[derive(Debug, PartialEq)]
+pub struct Envelope<T> {
+    pub meta: Meta,
+
+    //#[serde(flatten)]
+    pub payload: T,
+}
+
+// The capture only ever handles `meta`, so it doesn't need to be generic over
+// the payload; `T` is only bound by `Deserialize<'de>`, exactly as it would be
+// for a non-flattened field.
+impl<'de, T> Deserialize<'de> for Envelope<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Field;
+
+        struct Capture {
+            meta: Option<Meta>,
+        }
+
+        impl<'de> KeyCapture<'de> for &mut Capture {
+            type Token = Field;
+
+            #[inline]
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                match key {
+                    b"meta" => Some(Field),
+                    _ => None,
+                }
+            }
+
+            #[inline]
+            fn send_value<D>(&mut self, _field: Self::Token, value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                self.meta = Some(Deserialize::deserialize(value)?);
+                Ok(())
+            }
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "struct Envelope")
+            }
+
+            fn fields(&self) -> &'static [&'static str] {
+                &["meta"]
+            }
+        }
+
+        let mut capture = Capture { meta: None };
+        let payload = T::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+        let meta = capture
+            .meta
+            .ok_or_else(|| de::Error::missing_field("meta"))?;
+
+        Ok(Self { meta, payload })
+    }
+}
@@ -0,0 +1,112 @@
+//! Shared fixtures for the integration tests. `Outer` is deserialized by hand
+//! in the same way that `derive(Deserialize)` would generate it, if it used
+//! `FlattenDeserializer` for its `#[serde(flatten)]` field.
+
+#![allow(dead_code)]
+
+pub mod envelope;
+pub mod no_buffer;
+
+use serde::{de, Deserialize, Serialize};
+use serde_bufferless::private::flatten::{merge_fields, FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+pub struct Inner {
+    pub integer: i32,
+    pub string: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outer {
+    pub before: f32,
+    pub after: bool,
+
+    // #[serde(flatten)]
+    pub inner: Inner,
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    before,
+    after,
+}
+
+#[derive(Debug, Default)]
+pub struct Capture {
+    pub before: Option<f32>,
+    pub after: Option<bool>,
+}
+
+impl<'de> KeyCapture<'de> for &mut Capture {
+    type Token = Field;
+
+    #[inline]
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"before" => Some(Field::before),
+            b"after" => Some(Field::after),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+        match index {
+            0 => Some(Field::before),
+            1 => Some(Field::after),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            Field::before => self.before = Some(Deserialize::deserialize(value)?),
+            Field::after => self.after = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "struct Outer")
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        &["before", "after"]
+    }
+
+    /// `Inner`'s fields are known ahead of time, so the combined list can be
+    /// written out
+    fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+        match inner {
+            ["integer", "string"] => Some(&["before", "after", "integer", "string"]),
+            _ => merge_fields(self.fields(), inner),
+        }
+    }
+}
+
+impl Capture {
+    pub fn assemble<E: de::Error>(self, inner: Inner) -> Result<Outer, E> {
+        Ok(Outer {
+            before: self.before.ok_or_else(|| E::missing_field("before"))?,
+            after: self.after.ok_or_else(|| E::missing_field("after"))?,
+            inner,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+        capture.assemble(inner)
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::ContentCapture, private::flatten::FlattenDeserializer};
+use serde_value::Value;
+
+#[test]
+fn deferred_values() {
+    let mut capture = ContentCapture::new(&["before", "after"]);
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"after": true, "integer": 10, "before": 1.5, "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+
+    let values = capture.take_values();
+    assert_eq!(
+        values,
+        [("after", Value::Bool(true)), ("before", Value::F64(1.5))]
+    );
+    assert!(capture.values().is_empty());
+
+    let mut before = None;
+    let mut after = None;
+
+    for (field, value) in values {
+        match field {
+            "before" => before = Some(value.deserialize_into::<f32>().unwrap()),
+            "after" => after = Some(value.deserialize_into::<bool>().unwrap()),
+            _ => unreachable!(),
+        }
+    }
+
+    assert_eq!(before, Some(1.5));
+    assert_eq!(after, Some(true));
+}
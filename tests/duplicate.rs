@@ -0,0 +1,128 @@
+mod common;
+
+use common::{Capture, Inner};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::{DuplicateReject, MergeMode},
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+
+fn deserialize(input: &str) -> Result<(Capture, Inner), serde_json::Error> {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        DuplicateReject::new(&mut capture),
+    ))?;
+
+    Ok((capture, inner))
+}
+
+#[test]
+fn unique() {
+    let (capture, inner) =
+        deserialize(r#"{"integer": 10, "before": 1, "string": "hello", "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.0));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+}
+
+#[test]
+fn duplicate() {
+    let err = deserialize(r#"{"before": 1, "before": 2}"#).expect_err("`before` appears twice");
+
+    assert!(
+        err.to_string().starts_with("duplicate field `before`"),
+        "{}",
+        err
+    );
+}
+
+/// Duplicates are caught even after the inner type is done, while draining
+#[test]
+fn duplicate_while_draining() {
+    let err = deserialize(
+        r#"{"integer": 10, "string": "hello", "after": true, "before": 1, "after": false}"#,
+    )
+    .expect_err("`after` appears twice");
+
+    assert!(
+        err.to_string().starts_with("duplicate field `after`"),
+        "{}",
+        err
+    );
+}
+
+/// Accepts `previous` as an alias of `before`
+struct Alias<C>(C);
+
+impl<'de, C: KeyCapture<'de>> KeyCapture<'de> for Alias<C> {
+    type Token = C::Token;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"previous" => self.0.try_send_key(b"before"),
+            key => self.0.try_send_key(key),
+        }
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.0.send_value(token, value)
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        self.0.fields()
+    }
+}
+
+const ALIASED: &str =
+    r#"{"integer": 10, "before": 1, "string": "hello", "previous": 2, "after": true}"#;
+
+fn deserialize_aliased(mode: MergeMode) -> Result<(Capture, Inner), serde_json::Error> {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(ALIASED),
+        DuplicateReject::with_mode(Alias(&mut capture), mode),
+    ))?;
+
+    Ok((capture, inner))
+}
+
+#[test]
+fn first_wins() {
+    let (capture, inner) =
+        deserialize_aliased(MergeMode::FirstWins).expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.0));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.string, "hello");
+}
+
+#[test]
+fn last_wins() {
+    let (capture, inner) = deserialize_aliased(MergeMode::LastWins).expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(2.0));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.string, "hello");
+}
+
+/// The alias isn't in `fields`, so it's named as it appeared
+#[test]
+fn aliased_error() {
+    let err = deserialize_aliased(MergeMode::Error).expect_err("`before` appears twice");
+
+    assert!(
+        err.to_string().starts_with("duplicate field `previous`"),
+        "{}",
+        err
+    );
+}
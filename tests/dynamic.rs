@@ -0,0 +1,50 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::DynamicCapture, private::flatten::FlattenDeserializer};
+
+#[test]
+fn runtime_fields() {
+    let mut capture = DynamicCapture::new();
+
+    // Field names from some runtime schema
+    let fields: Vec<usize> = ["id", "tags", "enabled"]
+        .into_iter()
+        .map(|name| capture.field(name))
+        .collect();
+
+    assert_eq!(capture.field("tags"), fields[1]);
+    assert_eq!(capture.len(), 3);
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"id": 7, "integer": 10, "tags": ["a", "b"], "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        inner,
+        Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        }
+    );
+
+    let id: u32 = capture
+        .take(fields[0])
+        .expect("`id` was present")
+        .deserialize_into()
+        .expect("`id` is an integer");
+    let tags: Vec<String> = capture
+        .take(fields[1])
+        .expect("`tags` was present")
+        .deserialize_into()
+        .expect("`tags` is a list of strings");
+
+    assert_eq!(id, 7);
+    assert_eq!(tags, ["a", "b"]);
+    assert!(capture.get(fields[2]).is_none());
+}
@@ -0,0 +1,91 @@
+//! Flattening a type parameter: `Envelope<T>` deserializes with any
+//! `T: Deserialize<'de>`, borrowed or owned.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::envelope::{Envelope, Meta};
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Login<'a> {
+    user: &'a str,
+    success: bool,
+}
+
+/// Generic code forwarding its own `T` must be able to name `Envelope<T>`
+/// with only the bound it already has.
+fn parse<'de, T: Deserialize<'de>>(input: &'de str) -> serde_json::Result<Envelope<T>> {
+    serde_json::from_str(input)
+}
+
+const META: Meta = Meta {
+    id: 1024,
+    version: 2,
+};
+
+#[test]
+fn borrowed_payload() {
+    let input = r#"{"user": "lucretiel", "meta": {"id": 1024, "version": 2}, "success": true}"#;
+    let data: Envelope<Login> = parse(input).expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Envelope {
+            meta: META,
+            payload: Login {
+                user: "lucretiel",
+                success: true,
+            },
+        }
+    );
+}
+
+#[test]
+fn map_payload() {
+    let input = r#"{"a": 1, "meta": {"id": 1024, "version": 2}, "b": 2}"#;
+    let data: Envelope<BTreeMap<String, i32>> = parse(input).expect("failed to deserialize");
+
+    assert_eq!(data.meta, META);
+    assert_eq!(
+        data.payload,
+        BTreeMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+    );
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Reading {
+    sensor: String,
+    value: f64,
+}
+
+#[test]
+fn owned_payload() {
+    let input = br#"{"sensor": "t1", "value": 21.5, "meta": {"id": 1024, "version": 2}}"#;
+    let data: Envelope<Reading> =
+        serde_json::from_reader(&input[..]).expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Envelope {
+            meta: META,
+            payload: Reading {
+                sensor: "t1".to_owned(),
+                value: 21.5,
+            },
+        }
+    );
+}
+
+#[test]
+fn missing_meta() {
+    let err = parse::<Login>(r#"{"user": "lucretiel", "success": true}"#)
+        .expect_err("`meta` is required");
+
+    assert!(
+        err.to_string().starts_with("missing field `meta`"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{Capture, Field, Inner};
+use serde::Deserialize;
+use serde_bufferless::{
+    capture::{ErasedKeyCapture, Validated},
+    private::flatten::FlattenDeserializer,
+};
+
+fn require_positive(capture: &&mut Capture, field: &Field) -> Result<(), &'static str> {
+    match (field, capture.before) {
+        (Field::before, Some(before)) if before <= 0.0 => Err("`before` must be positive"),
+        _ => Ok(()),
+    }
+}
+
+/// Pick a capture at runtime, and deserialize through it as a trait object
+fn deserialize(input: &str, strict: bool) -> Result<(Capture, Inner), serde_json::Error> {
+    let mut capture = Capture::default();
+
+    let inner = {
+        let boxed: Box<dyn ErasedKeyCapture<'_, Token = Field>> = if strict {
+            Box::new(Validated::new(&mut capture, require_positive))
+        } else {
+            Box::new(&mut capture)
+        };
+
+        Inner::deserialize(FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(input),
+            boxed,
+        ))?
+    };
+
+    Ok((capture, inner))
+}
+
+#[test]
+fn boxed_capture() {
+    let input = r#"{"integer": 10, "before": -1.5, "string": "hello", "after": true}"#;
+
+    let (capture, inner) = deserialize(input, false).expect("failed to deserialize");
+    assert_eq!(capture.before, Some(-1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+
+    let err = deserialize(input, true).expect_err("validation should have failed");
+    assert!(
+        err.to_string().contains("`before` must be positive"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn borrowed_capture() {
+    let mut capture = Capture::default();
+    let erased: &mut dyn ErasedKeyCapture<'_, Token = Field> = &mut &mut capture;
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello", "after": false}"#,
+        ),
+        erased,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(false));
+    assert_eq!(inner.integer, 10);
+}
@@ -0,0 +1,305 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Capture, Inner, Outer};
+use serde::{
+    de::{self, value::MapDeserializer, Error as _},
+    forward_to_deserialize_any, Deserialize,
+};
+use serde_bufferless::{key_capture, private::flatten::FlattenDeserializer};
+use serde_json::{json, Value};
+
+/// A format that only supports `deserialize_struct`, and rejects any key that
+/// isn't among the declared fields.
+struct Strict(Vec<(&'static str, Value)>);
+
+impl<'de> de::Deserializer<'de> for Strict {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(Self::Error::custom(
+            "strict format requires deserialize_struct",
+        ))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if let Some(&(key, _)) = self.0.iter().find(|(key, _)| !fields.contains(key)) {
+            return Err(Self::Error::unknown_field(key, fields));
+        }
+
+        visitor.visit_map(MapDeserializer::new(self.0.into_iter()))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
+
+#[test]
+fn merged_field_list() {
+    let data = Outer::deserialize(Strict(vec![
+        ("integer", json!(10)),
+        ("before", json!(1.5)),
+        ("string", json!("hello")),
+        ("after", json!(true)),
+    ]))
+    .expect("captured keys should be declared to the format");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+
+    let err = Outer::deserialize(Strict(vec![
+        ("integer", json!(10)),
+        ("before", json!(1.5)),
+        ("string", json!("hello")),
+        ("after", json!(true)),
+        ("extra", json!(null)),
+    ]))
+    .expect_err("undeclared keys should be rejected");
+
+    assert_eq!(
+        err.to_string(),
+        "unknown field `extra`, expected one of `before`, `after`, `integer`, `string`"
+    );
+}
+
+/// A format that records which method it was asked to deserialize with
+struct Recording<'a> {
+    entries: Vec<(&'static str, Value)>,
+    called: &'a mut Option<(&'static str, &'static [&'static str])>,
+}
+
+impl<'de> de::Deserializer<'de> for Recording<'_> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        *self.called = Some(("deserialize_any", &[]));
+        visitor.visit_map(MapDeserializer::new(self.entries.into_iter()))
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        *self.called = Some(("deserialize_map", &[]));
+        visitor.visit_map(MapDeserializer::new(self.entries.into_iter()))
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        *self.called = Some(("deserialize_struct", fields));
+        visitor.visit_map(MapDeserializer::new(self.entries.into_iter()))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+/// Struct inner types reach the format through `deserialize_struct`, with
+/// the capture's fields ahead of their own; other inner types use
+/// `deserialize_map`.
+#[test]
+fn forwarded_method() {
+    let entries = || {
+        vec![
+            ("integer", json!(10)),
+            ("before", json!(1.5)),
+            ("string", json!("hello")),
+        ]
+    };
+
+    let mut called = None;
+    let mut capture = Capture::default();
+    Inner::deserialize(FlattenDeserializer::new(
+        Recording {
+            entries: entries(),
+            called: &mut called,
+        },
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        called,
+        Some((
+            "deserialize_struct",
+            &["before", "after", "integer", "string"][..]
+        ))
+    );
+
+    let mut called = None;
+    let mut capture = Capture::default();
+    BTreeMap::<String, Value>::deserialize(FlattenDeserializer::new(
+        Recording {
+            entries: entries(),
+            called: &mut called,
+        },
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(called, Some(("deserialize_map", &[][..])));
+    assert_eq!(capture.before, Some(1.5));
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+#[allow(dead_code)]
+struct DenyInner {
+    integer: i32,
+    string: String,
+}
+
+fn deny_unknown(input: &str) -> Result<DenyInner, serde_json::Error> {
+    let mut capture = Capture::default();
+    DenyInner::deserialize(
+        FlattenDeserializer::new(&mut serde_json::Deserializer::from_str(input), &mut capture)
+            .deny_unknown_fields(),
+    )
+}
+
+#[test]
+fn unknown_field_lists_outer_fields() {
+    deny_unknown(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+        .expect("captured keys aren't unknown");
+
+    let err = deny_unknown(r#"{"integer": 10, "befor": 1.5, "string": "hello"}"#)
+        .expect_err("misspelled outer field should be rejected");
+
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `befor`, expected one of `before`, `after`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn new_deny_unknown() {
+    const ALLOWED: &[&str] = &["before", "after", "integer", "string"];
+
+    let deserialize = |input: &str| {
+        let mut capture = Capture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new_deny_unknown(
+            &mut serde_json::Deserializer::from_str(input),
+            &mut capture,
+            ALLOWED,
+        ))?;
+        capture.assemble::<serde_json::Error>(inner)
+    };
+
+    let data = deserialize(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+        .expect("failed to deserialize");
+    assert_eq!(data.inner.integer, 10);
+
+    let err = deserialize(r#"{"integer": 10, "junk": 1, "before": 1.5, "string": "hello"}"#)
+        .expect_err("`junk` isn't allowed");
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `junk`, expected one of `before`, `after`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+
+    // Keys that the inner type would otherwise have ignored are rejected too
+    let mut capture = Capture::default();
+    let err = de::IgnoredAny::deserialize(FlattenDeserializer::new_deny_unknown(
+        &mut serde_json::Deserializer::from_str(r#"{"before": 1.5, "extra": null}"#),
+        &mut capture,
+        ALLOWED,
+    ))
+    .expect_err("`extra` isn't allowed");
+    assert!(
+        err.to_string().starts_with("unknown field `extra`"),
+        "{}",
+        err
+    );
+    assert_eq!(capture.before, Some(1.5));
+}
+
+key_capture! {
+    pub Unmerged {
+        before: Option<f32>,
+        after: Option<bool>,
+    }
+}
+
+/// A capture that can't provide the combined field list has its struct
+/// inner types deserialized as maps, and unknown fields are still reported
+/// against both lists
+#[test]
+fn without_merged_fields() {
+    let mut called = None;
+    let mut capture = Unmerged::default();
+    Inner::deserialize(FlattenDeserializer::new(
+        Recording {
+            entries: vec![
+                ("integer", json!(10)),
+                ("before", json!(1.5)),
+                ("string", json!("hello")),
+            ],
+            called: &mut called,
+        },
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(called, Some(("deserialize_map", &[][..])));
+    assert_eq!(capture.before, Some(1.5));
+
+    let mut capture = Unmerged::default();
+    let err = DenyInner::deserialize(
+        FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "befor": 1.5}"#),
+            &mut capture,
+        )
+        .deny_unknown_fields(),
+    )
+    .expect_err("misspelled outer field should be rejected");
+
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `befor`, expected one of `before`, `after`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+}
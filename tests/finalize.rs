@@ -0,0 +1,115 @@
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FinalizeWithInner, FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Payload {
+    data: Vec<u8>,
+}
+
+impl Payload {
+    fn checksum(&self) -> u32 {
+        self.data.iter().map(|&b| u32::from(b)).sum()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Signed {
+    checksum: u32,
+    payload: Payload,
+}
+
+#[derive(Default)]
+struct Capture {
+    checksum: Option<u32>,
+}
+
+impl<'de> KeyCapture<'de> for &mut Capture {
+    type Token = ();
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        (key == b"checksum").then_some(())
+    }
+
+    fn send_value<D>(&mut self, _token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.checksum = Some(u32::deserialize(value)?);
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "struct Signed")
+    }
+}
+
+impl FinalizeWithInner<Payload> for Capture {
+    fn finalize_with_inner<E>(&mut self, inner: &Payload) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        let expected = inner.checksum();
+
+        match self.checksum {
+            None => Err(E::missing_field("checksum")),
+            Some(checksum) if checksum != expected => Err(E::custom(format_args!(
+                "checksum mismatch: expected {expected}, found {checksum}"
+            ))),
+            Some(_) => Ok(()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Signed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let payload = Payload::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+        capture.finalize_with_inner(&payload)?;
+
+        Ok(Signed {
+            checksum: capture.checksum.unwrap_or_default(),
+            payload,
+        })
+    }
+}
+
+#[test]
+fn matching_checksum() {
+    let data: Signed = serde_json::from_str(r#"{"checksum": 6, "data": [1, 2, 3]}"#)
+        .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Signed {
+            checksum: 6,
+            payload: Payload {
+                data: vec![1, 2, 3]
+            },
+        }
+    );
+}
+
+#[test]
+fn mismatched_checksum() {
+    let err = serde_json::from_str::<Signed>(r#"{"data": [1, 2, 3], "checksum": 7}"#)
+        .expect_err("checksum should be checked against the payload");
+
+    assert!(
+        err.to_string()
+            .starts_with("checksum mismatch: expected 6, found 7"),
+        "{}",
+        err
+    );
+
+    let err =
+        serde_json::from_str::<Signed>(r#"{"data": [1, 2, 3]}"#).expect_err("checksum is required");
+
+    assert!(
+        err.to_string().starts_with("missing field `checksum`"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,2220 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{no_buffer::NoBuffer, Capture, Inner, Outer};
+use serde::{de, Deserialize};
+use serde_bufferless::private::{
+    flatten::{
+        deserialize_capture_only, FinalizeInOrder, FlattenDeserializer, FlattenMapAccess,
+        FlattenSeqDeserializer, KeyCapture, MapRemainder,
+    },
+    SomeDeserializer,
+};
+use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+
+#[test]
+fn one_field() {
+    let data = Outer::deserialize(NoBuffer(&mut serde_json::Deserializer::from_str(
+        r#"{
+            "integer": 10,
+            "before": 10.5,
+            "string": "hello",
+            "after": true
+        }"#,
+    )))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 10.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+}
+
+/// `NoBuffer` does catch serde's own flatten, which buffers every entry
+#[test]
+#[should_panic = "deserialize_any was called"]
+fn serde_flatten_buffers() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct SerdeOuter {
+        before: f32,
+        after: bool,
+
+        #[serde(flatten)]
+        inner: Inner,
+    }
+
+    let _ = SerdeOuter::deserialize(NoBuffer(&mut serde_json::Deserializer::from_str(
+        r#"{"integer": 10, "before": 10.5, "string": "hello", "after": true}"#,
+    )));
+}
+
+/// Captured floats are handed to `f32::deserialize` and `f64::deserialize`
+/// with the original value deserializer, so neither should be widened or
+/// narrowed along the way.
+#[test]
+fn float_precision() {
+    #[derive(Debug, PartialEq)]
+    struct Floats {
+        single: f32,
+        double: f64,
+        inner: Inner,
+    }
+
+    #[allow(non_camel_case_types)]
+    enum Field {
+        single,
+        double,
+    }
+
+    #[derive(Default)]
+    struct Capture {
+        single: Option<f32>,
+        double: Option<f64>,
+    }
+
+    impl<'de> KeyCapture<'de> for &mut Capture {
+        type Token = Field;
+
+        fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+            match key {
+                b"single" => Some(Field::single),
+                b"double" => Some(Field::double),
+                _ => None,
+            }
+        }
+
+        fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            match field {
+                Field::single => self.single = Some(Deserialize::deserialize(value)?),
+                Field::double => self.double = Some(Deserialize::deserialize(value)?),
+            }
+
+            Ok(())
+        }
+
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+            write!(formatter, "struct Floats")
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Floats {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut capture = Capture::default();
+            let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(Floats {
+                single: capture
+                    .single
+                    .ok_or_else(|| de::Error::missing_field("single"))?,
+                double: capture
+                    .double
+                    .ok_or_else(|| de::Error::missing_field("double"))?,
+                inner,
+            })
+        }
+    }
+
+    // 16777217 is the smallest positive integer that isn't representable as
+    // an f32, and 0.1 is rounded differently by f32 and f64.
+    let data: Floats = serde_json::from_str(
+        r#"{
+            "single": 0.1,
+            "integer": 10,
+            "double": 16777217.1,
+            "string": "hello"
+        }"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data.single, 0.1_f32);
+    assert_eq!(data.double, 16777217.1_f64);
+    assert_ne!(data.double, 16777217.1_f32 as f64);
+
+    let data: Floats = serde_json::from_str(
+        r#"{"single": 16777217, "double": 0.1, "integer": 10, "string": "hello"}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data.single, 16777216.0_f32);
+    assert_eq!(data.double, 0.1_f64);
+    assert_ne!(data.double, 0.1_f32 as f64);
+
+    // serde_test distinguishes visit_f32 from visit_f64
+    assert_de_tokens(
+        &Floats {
+            single: 0.1,
+            double: 0.1,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        },
+        &[
+            Token::Map { len: None },
+            Token::Str("single"),
+            Token::F32(0.1),
+            Token::Str("double"),
+            Token::F64(0.1),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// When an adjacently tagged enum reads its tag first, serde hands the content
+/// deserializer straight to the variant's `Deserialize`, so the flattened
+/// struct is deserialized bufferlessly from the source. If the content comes
+/// first, serde buffers it, and the flattened struct is deserialized from
+/// that buffer instead; both should capture the same fields.
+#[test]
+fn adjacently_tagged_content() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(tag = "t", content = "c")]
+    enum Tagged {
+        V(Outer),
+    }
+
+    let expected = Tagged::V(Outer {
+        before: 1.5,
+        after: false,
+        inner: Inner {
+            integer: 3,
+            string: "content".to_owned(),
+        },
+    });
+
+    let tag_first: Tagged = serde_json::from_str(
+        r#"{
+            "t": "V",
+            "c": {"integer": 3, "after": false, "string": "content", "before": 1.5}
+        }"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(tag_first, expected);
+
+    let content_first: Tagged = serde_json::from_str(
+        r#"{
+            "c": {"before": 1.5, "string": "content", "after": false, "integer": 3},
+            "t": "V"
+        }"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(content_first, expected);
+}
+
+/// Defaulted fields can be pre-seeded in the capture; only the fields that
+/// are present in the input are overwritten.
+#[test]
+fn preseeded_defaults() {
+    let mut capture = Capture {
+        before: Some(1.0),
+        after: Some(true),
+    };
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "after": false, "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(capture.before, Some(1.0));
+    assert_eq!(capture.after, Some(false));
+
+    // Required fields are left empty, so their absence is still detected
+    let mut capture = Capture {
+        before: Some(1.0),
+        after: None,
+    };
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "string": "hello"}"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    let err = capture
+        .assemble::<serde_json::Error>(inner)
+        .expect_err("`after` should be missing");
+    assert_eq!(err.to_string(), "missing field `after`");
+}
+
+/// An inner type that calls `deserialize_any` and branches on the shape of the
+/// data only ever sees a map when it's flattened; other shapes are rejected
+/// with an error describing the outer struct.
+#[test]
+fn inner_deserialize_any() {
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Map(Vec<(String, i32)>),
+        Str(String),
+    }
+
+    impl<'de> Deserialize<'de> for Shape {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = Shape;
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    write!(formatter, "a map or a string")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Shape, E> {
+                    Ok(Shape::Str(v.to_owned()))
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Shape, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+                {
+                    let mut entries = Vec::new();
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(Shape::Map(entries))
+                }
+            }
+
+            deserializer.deserialize_any(Visitor)
+        }
+    }
+
+    let mut capture = Capture::default();
+    let shape = Shape::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"a": 1, "before": 2, "b": 3, "after": true}"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        shape,
+        Shape::Map(vec![("a".to_owned(), 1), ("b".to_owned(), 3)])
+    );
+    assert_eq!(capture.before, Some(2.0));
+    assert_eq!(capture.after, Some(true));
+
+    let mut capture = Capture::default();
+    let err = Shape::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#""hello""#),
+        &mut capture,
+    ))
+    .expect_err("a string can't be flattened");
+
+    assert!(
+        err.to_string()
+            .starts_with(r#"invalid type: string "hello", expected struct Outer"#),
+        "{}",
+        err
+    );
+}
+
+/// Compact formats may identify fields by their declared index; the capture
+/// gets the first chance at those, and the rest go to the inner type.
+#[test]
+fn field_indices() {
+    let expected = Outer {
+        before: 2.5,
+        after: true,
+        inner: Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        },
+    };
+
+    assert_de_tokens(
+        &expected,
+        &[
+            Token::Map { len: Some(4) },
+            Token::U64(1),
+            Token::Bool(true),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::U64(0),
+            Token::F32(2.5),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::MapEnd,
+        ],
+    );
+
+    // Indices the capture rejects are forwarded to the inner type, which
+    // uses its own numbering
+    assert_de_tokens(
+        &expected,
+        &[
+            Token::Map { len: Some(4) },
+            Token::U8(0),
+            Token::F32(2.5),
+            Token::U32(1),
+            Token::Bool(true),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::U64(7),
+            Token::Unit,
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// A minimal streaming parser, whose deserializer and map access both hold a
+/// `&mut` borrow of the parser state, the same way real streaming parsers do.
+#[test]
+fn stateful_source() {
+    #[derive(Debug, Clone, Copy)]
+    enum Tok<'a> {
+        MapStart,
+        MapEnd,
+        Str(&'a str),
+        Int(i64),
+        Float(f64),
+        Bool(bool),
+    }
+
+    struct Parser<'a> {
+        tokens: &'a [Tok<'a>],
+        position: usize,
+        values_read: usize,
+    }
+
+    impl<'a> Parser<'a> {
+        fn next(&mut self) -> Tok<'a> {
+            let tok = self.tokens[self.position];
+            self.position += 1;
+            tok
+        }
+    }
+
+    impl<'de> de::Deserializer<'de> for &mut Parser<'de> {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            self.values_read += 1;
+            match self.next() {
+                Tok::MapStart => visitor.visit_map(ParserMap { parser: self }),
+                Tok::MapEnd => Err(de::Error::custom("unexpected end of map")),
+                Tok::Str(s) => visitor.visit_borrowed_str(s),
+                Tok::Int(i) => visitor.visit_i64(i),
+                Tok::Float(f) => visitor.visit_f64(f),
+                Tok::Bool(b) => visitor.visit_bool(b),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    struct ParserMap<'a, 'de> {
+        parser: &'a mut Parser<'de>,
+    }
+
+    impl<'de, 'a> de::MapAccess<'de> for ParserMap<'a, 'de> {
+        type Error = de::value::Error;
+
+        fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+        where
+            K: de::DeserializeSeed<'de>,
+        {
+            if let Tok::MapEnd = self.parser.tokens[self.parser.position] {
+                self.parser.position += 1;
+                return Ok(None);
+            }
+
+            seed.deserialize(&mut *self.parser).map(Some)
+        }
+
+        fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::DeserializeSeed<'de>,
+        {
+            seed.deserialize(&mut *self.parser)
+        }
+    }
+
+    let tokens = [
+        Tok::MapStart,
+        Tok::Str("before"),
+        Tok::Float(1.5),
+        Tok::Str("integer"),
+        Tok::Int(10),
+        Tok::Str("after"),
+        Tok::Bool(true),
+        Tok::Str("string"),
+        Tok::Str("hello"),
+        Tok::MapEnd,
+    ];
+
+    let mut parser = Parser {
+        tokens: &tokens,
+        position: 0,
+        values_read: 0,
+    };
+
+    let data = Outer::deserialize(&mut parser).expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+
+    // The whole map, and nothing else, was consumed through the borrow, and
+    // the parser is still usable afterwards
+    assert_eq!(parser.position, tokens.len());
+    assert_eq!(parser.values_read, 9);
+}
+
+/// Derived structs consume every entry of the map, so they don't need the
+/// trailing drain.
+#[test]
+fn without_drain() {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(
+        FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(
+                r#"{"integer": 10, "string": "hello", "extra": [1, 2], "after": true, "before": 1.5}"#,
+            ),
+            &mut capture,
+        )
+        .without_drain(),
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        capture
+            .assemble::<serde_json::Error>(inner)
+            .expect("missing fields"),
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+}
+
+/// Values are sent to the capture in input order, but the outer struct is
+/// assembled in declaration order.
+#[test]
+fn assembly_order() {
+    #[derive(Default)]
+    struct Logging {
+        capture: Capture,
+        log: Vec<&'static str>,
+    }
+
+    impl<'de> KeyCapture<'de> for &mut Logging {
+        type Token = common::Field;
+
+        fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+            (&mut self.capture).try_send_key(key)
+        }
+
+        fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            self.log.push(match field {
+                common::Field::before => "send before",
+                common::Field::after => "send after",
+            });
+            (&mut self.capture).send_value(field, value)
+        }
+
+        fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+            write!(formatter, "struct Outer")
+        }
+    }
+
+    impl FinalizeInOrder for Logging {
+        type Field = common::Field;
+
+        const FIELDS: &'static [common::Field] = &[common::Field::before, common::Field::after];
+
+        fn finalize_field<E>(&mut self, field: common::Field) -> Result<(), E>
+        where
+            E: de::Error,
+        {
+            let (name, present) = match field {
+                common::Field::before => ("before", self.capture.before.is_some()),
+                common::Field::after => ("after", self.capture.after.is_some()),
+            };
+
+            if !present {
+                return Err(E::missing_field(name));
+            }
+
+            self.log.push(match field {
+                common::Field::before => "assemble before",
+                common::Field::after => "assemble after",
+            });
+            Ok(())
+        }
+    }
+
+    let mut logging = Logging::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"after": true, "integer": 10, "before": 1.5, "string": "hello"}"#,
+        ),
+        &mut logging,
+    ))
+    .expect("failed to deserialize");
+
+    logging
+        .finalize_in_order::<serde_json::Error>()
+        .expect("both fields are present");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(
+        logging.log,
+        [
+            "send after",
+            "send before",
+            "assemble before",
+            "assemble after"
+        ]
+    );
+
+    // The first field missing in declaration order is the one reported
+    let mut logging = Logging::default();
+    Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "string": "hello"}"#),
+        &mut logging,
+    ))
+    .expect("failed to deserialize");
+
+    let err = logging
+        .finalize_in_order::<serde_json::Error>()
+        .expect_err("both fields are missing");
+    assert_eq!(err.to_string(), "missing field `before`");
+}
+
+/// Only the captured fields are deserialized; the rest of the document is
+/// skipped without being deserialized into anything.
+#[test]
+fn capture_only() {
+    let mut json = String::from(r#"{"after": false, "large": ["#);
+    for i in 0..1000 {
+        json.push_str(&format!(
+            r#"{{"id": {i}, "tags": ["a", "b"], "nested": {{"x": null}}}},"#
+        ));
+    }
+    json.push_str(r#"{}], "before": 3.5, "trailing": "ignored"}"#);
+
+    let mut capture = Capture::default();
+    deserialize_capture_only(&mut serde_json::Deserializer::from_str(&json), &mut capture)
+        .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(3.5));
+    assert_eq!(capture.after, Some(false));
+}
+
+/// A key wrapped in a `Some` is still offered to the capture.
+#[test]
+fn option_wrapped_key() {
+    assert_de_tokens(
+        &Outer {
+            before: 2.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        },
+        &[
+            Token::Map { len: Some(4) },
+            Token::Some,
+            Token::Str("before"),
+            Token::F32(2.5),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::Some,
+            Token::U64(1),
+            Token::Bool(true),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::MapEnd,
+        ],
+    );
+
+    // Rejected keys are forwarded to the inner type still wrapped in a `Some`
+    #[derive(Debug, PartialEq)]
+    struct OptionKeys {
+        before: Option<f32>,
+        rest: BTreeMap<Option<String>, i32>,
+    }
+
+    impl<'de> Deserialize<'de> for OptionKeys {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut capture = Capture::default();
+            let rest = BTreeMap::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(OptionKeys {
+                before: capture.before,
+                rest,
+            })
+        }
+    }
+
+    assert_de_tokens(
+        &OptionKeys {
+            before: Some(2.5),
+            rest: BTreeMap::from([(Some("key".to_owned()), 1), (None, 2)]),
+        },
+        &[
+            Token::Map { len: Some(3) },
+            Token::Some,
+            Token::Str("key"),
+            Token::I32(1),
+            Token::Some,
+            Token::Str("before"),
+            Token::F32(2.5),
+            Token::None,
+            Token::I32(2),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// Captured keys are never forwarded, so they're invisible to the inner
+/// type's `deny_unknown_fields`; anything the capture rejects and the inner
+/// type doesn't know is still an error.
+#[test]
+fn inner_deny_unknown_fields() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct DenyInner {
+        integer: i32,
+        string: String,
+    }
+
+    let mut capture = Capture::default();
+    let inner = DenyInner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("captured keys shouldn't count as unknown");
+
+    assert_eq!(
+        inner,
+        DenyInner {
+            integer: 10,
+            string: "hello".to_owned()
+        }
+    );
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+
+    let mut capture = Capture::default();
+    let err = DenyInner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "junk": null, "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect_err("junk key should be rejected by the inner type");
+
+    assert!(
+        err.to_string()
+            .starts_with("unknown field `junk`, expected `integer` or `string`"),
+        "{}",
+        err
+    );
+    assert_eq!(capture.before, Some(1.5));
+}
+
+/// Boolean, signed and floating point keys can be captured into named fields
+#[test]
+fn scalar_keys() {
+    #[derive(Debug, PartialEq)]
+    struct Sections {
+        enabled: Option<String>,
+        disabled: Option<String>,
+        below: Option<String>,
+        half: Option<String>,
+        rest: BTreeMap<i64, String>,
+    }
+
+    impl<'de> Deserialize<'de> for Sections {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            enum Field {
+                Enabled,
+                Disabled,
+                Below,
+                Half,
+            }
+
+            #[derive(Default)]
+            struct Capture {
+                enabled: Option<String>,
+                disabled: Option<String>,
+                below: Option<String>,
+                half: Option<String>,
+            }
+
+            impl<'de> KeyCapture<'de> for &mut Capture {
+                type Token = Field;
+
+                fn try_send_key(&mut self, _key: &[u8]) -> Option<Self::Token> {
+                    None
+                }
+
+                fn try_send_bool(&mut self, key: bool) -> Option<Self::Token> {
+                    Some(if key { Field::Enabled } else { Field::Disabled })
+                }
+
+                fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+                    (key < 0).then_some(Field::Below)
+                }
+
+                fn try_send_float(&mut self, key: f64) -> Option<Self::Token> {
+                    (key == 0.5).then_some(Field::Half)
+                }
+
+                fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+                where
+                    D: de::Deserializer<'de>,
+                {
+                    let slot = match field {
+                        Field::Enabled => &mut self.enabled,
+                        Field::Disabled => &mut self.disabled,
+                        Field::Below => &mut self.below,
+                        Field::Half => &mut self.half,
+                    };
+
+                    *slot = Some(String::deserialize(value)?);
+                    Ok(())
+                }
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    write!(formatter, "struct Sections")
+                }
+            }
+
+            let mut capture = Capture::default();
+            let rest = BTreeMap::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(Sections {
+                enabled: capture.enabled,
+                disabled: capture.disabled,
+                below: capture.below,
+                half: capture.half,
+                rest,
+            })
+        }
+    }
+
+    assert_de_tokens(
+        &Sections {
+            enabled: Some("on".to_owned()),
+            disabled: Some("off".to_owned()),
+            below: Some("negative".to_owned()),
+            half: Some("half".to_owned()),
+            rest: BTreeMap::from([(1, "one".to_owned()), (2, "two".to_owned())]),
+        },
+        &[
+            Token::Map { len: Some(6) },
+            Token::Bool(true),
+            Token::Str("on"),
+            Token::I8(1),
+            Token::Str("one"),
+            Token::F32(0.5),
+            Token::Str("half"),
+            Token::I32(-3),
+            Token::Str("negative"),
+            Token::Bool(false),
+            Token::Str("off"),
+            Token::I64(2),
+            Token::Str("two"),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// An inner type that only reads the first entry of the map
+#[derive(Debug, PartialEq)]
+struct FirstEntry(Option<(String, i32)>);
+
+impl<'de> Deserialize<'de> for FirstEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = FirstEntry;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "a map")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                map.next_entry().map(FirstEntry)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// Collects every remaining entry
+struct Rest;
+
+impl<'de> MapRemainder<'de> for Rest {
+    type Value = BTreeMap<String, i32>;
+
+    fn visit_remainder<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut rest = BTreeMap::new();
+
+        while let Some((key, value)) = map.next_entry()? {
+            rest.insert(key, value);
+        }
+
+        Ok(rest)
+    }
+}
+
+/// Without the drain, the entries that the inner type left behind can be
+/// read afterwards, with the captured fields among them still captured
+#[test]
+fn remainder() {
+    let mut capture = Capture::default();
+    let (first, rest): (FirstEntry, _) = FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 1, "before": 1.5, "x": 2, "after": true, "y": 3}"#,
+        ),
+        &mut capture,
+    )
+    .deserialize_without_drain(Rest)
+    .expect("failed to deserialize");
+
+    assert_eq!(first, FirstEntry(Some(("integer".to_owned(), 1))));
+    assert_eq!(
+        rest,
+        BTreeMap::from([("x".to_owned(), 2), ("y".to_owned(), 3)])
+    );
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+
+    // An inner type that read the whole map leaves nothing, and the ended
+    // map can still be read
+    let mut capture = Capture::default();
+    let (rest, empty): (BTreeMap<String, i32>, _) = FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"x": 2, "after": true}"#),
+        &mut capture,
+    )
+    .deserialize_without_drain(Rest)
+    .expect("failed to deserialize");
+
+    assert_eq!(rest, BTreeMap::from([("x".to_owned(), 2)]));
+    assert!(empty.is_empty());
+    assert_eq!(capture.after, Some(true));
+}
+
+/// An inner type that stops after its first entry leaves the rest of the map
+/// to the drain; captured keys found by the drain are still captured, and the
+/// drain stops cleanly at the end of the map.
+#[test]
+fn captured_during_drain() {
+    let mut capture = Capture::default();
+    let first = FirstEntry::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 1, "before": 1.5, "junk": [1, 2], "after": true}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(first, FirstEntry(Some(("integer".to_owned(), 1))));
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+
+    // serde_test checks that every token was consumed, including the map end
+    #[derive(Debug, PartialEq)]
+    struct Drained {
+        first: FirstEntry,
+        after: Option<bool>,
+    }
+
+    impl<'de> Deserialize<'de> for Drained {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut capture = Capture::default();
+            let first =
+                FirstEntry::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(Drained {
+                first,
+                after: capture.after,
+            })
+        }
+    }
+
+    assert_de_tokens(
+        &Drained {
+            first: FirstEntry(Some(("integer".to_owned(), 1))),
+            after: Some(false),
+        },
+        &[
+            Token::Map { len: Some(3) },
+            Token::Str("integer"),
+            Token::I32(1),
+            Token::Str("string"),
+            Token::Str("ignored"),
+            Token::Str("after"),
+            Token::Bool(false),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// A `&mut serde_json::Deserializer` can be flattened through and then reused
+/// for the next value in the stream.
+#[test]
+fn reused_deserializer() {
+    let mut de = serde_json::Deserializer::from_str(
+        r#"{"integer": 1, "before": 1.5, "string": "a", "after": true}
+           {"after": false, "string": "b", "before": 2.5, "integer": 2}"#,
+    );
+
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(&mut de, &mut capture))
+        .expect("failed to deserialize");
+    let first = capture.assemble::<serde_json::Error>(inner).unwrap();
+
+    let second = Outer::deserialize(&mut de).expect("failed to deserialize");
+    de.end().expect("both values should have been consumed");
+
+    assert_eq!(
+        (first, second),
+        (
+            Outer {
+                before: 1.5,
+                after: true,
+                inner: Inner {
+                    integer: 1,
+                    string: "a".to_owned(),
+                },
+            },
+            Outer {
+                before: 2.5,
+                after: false,
+                inner: Inner {
+                    integer: 2,
+                    string: "b".to_owned(),
+                },
+            },
+        )
+    );
+}
+
+/// Enum keys forwarded to the inner type still report the expected variants
+/// for an unknown variant. The visitor checks the variant itself, so this
+/// holds whether the key is replayed through `deserialize_enum` or
+/// `deserialize_any`.
+#[test]
+fn enum_keys() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    enum Kind {
+        Circle,
+        Rect,
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct EnumKeys {
+        before: Option<f32>,
+        rest: BTreeMap<Kind, i32>,
+    }
+
+    impl<'de> Deserialize<'de> for EnumKeys {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut capture = Capture::default();
+            let rest = BTreeMap::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(EnumKeys {
+                before: capture.before,
+                rest,
+            })
+        }
+    }
+
+    assert_de_tokens(
+        &EnumKeys {
+            before: Some(2.5),
+            rest: BTreeMap::from([(Kind::Rect, 1)]),
+        },
+        &[
+            Token::Map { len: Some(2) },
+            Token::UnitVariant {
+                name: "Kind",
+                variant: "Rect",
+            },
+            Token::I32(1),
+            Token::Str("before"),
+            Token::F32(2.5),
+            Token::MapEnd,
+        ],
+    );
+
+    assert_de_tokens_error::<EnumKeys>(
+        &[
+            Token::Map { len: Some(1) },
+            Token::UnitVariant {
+                name: "Kind",
+                variant: "Triangle",
+            },
+        ],
+        "unknown variant `Triangle`, expected `Circle` or `Rect`",
+    );
+}
+
+/// `SomeDeserializer` answers `deserialize_option` itself, so an `Option`
+/// is always `Some`, even when the wrapped value is null.
+#[test]
+fn some_deserializer_option() {
+    let value: Option<i32> = Option::deserialize(SomeDeserializer::new(serde_json::json!(5)))
+        .expect("failed to deserialize");
+    assert_eq!(value, Some(5));
+
+    let value: Option<Option<i32>> =
+        Option::deserialize(SomeDeserializer::new(serde_json::Value::Null))
+            .expect("failed to deserialize");
+    assert_eq!(value, Some(None));
+}
+
+/// Newtype-wrapped keys are forwarded to the inner type as newtypes, so an
+/// inner type with newtype keys receives them intact
+#[test]
+fn newtype_keys() {
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+    struct Key(String);
+
+    #[derive(Debug, PartialEq)]
+    struct Keyed {
+        before: Option<f32>,
+        rest: BTreeMap<Key, i32>,
+    }
+
+    impl<'de> Deserialize<'de> for Keyed {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut capture = Capture::default();
+            let rest = BTreeMap::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(Keyed {
+                before: capture.before,
+                rest,
+            })
+        }
+    }
+
+    assert_de_tokens(
+        &Keyed {
+            before: Some(1.5),
+            rest: BTreeMap::from([(Key("a".to_owned()), 1), (Key("b".to_owned()), 2)]),
+        },
+        &[
+            Token::Map { len: Some(3) },
+            Token::NewtypeStruct { name: "Key" },
+            Token::Str("a"),
+            Token::I32(1),
+            Token::Str("before"),
+            Token::F32(1.5),
+            Token::NewtypeStruct { name: "Key" },
+            Token::Str("b"),
+            Token::I32(2),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// Formats that serialize structs as sequences are matched up by position,
+/// with the flattened fields last.
+#[test]
+fn sequence() {
+    assert_de_tokens(
+        &Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        },
+        &[
+            Token::Seq { len: Some(4) },
+            Token::F32(1.5),
+            Token::Bool(true),
+            Token::I32(10),
+            Token::Str("hello"),
+            Token::SeqEnd,
+        ],
+    );
+
+    let outer: Outer =
+        serde_json::from_str(r#"[2.5, false, 20, "world"]"#).expect("failed to deserialize");
+    assert_eq!(outer.before, 2.5);
+    assert_eq!(outer.inner.string, "world");
+
+    let err = serde_json::from_str::<Outer>("[2.5]").expect_err("`after` is missing");
+    assert!(
+        err.to_string()
+            .starts_with("invalid length 1, expected struct Outer"),
+        "{}",
+        err
+    );
+}
+
+/// A capture can put the flattened field anywhere in the sequence
+#[test]
+fn sequence_flatten_index() {
+    struct Middle<'a>(&'a mut Capture);
+
+    impl<'de> KeyCapture<'de> for Middle<'_> {
+        type Token = common::Field;
+
+        fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+            self.0.try_send_key(key)
+        }
+
+        fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+            self.0.try_send_index(index)
+        }
+
+        fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            self.0.send_value(token, value)
+        }
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            self.0.expecting(formatter)
+        }
+
+        fn fields(&self) -> &'static [&'static str] {
+            self.0.fields()
+        }
+
+        fn merged_fields(&self, inner: &'static [&'static str]) -> Option<&'static [&'static str]> {
+            self.0.merged_fields(inner)
+        }
+
+        fn flatten_index(&self) -> usize {
+            1
+        }
+    }
+
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"[1.5, 10, "hello", true]"#),
+        Middle(&mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// A tuple struct flattened alongside named fields
+#[derive(Debug, PartialEq, Deserialize)]
+struct Pair(i32, String);
+
+/// A positional inner type is read from the same sequence as the captured
+/// fields, which are offered by index
+#[test]
+fn tuple_struct() {
+    let mut capture = Capture::default();
+    let pair = Pair::deserialize(FlattenSeqDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"[1.5, true, 10, "hello"]"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(pair, Pair(10, "hello".to_owned()));
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+
+    // A `FlattenDeserializer` switches to the positional path by itself
+    let mut capture = Capture::default();
+    let pair = Pair::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"[2.5, false, 20, "world"]"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(pair, Pair(20, "world".to_owned()));
+    assert_eq!(capture.before, Some(2.5));
+    assert_eq!(capture.after, Some(false));
+}
+
+/// The tuple's length includes the captured fields, for formats that
+/// aren't self-describing
+#[test]
+fn tuple_struct_len() {
+    /// Records the length requested by `deserialize_tuple`
+    struct TupleLen<'a, D> {
+        deserializer: D,
+        len: &'a mut Option<usize>,
+    }
+
+    impl<'de, D: de::Deserializer<'de>> de::Deserializer<'de> for TupleLen<'_, D> {
+        type Error = D::Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, D::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            self.deserializer.deserialize_any(visitor)
+        }
+
+        fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, D::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            *self.len = Some(len);
+            self.deserializer.deserialize_tuple(len, visitor)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    let mut capture = Capture::default();
+    let mut len = None;
+    let pair = Pair::deserialize(FlattenDeserializer::new(
+        TupleLen {
+            deserializer: &mut serde_json::Deserializer::from_str(r#"[1.5, true, 10, "hello"]"#),
+            len: &mut len,
+        },
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(pair, Pair(10, "hello".to_owned()));
+    assert_eq!(len, Some(4));
+}
+
+/// The inner type's size hint leaves out the captured fields
+#[test]
+fn size_hint() {
+    #[derive(Debug, PartialEq)]
+    struct Hinted {
+        hint: Option<usize>,
+        rest: BTreeMap<String, i32>,
+    }
+
+    impl<'de> Deserialize<'de> for Hinted {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct Visitor;
+
+            impl<'de> de::Visitor<'de> for Visitor {
+                type Value = Hinted;
+
+                fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    formatter.write_str("a map")
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Hinted, A::Error>
+                where
+                    A: de::MapAccess<'de>,
+                {
+                    let hint = map.size_hint();
+                    let mut rest = BTreeMap::new();
+                    while let Some((key, value)) = map.next_entry()? {
+                        rest.insert(key, value);
+                    }
+
+                    Ok(Hinted { hint, rest })
+                }
+            }
+
+            deserializer.deserialize_map(Visitor)
+        }
+    }
+
+    // A `serde_json::Value` map knows exactly how many entries it has
+    let mut capture = Capture::default();
+    let hinted = Hinted::deserialize(FlattenDeserializer::new(
+        serde_json::json!({"before": 1.5, "a": 1, "after": true, "b": 2}),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(hinted.hint, Some(2));
+    assert_eq!(hinted.rest.len(), 2);
+    assert_eq!(capture.before, Some(1.5));
+}
+
+/// When the capture and the inner type both declare a field, the capture
+/// takes it, and the inner type never sees it
+#[test]
+fn shared_field_name() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Shared {
+        #[serde(default)]
+        before: f32,
+        integer: i32,
+    }
+
+    let mut capture = Capture::default();
+    let inner = Shared::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "before": 1.5}"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(
+        inner,
+        Shared {
+            before: 0.0,
+            integer: 10
+        }
+    );
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct SharedRequired {
+        before: f32,
+        integer: i32,
+    }
+
+    let err = SharedRequired::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "before": 1.5}"#),
+        &mut Capture::default(),
+    ))
+    .expect_err("the inner type never sees `before`");
+
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
+
+/// A map keyed by integers can have particular keys captured, whether the
+/// format produces them as unsigned or signed integers
+#[test]
+fn integer_keyed_map() {
+    #[derive(Debug, PartialEq)]
+    struct Levels {
+        zero: Option<String>,
+        rest: BTreeMap<i64, String>,
+    }
+
+    impl<'de> Deserialize<'de> for Levels {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            struct Capture(Option<String>);
+
+            impl<'de> KeyCapture<'de> for &mut Capture {
+                type Token = ();
+
+                fn try_send_key(&mut self, _key: &[u8]) -> Option<Self::Token> {
+                    None
+                }
+
+                fn try_send_index(&mut self, index: u64) -> Option<Self::Token> {
+                    (index == 0).then_some(())
+                }
+
+                fn try_send_int(&mut self, key: i64) -> Option<Self::Token> {
+                    (key == 0).then_some(())
+                }
+
+                fn send_value<D>(&mut self, _field: (), value: D) -> Result<(), D::Error>
+                where
+                    D: de::Deserializer<'de>,
+                {
+                    self.0 = Some(String::deserialize(value)?);
+                    Ok(())
+                }
+
+                fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                    write!(formatter, "struct Levels")
+                }
+            }
+
+            let mut capture = Capture(None);
+            let rest = BTreeMap::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+            Ok(Levels {
+                zero: capture.0,
+                rest,
+            })
+        }
+    }
+
+    let expected = Levels {
+        zero: Some("ground".to_owned()),
+        rest: BTreeMap::from([(-1, "basement".to_owned()), (1, "first".to_owned())]),
+    };
+
+    assert_de_tokens(
+        &expected,
+        &[
+            Token::Map { len: Some(3) },
+            Token::U64(1),
+            Token::Str("first"),
+            Token::U64(0),
+            Token::Str("ground"),
+            Token::I64(-1),
+            Token::Str("basement"),
+            Token::MapEnd,
+        ],
+    );
+
+    assert_de_tokens(
+        &expected,
+        &[
+            Token::Map { len: Some(3) },
+            Token::I64(0),
+            Token::Str("ground"),
+            Token::I32(-1),
+            Token::Str("basement"),
+            Token::I64(1),
+            Token::Str("first"),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// A format that rejects a key outright reports the capture's fields and the
+/// inner type's as the expected keys
+#[test]
+fn key_expecting() {
+    struct NullKey;
+
+    impl<'de> de::Deserializer<'de> for NullKey {
+        type Error = de::value::Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: de::Visitor<'de>,
+        {
+            Err(de::Error::invalid_type(
+                de::Unexpected::Other("null"),
+                &visitor,
+            ))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    impl<'de> de::IntoDeserializer<'de, de::value::Error> for NullKey {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self {
+            self
+        }
+    }
+
+    let mut capture = Capture::default();
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        de::value::MapDeserializer::new([(NullKey, 10)].into_iter()),
+        &mut capture,
+    ))
+    .expect_err("a null key should be rejected");
+
+    assert_eq!(
+        err.to_string(),
+        "invalid type: null, expected one of `before`, `after`, `integer`, `string`"
+    );
+}
+
+/// A capture that names its fields has errors from their values annotated
+/// with the field name
+#[test]
+fn field_name() {
+    struct Named<'a>(&'a mut Capture);
+
+    impl<'de> KeyCapture<'de> for Named<'_> {
+        type Token = common::Field;
+
+        fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+            self.0.try_send_key(key)
+        }
+
+        fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            self.0.send_value(token, value)
+        }
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            self.0.expecting(formatter)
+        }
+
+        fn field_name(&self, token: &Self::Token) -> Option<&'static str> {
+            Some(match token {
+                common::Field::before => "before",
+                common::Field::after => "after",
+            })
+        }
+    }
+
+    let mut capture = Capture::default();
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        serde_json::json!({"integer": 10, "before": 1.5, "after": "yes", "string": "hello"}),
+        Named(&mut capture),
+    ))
+    .expect_err("`after` should be a boolean");
+
+    assert_eq!(
+        err.to_string(),
+        r#"error deserializing field `after`: invalid type: string "yes", expected a boolean"#
+    );
+
+    // Without a name, the error is unchanged
+    let mut capture = Capture::default();
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        serde_json::json!({"integer": 10, "before": 1.5, "after": "yes", "string": "hello"}),
+        &mut capture,
+    ))
+    .expect_err("`after` should be a boolean");
+
+    assert_eq!(
+        err.to_string(),
+        r#"invalid type: string "yes", expected a boolean"#
+    );
+}
+
+#[test]
+fn deserialize_flattened() {
+    let (inner, capture): (Inner, Capture) = serde_bufferless::deserialize_flattened(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#,
+        ),
+        Capture::default(),
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// A flattened `Option` is `None` when the whole value is null, and is
+/// otherwise flattened as usual
+#[test]
+fn option() {
+    let mut capture = Capture::default();
+    let inner = Option::<Inner>::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        inner,
+        Some(Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        })
+    );
+    assert_eq!(capture.before, Some(1.5));
+
+    let mut capture = Capture::default();
+    let inner = Option::<Inner>::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str("null"),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner, None);
+    assert_eq!(capture.before, None);
+
+    // An `Option` of a struct with a flattened field
+    let data: Vec<Option<Outer>> = serde_json::from_str(
+        r#"[null, {"integer": 10, "before": 1.5, "string": "hello", "after": true}]"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        [
+            None,
+            Some(Outer {
+                before: 1.5,
+                after: true,
+                inner: Inner {
+                    integer: 10,
+                    string: "hello".to_owned(),
+                },
+            })
+        ]
+    );
+}
+
+/// A flattened `Option` serialized on its own, where absence is an explicit
+/// null, deserializes back to the same value
+#[test]
+fn option_round_trip() {
+    for value in [
+        None,
+        Some(Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        }),
+    ] {
+        let json = serde_json::to_string(&value).expect("failed to serialize");
+
+        let mut capture = Capture::default();
+        let round_tripped = Option::<Inner>::deserialize(FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(&json),
+            &mut capture,
+        ))
+        .expect("failed to deserialize");
+
+        assert_eq!(round_tripped, value, "{json}");
+    }
+}
+
+#[test]
+fn match_field() {
+    use serde_bufferless::private::flatten::match_field;
+
+    const FIELDS: &[(&[u8], u32)] = &[(b"after", 1), (b"before", 0), (b"prior", 0)];
+
+    assert_eq!(match_field(FIELDS, b"after"), Some(1));
+    assert_eq!(match_field(FIELDS, b"before"), Some(0));
+    assert_eq!(match_field(FIELDS, b"prior"), Some(0));
+    assert_eq!(match_field(FIELDS, b"befor"), None);
+    assert_eq!(match_field(FIELDS, b""), None);
+    assert_eq!(match_field(&[], b"before"), None);
+}
+
+/// Input that isn't a map is reported against the outer struct, not the
+/// inner type
+#[test]
+fn not_a_map() {
+    for (input, expected) in [
+        ("[]", "invalid length 0, expected struct Outer"),
+        ("[[1]]", "invalid type: sequence, expected f32"),
+        ("10", "invalid type: integer `10`, expected struct Outer"),
+        (
+            "true",
+            "invalid type: boolean `true`, expected struct Outer",
+        ),
+    ] {
+        let err = serde_json::from_str::<Outer>(input).expect_err("not a map");
+        assert!(err.to_string().starts_with(expected), "{input}: {err}");
+    }
+}
+
+/// Counts the keys it's offered, and reports itself complete once `before`
+/// is filled
+struct CompleteAfterBefore<'a> {
+    capture: &'a mut Capture,
+    offered: usize,
+}
+
+impl<'de> KeyCapture<'de> for &mut CompleteAfterBefore<'_> {
+    type Token = common::Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.offered += 1;
+        self.capture.try_send_key(key)
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token, value)
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.capture.before.is_some()
+    }
+}
+
+/// Once the capture is complete, later keys go straight to the inner type,
+/// even ones the capture would have accepted
+#[test]
+fn is_complete() {
+    let mut capture = Capture::default();
+    let mut complete = CompleteAfterBefore {
+        capture: &mut capture,
+        offered: 0,
+    };
+
+    let rest: BTreeMap<String, serde_json::Value> =
+        BTreeMap::deserialize(FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(
+                r#"{"a": 1, "before": 1.5, "b": 2, "after": true, "before": 2.5}"#,
+            ),
+            &mut complete,
+        ))
+        .expect("failed to deserialize");
+
+    assert_eq!(complete.offered, 2);
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, None);
+    assert_eq!(
+        rest,
+        BTreeMap::from([
+            ("a".to_owned(), serde_json::json!(1)),
+            ("b".to_owned(), serde_json::json!(2)),
+            ("after".to_owned(), serde_json::json!(true)),
+            ("before".to_owned(), serde_json::json!(2.5)),
+        ])
+    );
+}
+
+/// Formats may emit a key as a unit variant, whose name is offered to the
+/// capture like any other key
+#[test]
+fn captured_enum_keys() {
+    assert_de_tokens(
+        &Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        },
+        &[
+            Token::Map { len: None },
+            Token::UnitVariant {
+                name: "Field",
+                variant: "before",
+            },
+            Token::F32(1.5),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::UnitVariant {
+                name: "Field",
+                variant: "after",
+            },
+            Token::Bool(true),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// A unit variant key whose name the format hands over with `visit_str`,
+/// rather than borrowing it from the input
+struct TransientVariant(&'static str);
+
+impl<'de> de::Deserializer<'de> for TransientVariant {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_enum(de::value::StrDeserializer::new(self.0))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> de::IntoDeserializer<'de> for TransientVariant {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// A transient variant name is offered to the capture as-is, so capturing
+/// it doesn't need to copy it (and works without `alloc`)
+#[test]
+fn transient_enum_keys() {
+    let mut capture = Capture::default();
+    deserialize_capture_only(
+        de::value::MapDeserializer::new([(TransientVariant("before"), 4.5f32)].into_iter()),
+        &mut capture,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(4.5));
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Active,
+    Failed { code: u32 },
+}
+
+serde_bufferless::key_capture! {
+    StatusCapture {
+        status: Option<Status>,
+    }
+}
+
+/// Enum-typed sibling values are deserialized by the capture like any other
+#[test]
+fn enum_values() {
+    for (input, expected) in [
+        (
+            r#"{"integer": 10, "status": "active", "string": "hello"}"#,
+            Status::Active,
+        ),
+        (
+            r#"{"status": {"failed": {"code": 3}}, "integer": 10, "string": "hello"}"#,
+            Status::Failed { code: 3 },
+        ),
+    ] {
+        let mut capture = StatusCapture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(
+            &mut serde_json::Deserializer::from_str(input),
+            &mut capture,
+        ))
+        .expect("failed to deserialize");
+
+        assert_eq!(capture.status, Some(expected));
+        assert_eq!(inner.integer, 10);
+    }
+}
+
+/// A `FlattenMapAccess` read by hand returns only the rejected keys, and
+/// `finish` drains the rest through the capture
+#[test]
+fn map_access() {
+    struct FirstEntry<'a>(&'a mut Capture);
+
+    impl<'de> de::Visitor<'de> for FirstEntry<'_> {
+        type Value = Option<(String, i32)>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("a map")
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut map = FlattenMapAccess::new(map, self.0);
+            let first = de::MapAccess::next_entry(&mut map)?;
+            map.finish()?;
+            Ok(first)
+        }
+    }
+
+    let mut capture = Capture::default();
+    let first = de::Deserializer::deserialize_map(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"before": 1.5, "first": 1, "second": 2, "after": true}"#,
+        ),
+        FirstEntry(&mut capture),
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(first, Some(("first".to_owned(), 1)));
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+}
+
+/// A field is captured the same way whichever of serde's string or byte
+/// representations its key arrives as
+#[test]
+fn key_representations() {
+    let keys: [fn(&'static str) -> Token; 6] = [
+        Token::Str,
+        Token::BorrowedStr,
+        Token::String,
+        |key| Token::Bytes(key.as_bytes()),
+        |key| Token::BorrowedBytes(key.as_bytes()),
+        |key| Token::ByteBuf(key.as_bytes()),
+    ];
+
+    for key in keys {
+        assert_de_tokens(
+            &Outer {
+                before: 1.5,
+                after: true,
+                inner: Inner {
+                    integer: 10,
+                    string: "hello".to_owned(),
+                },
+            },
+            &[
+                Token::Map { len: None },
+                key("before"),
+                Token::F32(1.5),
+                key("integer"),
+                Token::I32(10),
+                key("after"),
+                Token::Bool(true),
+                key("string"),
+                Token::Str("hello"),
+                Token::MapEnd,
+            ],
+        );
+    }
+}
+
+/// A name, or a map of named values, distinguished by the shape of the input
+#[derive(Debug, PartialEq)]
+enum Shape {
+    Name(String),
+    Values(BTreeMap<String, i32>),
+}
+
+impl<'de> Deserialize<'de> for Shape {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = Shape;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a name or a map")
+            }
+
+            fn visit_str<E>(self, name: &str) -> Result<Shape, E>
+            where
+                E: de::Error,
+            {
+                Ok(Shape::Name(name.to_owned()))
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Shape, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))
+                    .map(Shape::Values)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// An inner type that calls `deserialize_any` sees the input's own shape, but
+/// only a map can be flattened, and anything else is reported against the
+/// outer struct
+#[test]
+fn any_shape() {
+    let mut capture = Capture::default();
+    let shape = Shape::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"x": 1, "before": 1.5, "y": 2}"#),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        shape,
+        Shape::Values(BTreeMap::from([("x".to_owned(), 1), ("y".to_owned(), 2)]))
+    );
+    assert_eq!(capture.before, Some(1.5));
+
+    let err = Shape::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#""name""#),
+        &mut Capture::default(),
+    ))
+    .expect_err("flattened a string");
+
+    assert_eq!(
+        err.to_string(),
+        r#"invalid type: string "name", expected struct Outer at line 1 column 6"#
+    );
+}
+
+/// A map that fails the test if it's read any further after `key` has been
+/// returned without its value being read
+struct StrictMap {
+    entries: std::vec::IntoIter<(&'static str, i32)>,
+    pending: Option<i32>,
+    stop_at: &'static str,
+    stopped: bool,
+}
+
+impl<'de> de::MapAccess<'de> for StrictMap {
+    type Error = de::value::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        assert!(!self.stopped, "the map was read after the inner error");
+        assert!(
+            self.pending.is_none(),
+            "a key was read before the last value"
+        );
+
+        match self.entries.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.stopped = key == self.stop_at;
+                self.pending = Some(value);
+                seed.deserialize(de::value::BorrowedStrDeserializer::new(key))
+                    .map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        assert!(!self.stopped, "the map was read after the inner error");
+        let value = self
+            .pending
+            .take()
+            .expect("a value was read before its key");
+        seed.deserialize(de::IntoDeserializer::into_deserializer(value))
+    }
+}
+
+/// An inner type that fails as soon as it sees the `fail` key, without
+/// reading its value
+struct FailsMidMap;
+
+impl<'de> Deserialize<'de> for FailsMidMap {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = FailsMidMap;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a map")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<FailsMidMap, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                while let Some(key) = map.next_key::<&str>()? {
+                    if key == "fail" {
+                        return Err(de::Error::custom("inner type failed"));
+                    }
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+
+                Ok(FailsMidMap)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+/// An error from the inner type is returned as-is, without reading the
+/// rest of the map (or the value of the key it stopped at) and without
+/// finishing the capture
+#[test]
+fn inner_error_mid_map() {
+    let map = StrictMap {
+        entries: vec![("x", 1), ("fail", 2), ("y", 3)].into_iter(),
+        pending: None,
+        stop_at: "fail",
+        stopped: false,
+    };
+
+    let mut capture = Capture::default();
+    let err = FailsMidMap::deserialize(FlattenDeserializer::new(
+        de::value::MapAccessDeserializer::new(map),
+        &mut capture,
+    ))
+    .err()
+    .expect("the inner type failed");
+
+    assert_eq!(err.to_string(), "inner type failed");
+}
@@ -0,0 +1,77 @@
+use serde::de::{self, value::Error, IgnoredAny, MapAccess};
+use serde_bufferless::private::FusedAccess;
+
+/// A map with a single entry, which panics if it's polled after its end
+struct Single {
+    entries: u8,
+    ended: bool,
+}
+
+impl<'de> MapAccess<'de> for Single {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        assert!(!self.ended, "polled after the end of the map");
+
+        match self.entries {
+            0 => {
+                self.ended = true;
+                Ok(None)
+            }
+            _ => {
+                self.entries -= 1;
+                seed.deserialize(de::value::StrDeserializer::new("key"))
+                    .map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        assert!(!self.ended, "polled after the end of the map");
+        seed.deserialize(de::value::U32Deserializer::new(1))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        assert!(!self.ended, "polled after the end of the map");
+        Some(self.entries.into())
+    }
+}
+
+#[test]
+fn stays_ended() {
+    let mut map = FusedAccess::new(Single {
+        entries: 1,
+        ended: false,
+    });
+
+    assert_eq!(
+        map.next_entry::<String, u32>().unwrap(),
+        Some(("key".to_owned(), 1))
+    );
+    assert!(map.next_key::<IgnoredAny>().unwrap().is_none());
+    assert!(map.next_key::<IgnoredAny>().unwrap().is_none());
+}
+
+#[test]
+fn value_after_end() {
+    let mut map = FusedAccess::new(Single {
+        entries: 0,
+        ended: false,
+    });
+
+    assert!(map.next_key::<IgnoredAny>().unwrap().is_none());
+
+    let err = map
+        .next_value::<IgnoredAny>()
+        .expect_err("there's no value after the end of the map");
+    assert_eq!(
+        err.to_string(),
+        "called next_value_seed after next_key_seed returned None"
+    );
+}
@@ -0,0 +1,154 @@
+//! Third-party types like `chrono::DateTime` and std types like `IpAddr` have
+//! `Deserialize` impls that may branch on `is_human_readable`; both the
+//! captured values and the flattened inner type must see the source format's
+//! answer.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de, Deserialize, Serialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+/// Records whether the deserializer it was given claimed to be human readable
+#[derive(Debug, PartialEq)]
+struct Probe<T> {
+    human_readable: bool,
+    value: T,
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Probe<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        Ok(Probe {
+            human_readable: deserializer.is_human_readable(),
+            value: T::deserialize(deserializer)?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Inner {
+    port: u16,
+    peer: IpAddr,
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    timestamp: DateTime<Utc>,
+    addr: IpAddr,
+    inner: Probe<Inner>,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        #[allow(non_camel_case_types)]
+        enum Field {
+            timestamp,
+            addr,
+        }
+
+        #[derive(Default)]
+        struct Capture {
+            timestamp: Option<DateTime<Utc>>,
+            addr: Option<IpAddr>,
+        }
+
+        impl<'de> KeyCapture<'de> for &mut Capture {
+            type Token = Field;
+
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                match key {
+                    b"timestamp" => Some(Field::timestamp),
+                    b"addr" => Some(Field::addr),
+                    _ => None,
+                }
+            }
+
+            fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                match field {
+                    Field::timestamp => self.timestamp = Some(Deserialize::deserialize(value)?),
+                    Field::addr => self.addr = Some(Deserialize::deserialize(value)?),
+                }
+
+                Ok(())
+            }
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "struct Outer")
+            }
+        }
+
+        let mut capture = Capture::default();
+        let inner = Deserialize::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Outer {
+            timestamp: capture
+                .timestamp
+                .ok_or_else(|| de::Error::missing_field("timestamp"))?,
+            addr: capture
+                .addr
+                .ok_or_else(|| de::Error::missing_field("addr"))?,
+            inner,
+        })
+    }
+}
+
+/// The same data, as serde would serialize it with the flattened fields
+/// inlined
+#[derive(Serialize)]
+struct Wire {
+    port: u16,
+    timestamp: DateTime<Utc>,
+    peer: IpAddr,
+    addr: IpAddr,
+}
+
+fn wire() -> Wire {
+    Wire {
+        port: 8080,
+        timestamp: Utc.with_ymd_and_hms(2022, 3, 14, 15, 9, 26).unwrap(),
+        peer: IpAddr::V6(Ipv6Addr::LOCALHOST),
+        addr: IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+    }
+}
+
+fn expected(human_readable: bool) -> Outer {
+    let wire = wire();
+
+    Outer {
+        timestamp: wire.timestamp,
+        addr: wire.addr,
+        inner: Probe {
+            human_readable,
+            value: Inner {
+                port: wire.port,
+                peer: wire.peer,
+            },
+        },
+    }
+}
+
+#[test]
+fn json() {
+    let json = serde_json::to_string(&wire()).expect("failed to serialize");
+    let data: Outer = serde_json::from_str(&json).expect("failed to deserialize");
+
+    assert_eq!(data, expected(true));
+}
+
+#[test]
+fn cbor() {
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&wire(), &mut cbor).expect("failed to serialize");
+    let data: Outer = ciborium::de::from_reader(cbor.as_slice()).expect("failed to deserialize");
+
+    assert_eq!(data, expected(false));
+}
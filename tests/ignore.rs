@@ -0,0 +1,65 @@
+mod common;
+
+use common::Capture;
+use serde::Deserialize;
+use serde_bufferless::{
+    capture::{ChainCapture, IgnoreCapture},
+    private::flatten::FlattenDeserializer,
+};
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+    integer: i32,
+    string: String,
+}
+
+/// Ignored keys are consumed without reaching the inner type, whatever their
+/// values are
+#[test]
+fn known_but_ignored() {
+    let mut capture = Capture::default();
+
+    let inner = Strict::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{
+                "deprecated": {"nested": [1, 2, 3]},
+                "integer": 10,
+                "before": 1.5,
+                "legacy": null,
+                "string": "hello",
+                "after": true
+            }"#,
+        ),
+        ChainCapture::new(&mut capture, IgnoreCapture::new(&["deprecated", "legacy"]))
+            .with_fields(&["before", "after", "deprecated", "legacy"]),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        inner,
+        Strict {
+            integer: 10,
+            string: "hello".to_owned(),
+        }
+    );
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+}
+
+/// Keys that aren't ignored still reach the inner type
+#[test]
+fn other_keys_forwarded() {
+    let err = Strict::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "string": "hello", "unknown": 1}"#,
+        ),
+        IgnoreCapture::new(&["deprecated", "legacy"]),
+    ))
+    .expect_err("accepted an unknown field");
+
+    assert!(
+        err.to_string().starts_with("unknown field `unknown`"),
+        "{err}"
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::Capture;
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{deserialize_into_map, KeyCapture};
+use serde_json::{json, Value};
+
+#[derive(Debug, PartialEq)]
+struct Extra {
+    id: u64,
+    extra: HashMap<String, Value>,
+}
+
+impl<'de> Deserialize<'de> for Extra {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Capture {
+            id: Option<u64>,
+        }
+
+        impl<'de> KeyCapture<'de> for &mut Capture {
+            type Token = ();
+
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                (key == b"id").then_some(())
+            }
+
+            fn send_value<D>(&mut self, _token: (), value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                self.id = Some(u64::deserialize(value)?);
+                Ok(())
+            }
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "struct Extra")
+            }
+        }
+
+        let mut capture = Capture { id: None };
+        let mut extra = HashMap::new();
+        deserialize_into_map(deserializer, &mut capture, &mut extra)?;
+
+        Ok(Extra {
+            id: capture.id.ok_or_else(|| de::Error::missing_field("id"))?,
+            extra,
+        })
+    }
+}
+
+#[test]
+fn only_flatten_field() {
+    let data: Extra = serde_json::from_str(r#"{"a": 1, "id": 7, "b": [true], "c": {"d": null}}"#)
+        .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Extra {
+            id: 7,
+            extra: HashMap::from([
+                ("a".to_owned(), json!(1)),
+                ("b".to_owned(), json!([true])),
+                ("c".to_owned(), json!({"d": null})),
+            ]),
+        }
+    );
+}
+
+/// Entries are added in input order, with captured siblings interleaved
+#[test]
+fn insertion_order() {
+    let mut capture = Capture::default();
+    let mut extra: Vec<(String, Value)> = Vec::new();
+
+    deserialize_into_map(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"z": 1, "before": 1.5, "y": "two", "after": false, "x": null}"#,
+        ),
+        &mut capture,
+        &mut extra,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(false));
+    assert_eq!(
+        extra,
+        [
+            ("z".to_owned(), json!(1)),
+            ("y".to_owned(), json!("two")),
+            ("x".to_owned(), Value::Null),
+        ]
+    );
+}
+
+/// Entries are added to whatever the map already contains
+#[test]
+fn existing_entries() {
+    let mut capture = Capture::default();
+    let mut extra = HashMap::from([("kept".to_owned(), 0), ("replaced".to_owned(), 0)]);
+
+    deserialize_into_map(
+        &mut serde_json::Deserializer::from_str(r#"{"replaced": 2, "after": true, "new": 3}"#),
+        &mut capture,
+        &mut extra,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        extra,
+        HashMap::from([
+            ("kept".to_owned(), 0),
+            ("replaced".to_owned(), 2),
+            ("new".to_owned(), 3),
+        ])
+    );
+}
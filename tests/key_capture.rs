@@ -0,0 +1,174 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{
+    key_capture,
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+use serde_test::{assert_de_tokens, Token};
+
+key_capture! {
+    #[derive(Debug)]
+    pub Capture {
+        before: Option<f32>,
+        after: Option<bool>,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    before: f32,
+    after: bool,
+
+    // #[serde(flatten)]
+    inner: Inner,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Outer {
+            before: capture
+                .before
+                .ok_or_else(|| serde::de::Error::missing_field("before"))?,
+            after: capture.after.unwrap_or_default(),
+            inner,
+        })
+    }
+}
+
+#[test]
+fn generated_capture() {
+    let data: Outer =
+        serde_json::from_str(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(
+        data,
+        Outer {
+            before: 1.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        }
+    );
+
+    let err = serde_json::from_str::<Outer>(r#"{"integer": 10, "string": "hello"}"#)
+        .expect_err("`before` is required");
+    assert!(
+        err.to_string().starts_with("missing field `before`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn field_indices() {
+    assert_de_tokens(
+        &Outer {
+            before: 2.5,
+            after: true,
+            inner: Inner {
+                integer: 10,
+                string: "hello".to_owned(),
+            },
+        },
+        &[
+            Token::Map { len: Some(4) },
+            Token::U64(1),
+            Token::Bool(true),
+            Token::Str("integer"),
+            Token::I32(10),
+            Token::U64(0),
+            Token::F32(2.5),
+            Token::Str("string"),
+            Token::Str("hello"),
+            Token::MapEnd,
+        ],
+    );
+}
+
+/// Captures can be declared inside a function, as the generated code does
+#[test]
+fn local_capture() {
+    key_capture! {
+        Local {
+            after: Option<bool>,
+        }
+    }
+
+    let mut capture = Local::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "after": false, "string": "hello"}"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.after, Some(false));
+    assert_eq!(inner.integer, 10);
+}
+
+/// A raw field matches its name without the `r#`, as with serde's derive
+#[test]
+fn raw_field() {
+    key_capture! {
+        Raw {
+            r#type: Option<bool>,
+        }
+    }
+
+    let mut capture = Raw::default();
+    let raw = &mut capture;
+    assert_eq!(raw.fields(), ["type"]);
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "type": true, "r#type": 1, "string": "hello"}"#,
+        ),
+        raw,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.r#type, Some(true));
+    assert_eq!(inner.integer, 10);
+}
+
+/// Errors describe the struct named with `for`, rather than the capture
+#[test]
+fn expecting() {
+    key_capture! {
+        Local for Outer {
+            after: Option<bool>,
+        }
+    }
+
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str("5"),
+        &mut Local::default(),
+    ))
+    .expect_err("an integer isn't a map");
+    assert!(
+        err.to_string()
+            .starts_with("invalid type: integer `5`, expected struct Outer"),
+        "{}",
+        err
+    );
+
+    let err = serde_json::from_str::<Outer>("5").expect_err("an integer isn't a map");
+    assert!(
+        err.to_string()
+            .starts_with("invalid type: integer `5`, expected struct Capture"),
+        "{}",
+        err
+    );
+}
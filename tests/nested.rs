@@ -0,0 +1,159 @@
+//! Three levels of flattening: `Top` flattens `Middle`, which flattens
+//! `Inner`. Each level's `Deserialize` impl wraps the deserializer it's given
+//! in a `FlattenDeserializer`, so the middle level's capture sees only the
+//! keys the top level's capture rejected, and `Inner` sees only the keys
+//! neither capture wanted.
+
+mod common;
+
+use common::Inner;
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+/// A capture for a single `u64` field with the given name
+struct One {
+    name: &'static str,
+    value: Option<u64>,
+}
+
+impl One {
+    fn new(name: &'static str) -> Self {
+        Self { name, value: None }
+    }
+
+    fn get<E: de::Error>(self) -> Result<u64, E> {
+        self.value.ok_or_else(|| E::missing_field(self.name))
+    }
+}
+
+impl<'de> KeyCapture<'de> for &mut One {
+    type Token = ();
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        (key == self.name.as_bytes()).then_some(())
+    }
+
+    fn send_value<D>(&mut self, _token: (), value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.value = Some(u64::deserialize(value)?);
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(formatter, "a struct with `{}`", self.name)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Middle {
+    middle: u64,
+    inner: Inner,
+}
+
+impl<'de> Deserialize<'de> for Middle {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = One::new("middle");
+        let inner = Inner::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Middle {
+            middle: capture.get()?,
+            inner,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Top {
+    top: u64,
+    middle: Middle,
+}
+
+impl<'de> Deserialize<'de> for Top {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = One::new("top");
+        let middle = Middle::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Top {
+            top: capture.get()?,
+            middle,
+        })
+    }
+}
+
+fn expected() -> Top {
+    Top {
+        top: 1,
+        middle: Middle {
+            middle: 2,
+            inner: Inner {
+                integer: 3,
+                string: "s".to_owned(),
+            },
+        },
+    }
+}
+
+#[test]
+fn nested() {
+    let inputs = [
+        r#"{"top": 1, "middle": 2, "integer": 3, "string": "s"}"#,
+        r#"{"string": "s", "integer": 3, "middle": 2, "top": 1}"#,
+        r#"{"integer": 3, "top": 1, "string": "s", "middle": 2}"#,
+        r#"{"middle": 2, "string": "s", "extra": [1], "top": 1, "integer": 3}"#,
+    ];
+
+    for input in inputs {
+        let data: Top = serde_json::from_str(input).expect("failed to deserialize");
+        assert_eq!(data, expected(), "{}", input);
+    }
+}
+
+/// Keys are only ever seen by the outermost capture that wants them
+#[test]
+fn duplicate_names() {
+    #[derive(Debug, PartialEq)]
+    struct Shadow {
+        top: u64,
+        middle: u64,
+    }
+
+    impl<'de> Deserialize<'de> for Shadow {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: de::Deserializer<'de>,
+        {
+            let mut outer = One::new("top");
+            let mut inner = One::new("top");
+            let middle = {
+                #[derive(Deserialize)]
+                struct Rest {
+                    middle: u64,
+                }
+
+                Rest::deserialize(FlattenDeserializer::new(
+                    FlattenDeserializer::new(deserializer, &mut outer),
+                    &mut inner,
+                ))?
+                .middle
+            };
+
+            assert_eq!(inner.value, None);
+
+            Ok(Shadow {
+                top: outer.get()?,
+                middle,
+            })
+        }
+    }
+
+    let data: Shadow = serde_json::from_str(r#"{"top": 1, "middle": 2}"#).unwrap();
+    assert_eq!(data, Shadow { top: 1, middle: 2 });
+}
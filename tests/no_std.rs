@@ -0,0 +1,87 @@
+//! The crate must build without `std`, with or without `alloc`. Integration
+//! tests always link `std` themselves, so this runs a nested `cargo check`
+//! (with its own target directory, to avoid contending with this build).
+
+use std::{fs, path::Path, process::Command};
+
+fn check(features: &str) {
+    let status = Command::new(env!("CARGO"))
+        .args([
+            "check",
+            "--quiet",
+            "--lib",
+            "--package",
+            env!("CARGO_PKG_NAME"),
+            "--no-default-features",
+            "--features",
+            features,
+        ])
+        .env(
+            "CARGO_TARGET_DIR",
+            concat!(env!("CARGO_TARGET_TMPDIR"), "/no_std"),
+        )
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .expect("failed to run cargo");
+
+    assert!(
+        status.success(),
+        "failed to build with features {features:?}"
+    );
+}
+
+#[test]
+fn core_only() {
+    check("");
+}
+
+#[test]
+fn alloc() {
+    check("alloc");
+}
+
+/// A `#![no_std]` crate, `no_std/smoke.rs`, can use `FlattenDeserializer`
+/// and `KeyCapture` without `alloc`
+#[test]
+fn consumer() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let crate_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std_smoke");
+    fs::create_dir_all(&crate_dir).expect("failed to create the smoke crate");
+
+    let manifest = format!(
+        r#"[package]
+name = "no-std-smoke"
+version = "0.0.0"
+edition = "2021"
+
+[lib]
+path = {lib:?}
+
+[dependencies]
+serde = {{ version = "1.0.136", default-features = false, features = ["derive"] }}
+serde-bufferless = {{ path = {path:?}, default-features = false }}
+
+[workspace]
+"#,
+        lib = manifest_dir.join("tests/no_std/smoke.rs"),
+        path = manifest_dir,
+    );
+    fs::write(crate_dir.join("Cargo.toml"), manifest).expect("failed to write the manifest");
+
+    // Reuse this crate's lockfile, so the nested build needn't resolve
+    // anything new
+    fs::copy(
+        manifest_dir.join("Cargo.lock"),
+        crate_dir.join("Cargo.lock"),
+    )
+    .expect("failed to copy the lockfile");
+
+    let status = Command::new(env!("CARGO"))
+        .args(["check", "--quiet", "--offline"])
+        .env("CARGO_TARGET_DIR", crate_dir.join("target"))
+        .current_dir(&crate_dir)
+        .status()
+        .expect("failed to run cargo");
+
+    assert!(status.success(), "failed to build the no_std smoke crate");
+}
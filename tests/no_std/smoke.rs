@@ -0,0 +1,76 @@
+//! A `#![no_std]` crate using the flatten machinery, checked by
+//! `tests/no_std.rs`. It has no `alloc`, so its keys are borrowed and its
+//! errors are serde's own allocation-free `value::Error`.
+
+#![no_std]
+
+use core::fmt;
+
+use serde::{
+    de::{
+        self,
+        value::{Error, MapDeserializer},
+    },
+    Deserialize,
+};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+#[derive(Deserialize)]
+pub struct Inner {
+    pub integer: u32,
+}
+
+pub struct Outer {
+    pub before: u32,
+    pub inner: Inner,
+}
+
+enum Field {
+    Before,
+}
+
+#[derive(Default)]
+struct Capture {
+    before: Option<u32>,
+}
+
+impl<'de> KeyCapture<'de> for &mut Capture {
+    type Token = Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"before" => Some(Field::Before),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            Field::Before => self.before = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Outer")
+    }
+}
+
+pub fn deserialize(entries: &[(&str, u32)]) -> Result<Outer, Error> {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        MapDeserializer::new(entries.iter().copied()),
+        &mut capture,
+    ))?;
+
+    Ok(Outer {
+        before: capture
+            .before
+            .ok_or_else(|| de::Error::missing_field("before"))?,
+        inner,
+    })
+}
@@ -0,0 +1,19 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::NullCapture, private::flatten::FlattenDeserializer};
+
+#[test]
+fn pass_through() {
+    let input = r#"{"integer": 10, "before": 1.5, "string": "hello"}"#;
+
+    let direct: Inner = serde_json::from_str(input).expect("failed to deserialize");
+    let flattened = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        NullCapture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(flattened, direct);
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::OrderedCapture, private::flatten::FlattenDeserializer};
+use serde_value::Value;
+
+const FIELDS: &[&str] = &["name", "version", "edition"];
+
+#[test]
+fn input_order() {
+    let mut capture = OrderedCapture::new(FIELDS);
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{
+                "edition": "2021",
+                "integer": 10,
+                "name": "demo",
+                "string": "hello",
+                "version": 3
+            }"#,
+        ),
+        &mut capture,
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+
+    assert_eq!(capture.order().collect::<Vec<_>>(), [2, 0, 1]);
+    assert_eq!(
+        capture.take_entries(),
+        [
+            (2, Value::String("2021".to_owned())),
+            (0, Value::String("demo".to_owned())),
+            (1, Value::U64(3)),
+        ]
+    );
+    assert!(capture.entries().is_empty());
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{
+    capture::{PatchCapture, PatchSetter},
+    private::flatten::FlattenDeserializer,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    host: String,
+    port: u16,
+    nickname: Option<String>,
+}
+
+const SETTERS: &[(&str, PatchSetter<Config>)] = &[
+    ("host", |config, value| {
+        config.host = erased_serde::deserialize(value)?;
+        Ok(())
+    }),
+    ("port", |config, value| {
+        config.port = erased_serde::deserialize(value)?;
+        Ok(())
+    }),
+    ("nickname", |config, value| {
+        config.nickname = erased_serde::deserialize(value)?;
+        Ok(())
+    }),
+];
+
+fn existing() -> Config {
+    Config {
+        host: "localhost".to_owned(),
+        port: 80,
+        nickname: Some("dev".to_owned()),
+    }
+}
+
+/// Only the fields present in the input are changed, and a `null` clears an
+/// optional field
+#[test]
+fn partial_update() {
+    let mut config = existing();
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "port": 8080, "string": "hello", "nickname": null}"#,
+        ),
+        PatchCapture::new(&mut config, SETTERS),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+    assert_eq!(
+        config,
+        Config {
+            host: "localhost".to_owned(),
+            port: 8080,
+            nickname: None,
+        }
+    );
+}
+
+/// Errors name the field, and the fields patched before the error keep their
+/// new values
+#[test]
+fn invalid_value() {
+    let mut config = existing();
+
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "host": "example.com", "port": "http", "string": "hello"}"#,
+        ),
+        PatchCapture::new(&mut config, SETTERS),
+    ))
+    .expect_err("deserialized an invalid port");
+
+    assert!(
+        err.to_string()
+            .starts_with("error deserializing field `port`: invalid type: string \"http\""),
+        "{err}"
+    );
+    assert_eq!(config.host, "example.com");
+    assert_eq!(config.port, 80);
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use std::collections::HashMap;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::PrefixMapCapture, private::flatten::FlattenDeserializer};
+
+#[test]
+fn namespaced_keys() {
+    let mut meta: HashMap<String, String> = HashMap::new();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{
+                "meta_author": "someone",
+                "integer": 10,
+                "meta_created": "2024-01-01",
+                "string": "hello"
+            }"#,
+        ),
+        PrefixMapCapture::new("meta_", &mut meta),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+    assert_eq!(
+        meta,
+        HashMap::from([
+            ("author".to_owned(), "someone".to_owned()),
+            ("created".to_owned(), "2024-01-01".to_owned()),
+        ])
+    );
+}
+
+#[test]
+fn unprefixed_keys_forwarded() {
+    let mut meta: HashMap<String, i32> = HashMap::new();
+    let rest: HashMap<String, i32> = HashMap::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"meta_a": 1, "meta": 2, "a": 3, "meta_": 4, "metadata": 5}"#,
+        ),
+        PrefixMapCapture::new("meta_", &mut meta),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        meta,
+        HashMap::from([("a".to_owned(), 1), ("".to_owned(), 4)])
+    );
+    assert_eq!(
+        rest,
+        HashMap::from([
+            ("meta".to_owned(), 2),
+            ("a".to_owned(), 3),
+            ("metadata".to_owned(), 5),
+        ])
+    );
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Capture, Inner};
+use serde::Deserialize;
+use serde_bufferless::{
+    capture::PrefixStrip,
+    private::flatten::{FlattenDeserializer, UnknownPolicy},
+};
+
+#[test]
+fn prefixed_keys() {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "meta_before": 1.5, "string": "hello", "meta_after": true}"#,
+        ),
+        PrefixStrip::new("meta_", &mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// Keys without the prefix, and prefixed keys the capture rejects, reach the
+/// inner type unchanged
+#[test]
+fn forwarded_keys_unchanged() {
+    let mut capture = Capture::default();
+    let rest: BTreeMap<String, i32> = BTreeMap::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"before": 1, "meta_before": 2.5, "meta_other": 2, "meta_": 3, "meta": 4}"#,
+        ),
+        PrefixStrip::new("meta_", &mut capture),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(2.5));
+    assert_eq!(
+        rest,
+        BTreeMap::from([
+            ("before".to_owned(), 1),
+            ("meta_other".to_owned(), 2),
+            ("meta_".to_owned(), 3),
+            ("meta".to_owned(), 4),
+        ])
+    );
+}
+
+/// Unknown-field errors list the keys as they appear in the input: the
+/// prefixed names given to `with_fields`, and nothing of the wrapped
+/// capture's unprefixed names
+#[test]
+fn reported_fields() {
+    let deserialize = |capture: PrefixStrip<'_, &mut Capture>| {
+        FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "meta_before": 1.5, "meta_befor": 2.5, "string": "hello"}"#,
+        ))
+        .capture(capture)
+        .unknown_policy(UnknownPolicy::Deny)
+        .deserialize::<Inner>()
+        .expect_err("misspelled prefixed field should be rejected")
+        .to_string()
+    };
+
+    let mut capture = Capture::default();
+    let err = deserialize(
+        PrefixStrip::new("meta_", &mut capture).with_fields(&["meta_before", "meta_after"]),
+    );
+    assert!(
+        err.starts_with(
+            "unknown field `meta_befor`, expected one of `meta_before`, `meta_after`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+    assert_eq!(capture.before, Some(1.5));
+
+    let err = deserialize(PrefixStrip::new("meta_", &mut Capture::default()));
+    assert!(
+        err.starts_with("unknown field `meta_befor`, expected `integer` or `string`"),
+        "{}",
+        err
+    );
+}
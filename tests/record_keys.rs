@@ -0,0 +1,76 @@
+mod common;
+
+use common::{Capture, Field, Inner};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::RecordKeys,
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+
+/// Matches keys case-insensitively, losing the original spelling
+struct CaseFold<C>(C);
+
+impl<'de, C: KeyCapture<'de>> KeyCapture<'de> for CaseFold<C> {
+    type Token = C::Token;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        self.0.try_send_key(&key.to_ascii_lowercase())
+    }
+
+    fn send_value<D>(&mut self, token: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.0.send_value(token, value)
+    }
+
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+        self.0.expecting(formatter)
+    }
+}
+
+#[test]
+fn original_spelling() {
+    let mut capture = Capture::default();
+    let mut keys = Vec::new();
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "BeFore": 1.5, "string": "hello", "AFTER": true}"#,
+        ),
+        RecordKeys::new(CaseFold(&mut capture), |key: &[u8], field: &Field| {
+            keys.push((String::from_utf8(key.to_vec()).unwrap(), *field))
+        }),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(
+        keys,
+        [
+            ("BeFore".to_owned(), Field::before),
+            ("AFTER".to_owned(), Field::after)
+        ]
+    );
+}
+
+/// Rejected keys aren't recorded
+#[test]
+fn only_accepted() {
+    let mut capture = Capture::default();
+    let mut keys = Vec::new();
+
+    Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "string": "hello", "after": false}"#,
+        ),
+        RecordKeys::new(&mut capture, |key: &[u8], _: &Field| {
+            keys.push(key.to_vec())
+        }),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(keys, [b"after".to_vec()]);
+}
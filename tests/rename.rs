@@ -0,0 +1,94 @@
+use std::borrow::Cow;
+
+use serde::Deserialize;
+use serde_bufferless::rename::{
+    rename_camel_case, rename_kebab_case, rename_pascal_case, rename_screaming_kebab_case,
+    rename_screaming_snake_case, rename_snake_case, rename_uppercase, RenameRule,
+};
+
+#[test]
+fn camel_case() {
+    assert_eq!(rename_camel_case("created_at"), "createdAt");
+    assert_eq!(rename_camel_case("a_b_c"), "aBC");
+    assert!(matches!(rename_camel_case("name"), Cow::Borrowed("name")));
+}
+
+#[test]
+fn pascal_case() {
+    assert_eq!(rename_pascal_case("created_at"), "CreatedAt");
+    assert_eq!(rename_pascal_case("name"), "Name");
+}
+
+#[test]
+fn snake_case() {
+    assert!(matches!(
+        rename_snake_case("created_at"),
+        Cow::Borrowed("created_at")
+    ));
+}
+
+#[test]
+fn kebab_case() {
+    assert_eq!(rename_kebab_case("created_at"), "created-at");
+    assert!(matches!(rename_kebab_case("name"), Cow::Borrowed("name")));
+}
+
+#[test]
+fn screaming_snake_case() {
+    assert_eq!(rename_screaming_snake_case("created_at"), "CREATED_AT");
+    assert_eq!(rename_uppercase("name"), "NAME");
+}
+
+#[test]
+fn screaming_kebab_case() {
+    assert_eq!(rename_screaming_kebab_case("created_at"), "CREATED-AT");
+}
+
+#[test]
+fn rule_names() {
+    for name in [
+        "lowercase",
+        "UPPERCASE",
+        "PascalCase",
+        "camelCase",
+        "snake_case",
+        "SCREAMING_SNAKE_CASE",
+        "kebab-case",
+        "SCREAMING-KEBAB-CASE",
+    ] {
+        let rule = RenameRule::from_name(name).expect("unknown rule");
+        assert_eq!(rule.name(), name);
+    }
+
+    assert_eq!(RenameRule::from_name("Title Case"), None);
+}
+
+/// Every rule agrees with serde's derive
+#[test]
+fn matches_serde() {
+    macro_rules! check {
+        ($($rule:literal),*) => {$({
+            #[derive(Deserialize)]
+            #[serde(rename_all = $rule, deny_unknown_fields)]
+            #[allow(dead_code)]
+            struct Renamed {
+                created_at: u8,
+            }
+
+            let key = RenameRule::from_name($rule).unwrap().apply("created_at");
+            serde_json::from_str::<Renamed>(&format!(r#"{{"{key}": 1}}"#))
+                .unwrap_or_else(|err| panic!("{}: {}", $rule, err));
+        })*};
+    }
+
+    check!(
+        "lowercase",
+        "UPPERCASE",
+        "PascalCase",
+        "camelCase",
+        "snake_case",
+        "SCREAMING_SNAKE_CASE",
+        "kebab-case",
+        "SCREAMING-KEBAB-CASE"
+    );
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use std::fmt;
+
+use common::{Capture, Field, Inner, Outer};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::RequiredSet,
+    private::flatten::{FlattenDeserializer, KeyCapture},
+};
+
+fn deserialize(input: &str) -> Result<Outer, serde_json::Error> {
+    let mut capture = Capture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        &mut capture,
+    ))?;
+
+    RequiredSet::new(&[
+        ("before", capture.before.is_some()),
+        ("after", capture.after.is_some()),
+    ])
+    .check::<serde_json::Error>()?;
+
+    capture.assemble(inner)
+}
+
+#[test]
+fn all_present() {
+    let data = deserialize(r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#)
+        .expect("failed to deserialize");
+
+    assert_eq!(data.before, 1.5);
+}
+
+#[test]
+fn one_missing() {
+    let err = deserialize(r#"{"integer": 10, "string": "hello", "after": true}"#)
+        .expect_err("`before` is missing");
+
+    assert_eq!(err.to_string(), "missing field `before`");
+}
+
+#[test]
+fn all_missing() {
+    let err =
+        deserialize(r#"{"integer": 10, "string": "hello"}"#).expect_err("both fields are missing");
+
+    assert_eq!(err.to_string(), "missing fields `before`, `after`");
+}
+
+/// A capture that checks its own required fields in `finish`
+struct Finishing<'a> {
+    capture: &'a mut Capture,
+    finished: bool,
+}
+
+impl<'de> KeyCapture<'de> for &mut Finishing<'_> {
+    type Token = Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Field> {
+        self.capture.try_send_key(key)
+    }
+
+    fn send_value<D>(&mut self, token: Field, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        self.capture.send_value(token, value)
+    }
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.capture.expecting(formatter)
+    }
+
+    fn finish<E>(&mut self) -> Result<(), E>
+    where
+        E: de::Error,
+    {
+        self.finished = true;
+
+        RequiredSet::new(&[
+            ("before", self.capture.before.is_some()),
+            ("after", self.capture.after.is_some()),
+        ])
+        .check()
+    }
+}
+
+fn deserialize_finishing(input: &str) -> (Result<Inner, serde_json::Error>, bool) {
+    let mut capture = Capture::default();
+    let mut finishing = Finishing {
+        capture: &mut capture,
+        finished: false,
+    };
+
+    let result = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        &mut finishing,
+    ));
+
+    (result, finishing.finished)
+}
+
+#[test]
+fn finish() {
+    let (result, finished) = deserialize_finishing(
+        r#"{"integer": 10, "string": "hello", "after": true, "before": 1.5}"#,
+    );
+    assert_eq!(result.expect("failed to deserialize").integer, 10);
+    assert!(finished);
+
+    // `finish` runs inside the visitor, so the error has a position
+    let (result, finished) = deserialize_finishing(r#"{"integer": 10, "string": "hello"}"#);
+    let err = result.expect_err("both fields are missing");
+    assert!(finished);
+    assert_eq!(
+        err.to_string(),
+        "missing fields `before`, `after` at line 1 column 34"
+    );
+}
+
+#[test]
+fn finish_skipped_on_error() {
+    let (result, finished) =
+        deserialize_finishing(r#"{"integer": "ten", "string": "hello", "after": true}"#);
+
+    result.expect_err("`integer` has the wrong type");
+    assert!(!finished);
+}
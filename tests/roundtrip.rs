@@ -0,0 +1,134 @@
+//! Extract, modify and re-emit: a document is deserialized into typed fields
+//! plus a catch-all map, then serialized again.
+//!
+//! Byte-for-byte stable output depends only on the catch-all's map type. A
+//! `BTreeMap` always serializes its entries in sorted order, so a document
+//! whose catch-all keys are already sorted (and that places the typed fields
+//! first, in declaration order) round trips exactly. A `HashMap` only round
+//! trips semantically, since its iteration order is unspecified. The typed
+//! fields themselves are always serialized in declaration order, regardless
+//! of where they appeared in the input.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{de, Deserialize, Serialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+use serde_json::Value;
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Document<M> {
+    id: u64,
+    version: u32,
+
+    #[serde(flatten)]
+    extra: M,
+}
+
+impl<'de, M: Deserialize<'de>> Deserialize<'de> for Document<M> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        enum Field {
+            Id,
+            Version,
+        }
+
+        #[derive(Default)]
+        struct Capture {
+            id: Option<u64>,
+            version: Option<u32>,
+        }
+
+        impl<'de> KeyCapture<'de> for &mut Capture {
+            type Token = Field;
+
+            fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+                match key {
+                    b"id" => Some(Field::Id),
+                    b"version" => Some(Field::Version),
+                    _ => None,
+                }
+            }
+
+            fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                match field {
+                    Field::Id => self.id = Some(Deserialize::deserialize(value)?),
+                    Field::Version => self.version = Some(Deserialize::deserialize(value)?),
+                }
+
+                Ok(())
+            }
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "struct Document")
+            }
+        }
+
+        let mut capture = Capture::default();
+        let extra = M::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Document {
+            id: capture.id.ok_or_else(|| de::Error::missing_field("id"))?,
+            version: capture
+                .version
+                .ok_or_else(|| de::Error::missing_field("version"))?,
+            extra,
+        })
+    }
+}
+
+const CANONICAL: &str =
+    r#"{"id":7,"version":1,"alpha":[1,2,3],"beta":{"nested":true},"gamma":"text"}"#;
+
+#[test]
+fn byte_stable() {
+    let doc: Document<BTreeMap<String, Value>> =
+        serde_json::from_str(CANONICAL).expect("failed to deserialize");
+
+    assert_eq!(doc.extra.len(), 3);
+    assert_eq!(serde_json::to_string(&doc).unwrap(), CANONICAL);
+}
+
+/// Typed fields can appear anywhere in the input; the output is canonical
+#[test]
+fn reordered_input() {
+    let doc: Document<BTreeMap<String, Value>> = serde_json::from_str(
+        r#"{"gamma":"text","version":1,"alpha":[1,2,3],"id":7,"beta":{"nested":true}}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(serde_json::to_string(&doc).unwrap(), CANONICAL);
+}
+
+#[test]
+fn modified_field() {
+    let mut doc: Document<BTreeMap<String, Value>> =
+        serde_json::from_str(CANONICAL).expect("failed to deserialize");
+    doc.version += 1;
+
+    assert_eq!(
+        serde_json::to_string(&doc).unwrap(),
+        r#"{"id":7,"version":2,"alpha":[1,2,3],"beta":{"nested":true},"gamma":"text"}"#
+    );
+}
+
+#[test]
+fn semantically_stable() {
+    let mut doc: Document<HashMap<String, Value>> =
+        serde_json::from_str(CANONICAL).expect("failed to deserialize");
+    doc.id = 8;
+
+    let output: Value = serde_json::to_value(&doc).unwrap();
+    let mut expected: Value = serde_json::from_str(CANONICAL).unwrap();
+    expected["id"] = 8.into();
+
+    assert_eq!(output, expected);
+
+    let again: Document<HashMap<String, Value>> =
+        serde_json::from_value(output).expect("failed to deserialize");
+    assert_eq!(again, doc);
+}
@@ -0,0 +1,122 @@
+//! A capture whose `distance` field is parsed according to its `unit` field,
+//! with a `DeserializeSeed` built from the captured unit
+
+mod common;
+
+use std::fmt;
+
+use common::Inner;
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{FlattenDeserializer, KeyCapture};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Meters,
+    Feet,
+}
+
+/// Deserializes a distance in `unit`, converting it to meters
+struct Meters(Unit);
+
+impl<'de> de::DeserializeSeed<'de> for Meters {
+    type Value = f64;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<f64, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let distance = f64::deserialize(deserializer)?;
+
+        Ok(match self.0 {
+            Unit::Meters => distance,
+            Unit::Feet => distance * 0.3048,
+        })
+    }
+}
+
+#[derive(Default)]
+struct SeedCapture {
+    unit: Option<Unit>,
+    meters: Option<f64>,
+}
+
+enum Field {
+    Unit,
+    Distance,
+}
+
+impl<'de> KeyCapture<'de> for &mut SeedCapture {
+    type Token = Field;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"unit" => Some(Field::Unit),
+            b"distance" => Some(Field::Distance),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            Field::Unit => self.unit = Some(Unit::deserialize(value)?),
+            Field::Distance => {
+                let unit = self
+                    .unit
+                    .ok_or_else(|| de::Error::custom("`unit` must come before `distance`"))?;
+                self.meters = Some(de::DeserializeSeed::deserialize(Meters(unit), value)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("struct Measurement")
+    }
+}
+
+fn deserialize(input: &str) -> Result<(SeedCapture, Inner), serde_json::Error> {
+    let mut capture = SeedCapture::default();
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(input),
+        &mut capture,
+    ))?;
+
+    Ok((capture, inner))
+}
+
+#[test]
+fn dependent_field() {
+    let (capture, inner) =
+        deserialize(r#"{"unit": "feet", "integer": 10, "distance": 100.0, "string": "hello"}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(capture.unit, Some(Unit::Feet));
+    assert_eq!(capture.meters, Some(30.48));
+    assert_eq!(inner.integer, 10);
+
+    let (capture, _) =
+        deserialize(r#"{"integer": 10, "unit": "meters", "string": "hello", "distance": 100.0}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(capture.meters, Some(100.0));
+}
+
+#[test]
+fn out_of_order() {
+    let err =
+        deserialize(r#"{"distance": 100.0, "unit": "feet", "integer": 10, "string": "hello"}"#)
+            .err()
+            .expect("`distance` came before `unit`");
+
+    assert!(
+        err.to_string()
+            .starts_with("`unit` must come before `distance`"),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,119 @@
+mod common;
+
+use std::{cell::RefCell, collections::HashMap};
+
+use common::Capture;
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::{ChainCapture, SeededMapCapture},
+    private::flatten::{deserialize_capture_only, FlattenDeserializer},
+};
+use serde_test::{assert_de_tokens, Token};
+
+/// Stores each distinct string once, identifying it by its index
+#[derive(Default)]
+struct Interner {
+    strings: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> usize {
+        if let Some(&index) = self.indices.get(string) {
+            return index;
+        }
+
+        let index = self.strings.len();
+        self.strings.push(string.to_owned());
+        self.indices.insert(string.to_owned(), index);
+        index
+    }
+}
+
+/// Deserializes a string into the shared interner
+struct Intern<'a>(&'a RefCell<Interner>);
+
+impl<'de> de::DeserializeSeed<'de> for Intern<'_> {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<usize, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let string = String::deserialize(deserializer)?;
+        Ok(self.0.borrow_mut().intern(&string))
+    }
+}
+
+#[test]
+fn interned_values() {
+    let interner = RefCell::new(Interner::default());
+    let mut capture = Capture::default();
+    let mut extra: Vec<(String, usize)> = Vec::new();
+
+    deserialize_capture_only(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"color": "red", "before": 1.5, "shade": "dark", "tint": "red", "after": true}"#,
+        ),
+        ChainCapture::new(
+            &mut capture,
+            SeededMapCapture::new(&mut extra, || Intern(&interner)),
+        ),
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(
+        extra,
+        [
+            ("color".to_owned(), 0),
+            ("shade".to_owned(), 1),
+            ("tint".to_owned(), 0),
+        ]
+    );
+    assert_eq!(interner.into_inner().strings, ["red", "dark"]);
+}
+
+/// The interned entries, along with the entries left for the inner type
+#[derive(Debug, PartialEq)]
+struct Split {
+    extra: Vec<(String, usize)>,
+    rest: HashMap<u32, String>,
+}
+
+impl<'de> Deserialize<'de> for Split {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let interner = RefCell::new(Interner::default());
+        let mut extra = Vec::new();
+
+        let rest = HashMap::deserialize(FlattenDeserializer::new(
+            deserializer,
+            SeededMapCapture::new(&mut extra, || Intern(&interner)),
+        ))?;
+
+        Ok(Split { extra, rest })
+    }
+}
+
+/// Keys that aren't strings are left for the inner type
+#[test]
+fn string_keys_only() {
+    assert_de_tokens(
+        &Split {
+            extra: vec![("name".to_owned(), 0)],
+            rest: HashMap::from([(1, "one".to_owned())]),
+        },
+        &[
+            Token::Map { len: Some(2) },
+            Token::U32(1),
+            Token::Str("one"),
+            Token::Str("name"),
+            Token::Str("red"),
+            Token::MapEnd,
+        ],
+    );
+}
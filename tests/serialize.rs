@@ -0,0 +1,96 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::{Inner, Outer};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use serde_bufferless::private::flatten_ser::FlattenSerializer;
+
+/// Serializes an `Outer` the way a derived impl would, with the flattened
+/// fields after the outer ones
+struct Flattened<'a, T>(&'a Outer, T);
+
+impl<T: Serialize> Serialize for Flattened<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("before", &self.0.before)?;
+        map.serialize_entry("after", &self.0.after)?;
+        self.1.serialize(FlattenSerializer::new(&mut map))?;
+        map.end()
+    }
+}
+
+fn outer() -> Outer {
+    Outer {
+        before: 1.5,
+        after: true,
+        inner: Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        },
+    }
+}
+
+#[test]
+fn declaration_order() {
+    let outer = outer();
+    let json = serde_json::to_string(&Flattened(&outer, &outer.inner)).unwrap();
+
+    assert_eq!(
+        json,
+        r#"{"before":1.5,"after":true,"integer":10,"string":"hello"}"#
+    );
+
+    let round_trip: Outer = serde_json::from_str(&json).unwrap();
+    assert_eq!(round_trip, outer);
+}
+
+#[test]
+fn map() {
+    let outer = outer();
+    let extra = BTreeMap::from([("x", 1), ("y", 2)]);
+    let json = serde_json::to_string(&Flattened(&outer, &extra)).unwrap();
+
+    assert_eq!(json, r#"{"before":1.5,"after":true,"x":1,"y":2}"#);
+}
+
+#[test]
+fn option() {
+    let outer = outer();
+
+    let json = serde_json::to_string(&Flattened(&outer, None::<&Inner>)).unwrap();
+    assert_eq!(json, r#"{"before":1.5,"after":true}"#);
+
+    let json = serde_json::to_string(&Flattened(&outer, Some(&outer.inner))).unwrap();
+    assert_eq!(
+        json,
+        r#"{"before":1.5,"after":true,"integer":10,"string":"hello"}"#
+    );
+}
+
+#[test]
+fn newtype_variant() {
+    #[derive(Serialize)]
+    enum Extra {
+        Count(u32),
+    }
+
+    let outer = outer();
+    let json = serde_json::to_string(&Flattened(&outer, Extra::Count(3))).unwrap();
+
+    assert_eq!(json, r#"{"before":1.5,"after":true,"Count":3}"#);
+}
+
+#[test]
+fn unsupported() {
+    let outer = outer();
+    let err = serde_json::to_string(&Flattened(&outer, [1, 2])).expect_err("can't flatten a tuple");
+
+    assert_eq!(
+        err.to_string(),
+        "can only flatten structs and maps (got a tuple)"
+    );
+}
@@ -0,0 +1,51 @@
+mod common;
+
+use common::Inner;
+use serde::Deserialize;
+use serde_bufferless::{capture::StructCapture, private::flatten::FlattenDeserializer};
+
+#[test]
+fn field_table() {
+    let mut before: Option<f32> = None;
+    let mut after: Option<bool> = None;
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#,
+        ),
+        StructCapture::new(&["before", "after"], |index, value| {
+            match index {
+                0 => before = Some(erased_serde::deserialize(value)?),
+                _ => after = Some(erased_serde::deserialize(value)?),
+            }
+            Ok(())
+        }),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(before, Some(1.5));
+    assert_eq!(after, Some(true));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// Errors from the closure are reported through the original deserializer,
+/// annotated with the field name from the table
+#[test]
+fn field_error() {
+    let mut before: Option<f32> = None;
+
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        serde_json::json!({"integer": 10, "before": "x", "string": "hello"}),
+        StructCapture::new(&["before"], |_, value| {
+            before = Some(erased_serde::deserialize(value)?);
+            Ok(())
+        }),
+    ))
+    .expect_err("`before` should be a float");
+
+    assert_eq!(
+        err.to_string(),
+        r#"error deserializing field `before`: invalid type: string "x", expected f32"#
+    );
+}
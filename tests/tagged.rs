@@ -0,0 +1,158 @@
+mod common;
+
+use common::Capture;
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::{deserialize_tagged, TaggedEnum};
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Circle {
+    radius: f64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Rect {
+    width: f64,
+    height: f64,
+}
+
+// #[serde(tag = "type")]
+#[derive(Debug, PartialEq)]
+enum Shape {
+    Circle(Circle),
+    Rect(Rect),
+}
+
+impl<'de> TaggedEnum<'de> for Shape {
+    const TAG: &'static str = "type";
+    const VARIANTS: &'static [&'static str] = &["Circle", "Rect"];
+
+    fn deserialize_variant<D>(variant: &'static str, deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match variant {
+            "Circle" => Circle::deserialize(deserializer).map(Shape::Circle),
+            "Rect" => Rect::deserialize(deserializer).map(Shape::Rect),
+            _ => unreachable!("variant is always one of VARIANTS"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    before: f32,
+    after: bool,
+
+    // #[serde(flatten)]
+    shape: Shape,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let shape = deserialize_tagged(deserializer, &mut capture)?;
+
+        Ok(Outer {
+            before: capture
+                .before
+                .ok_or_else(|| de::Error::missing_field("before"))?,
+            after: capture
+                .after
+                .ok_or_else(|| de::Error::missing_field("after"))?,
+            shape,
+        })
+    }
+}
+
+#[test]
+fn variants() {
+    let outer: Outer =
+        serde_json::from_str(r#"{"before": 1.5, "type": "Circle", "radius": 2.0, "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(
+        outer,
+        Outer {
+            before: 1.5,
+            after: true,
+            shape: Shape::Circle(Circle { radius: 2.0 }),
+        }
+    );
+
+    let outer: Outer = serde_json::from_str(
+        r#"{"type": "Rect", "width": 3.0, "after": false, "height": 4.0, "before": 0.5}"#,
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(
+        outer,
+        Outer {
+            before: 0.5,
+            after: false,
+            shape: Shape::Rect(Rect {
+                width: 3.0,
+                height: 4.0,
+            }),
+        }
+    );
+}
+
+#[test]
+fn unknown_variant() {
+    let err = serde_json::from_str::<Outer>(
+        r#"{"before": 1.5, "after": true, "type": "Triangle", "sides": 3}"#,
+    )
+    .expect_err("Triangle isn't a variant");
+
+    assert!(
+        err.to_string()
+            .starts_with("unknown variant `Triangle`, expected `Circle` or `Rect`"),
+        "{}",
+        err
+    );
+}
+
+/// A tag that isn't a string is reported against the variant names too
+#[test]
+fn tag_not_a_string() {
+    let err =
+        serde_json::from_str::<Outer>(r#"{"before": 1.5, "after": true, "type": 3, "sides": 3}"#)
+            .expect_err("the tag isn't a string");
+
+    assert!(
+        err.to_string()
+            .starts_with("invalid type: integer `3`, expected `Circle` or `Rect`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn missing_tag() {
+    let err = serde_json::from_str::<Outer>(r#"{"before": 1.5, "after": true}"#)
+        .expect_err("there's no tag");
+
+    assert!(
+        err.to_string().starts_with("missing field `type`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn tag_after_fields() {
+    let err = serde_json::from_str::<Outer>(
+        r#"{"before": 1.5, "radius": 2.0, "type": "Circle", "after": true}"#,
+    )
+    .expect_err("the tag must come first");
+
+    assert!(
+        err.to_string()
+            .starts_with("the `type` tag must appear before the variant's fields"),
+        "{}",
+        err
+    );
+}
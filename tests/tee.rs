@@ -0,0 +1,156 @@
+//! A `"kind"` key needed both by the outer struct and by a flattened struct
+//! that's captured alongside it.
+
+mod common;
+
+use common::{Capture, Inner};
+use serde::{de, Deserialize};
+use serde_bufferless::{
+    capture::{IgnoreCapture, TeeCapture},
+    private::flatten::{FlattenDeserializer, KeyCapture, UnknownPolicy},
+};
+
+#[derive(Default)]
+struct OuterCapture {
+    kind: Option<String>,
+    id: Option<u64>,
+}
+
+impl<'de> KeyCapture<'de> for &mut OuterCapture {
+    type Token = &'static str;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"kind" => Some("kind"),
+            b"id" => Some("id"),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            "kind" => self.kind = Some(Deserialize::deserialize(value)?),
+            _ => self.id = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct Outer")
+    }
+}
+
+#[derive(Default)]
+struct MetaCapture {
+    kind: Option<String>,
+    version: Option<u32>,
+}
+
+impl<'de> KeyCapture<'de> for &mut MetaCapture {
+    type Token = &'static str;
+
+    fn try_send_key(&mut self, key: &[u8]) -> Option<Self::Token> {
+        match key {
+            b"kind" => Some("kind"),
+            b"version" => Some("version"),
+            _ => None,
+        }
+    }
+
+    fn send_value<D>(&mut self, field: Self::Token, value: D) -> Result<(), D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match field {
+            "kind" => self.kind = Some(Deserialize::deserialize(value)?),
+            _ => self.version = Some(Deserialize::deserialize(value)?),
+        }
+
+        Ok(())
+    }
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("struct Meta")
+    }
+}
+
+#[test]
+fn shared_key() {
+    let mut outer = OuterCapture::default();
+    let mut meta = MetaCapture::default();
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "kind": "widget", "id": 4, "version": 2, "string": "hello"}"#,
+        ),
+        TeeCapture::new(&mut outer, &mut meta),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(outer.kind.as_deref(), Some("widget"));
+    assert_eq!(meta.kind.as_deref(), Some("widget"));
+    assert_eq!(outer.id, Some(4));
+    assert_eq!(meta.version, Some(2));
+    assert_eq!(inner.integer, 10);
+    assert_eq!(inner.string, "hello");
+}
+
+/// Errors from a replayed value use the original deserializer's error type
+#[test]
+fn replayed_error() {
+    let mut outer = OuterCapture::default();
+    let mut meta = MetaCapture::default();
+
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"integer": 10, "kind": 5, "string": "hello"}"#),
+        TeeCapture::new(&mut outer, &mut meta),
+    ))
+    .expect_err("`kind` must be a string");
+
+    assert!(
+        err.to_string()
+            .starts_with("invalid type: integer `5`, expected a string"),
+        "{}",
+        err
+    );
+}
+
+/// Both captures' fields are reported in unknown-field errors
+#[test]
+fn unknown_field() {
+    let mut capture = Capture::default();
+
+    let err = FlattenDeserializer::builder(&mut serde_json::Deserializer::from_str(
+        r#"{"integer": 10, "before": 1.5, "legacy": 1, "legcy": 2, "string": "hello"}"#,
+    ))
+    .capture(
+        TeeCapture::new(&mut capture, IgnoreCapture::new(&["before", "legacy"]))
+            .with_fields(&["before", "after", "legacy"]),
+    )
+    .unknown_policy(UnknownPolicy::Deny)
+    .deserialize::<Inner>()
+    .expect_err("misspelled field should be rejected");
+
+    assert!(
+        err.to_string().starts_with(
+            "unknown field `legcy`, expected one of `before`, `after`, `legacy`, `integer`, `string`"
+        ),
+        "{}",
+        err
+    );
+    assert_eq!(capture.before, Some(1.5));
+}
+
+/// Two captures with fields can't be combined without `with_fields`
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "must be combined with `with_fields`")]
+fn missing_fields() {
+    let mut capture = Capture::default();
+
+    let _ = TeeCapture::new(&mut capture, IgnoreCapture::new(&["legacy"])).fields();
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{Capture, Inner};
+use serde::Deserialize;
+use serde_bufferless::{
+    capture::{OfferedKey, Trace, TracingCapture},
+    private::flatten::FlattenDeserializer,
+};
+
+#[test]
+fn events() {
+    let mut capture = Capture::default();
+    let mut events = Vec::new();
+
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(
+            r#"{"integer": 10, "before": 1.5, "string": "hello", "after": true}"#,
+        ),
+        TracingCapture::new(&mut capture, |trace: Trace<'_>| {
+            events.push(trace.to_string())
+        }),
+    ))
+    .expect("failed to deserialize");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(
+        events,
+        [
+            r#"key "integer": rejected"#,
+            r#"key "before": accepted"#,
+            "value: ok",
+            r#"key "string": rejected"#,
+            r#"key "after": accepted"#,
+            "value: ok",
+            "finish: ok",
+        ]
+    );
+}
+
+/// Failed values are reported, and the error is passed through unchanged
+#[test]
+fn failed_value() {
+    let mut capture = Capture::default();
+    let mut events = Vec::new();
+
+    let err = Inner::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"after": "yes"}"#),
+        TracingCapture::new(&mut capture, |trace: Trace<'_>| match trace {
+            Trace::Offered { key, accepted, .. } => events.push(Some((key.to_string(), accepted))),
+            Trace::Value { ok, .. } => {
+                assert!(!ok);
+                events.push(None)
+            }
+            Trace::Finish { .. } => panic!("finished after an error"),
+        }),
+    ))
+    .expect_err("deserialized an invalid value");
+
+    assert_eq!(
+        err.to_string(),
+        r#"invalid type: string "yes", expected a boolean at line 1 column 15"#
+    );
+    assert_eq!(events, [Some((r#""after""#.to_owned(), true)), None]);
+}
+
+#[test]
+fn offered_keys() {
+    assert_eq!(OfferedKey::Key(b"name").to_string(), r#""name""#);
+    assert_eq!(OfferedKey::Key(b"\xff").to_string(), "bytes [255]");
+    assert_eq!(OfferedKey::Index(3).to_string(), "index 3");
+}
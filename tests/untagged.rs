@@ -0,0 +1,94 @@
+//! Flattening an untagged enum. Serde's untagged enums buffer their input so
+//! that each variant can be tried in turn, but through a
+//! `FlattenDeserializer` that buffer only ever holds the entries the capture
+//! rejected; the outer struct's own fields are captured as they stream past.
+
+mod common;
+
+use common::Capture;
+use serde::{de, Deserialize};
+use serde_bufferless::private::flatten::FlattenDeserializer;
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Location {
+    Point { x: f64, y: f64 },
+    Named { name: String },
+}
+
+#[derive(Debug, PartialEq)]
+struct Outer {
+    before: f32,
+    after: bool,
+
+    // #[serde(flatten)]
+    location: Location,
+}
+
+impl<'de> Deserialize<'de> for Outer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let mut capture = Capture::default();
+        let location = Location::deserialize(FlattenDeserializer::new(deserializer, &mut capture))?;
+
+        Ok(Outer {
+            before: capture
+                .before
+                .ok_or_else(|| de::Error::missing_field("before"))?,
+            after: capture.after.unwrap_or_default(),
+            location,
+        })
+    }
+}
+
+#[test]
+fn variants() {
+    let point: Outer =
+        serde_json::from_str(r#"{"x": 1.0, "before": 2.5, "y": -1.0, "after": true}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(
+        point,
+        Outer {
+            before: 2.5,
+            after: true,
+            location: Location::Point { x: 1.0, y: -1.0 },
+        }
+    );
+
+    let named: Outer =
+        serde_json::from_str(r#"{"before": 0.5, "name": "home"}"#).expect("failed to deserialize");
+
+    assert_eq!(
+        named,
+        Outer {
+            before: 0.5,
+            after: false,
+            location: Location::Named {
+                name: "home".to_owned()
+            },
+        }
+    );
+}
+
+/// The buffered remainder doesn't include the captured fields, so they can't
+/// make a variant match, and they're still captured when no variant does
+#[test]
+fn no_variant() {
+    let mut capture = Capture::default();
+    let err = Location::deserialize(FlattenDeserializer::new(
+        &mut serde_json::Deserializer::from_str(r#"{"before": 1.5, "x": 1.0}"#),
+        &mut capture,
+    ))
+    .expect_err("neither variant should match");
+
+    assert!(
+        err.to_string()
+            .starts_with("data did not match any variant of untagged enum Location"),
+        "{}",
+        err
+    );
+    assert_eq!(capture.before, Some(1.5));
+}
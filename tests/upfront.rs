@@ -0,0 +1,68 @@
+mod common;
+
+use common::{Capture, Inner, Outer};
+use serde::Deserialize;
+use serde_bufferless::private::{flatten::FlattenDeserializer, upfront::deserialize_upfront};
+use serde_json::{json, Value};
+
+fn input() -> Value {
+    json!({"integer": 10, "before": 1.5, "string": "hello", "after": true})
+}
+
+#[test]
+fn value_source() {
+    let mut capture = Capture::default();
+    let inner: Inner = deserialize_upfront(input(), &mut capture).expect("failed to deserialize");
+    let data: Outer = capture.assemble::<serde_json::Error>(inner).unwrap();
+
+    let mut capture = Capture::default();
+    let streamed = Inner::deserialize(FlattenDeserializer::new(input(), &mut capture))
+        .expect("failed to deserialize");
+    let streamed: Outer = capture.assemble::<serde_json::Error>(streamed).unwrap();
+
+    assert_eq!(data, streamed);
+}
+
+#[test]
+fn map_source() {
+    let map = match input() {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    };
+
+    let mut capture = Capture::default();
+    let inner: Inner = deserialize_upfront(map, &mut capture).expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(1.5));
+    assert_eq!(capture.after, Some(true));
+    assert_eq!(inner.string, "hello");
+}
+
+/// The inner type is deserialized from only the rejected entries, so it never
+/// sees the captured keys at all.
+#[test]
+fn clean_tail() {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Strict {
+        integer: i32,
+    }
+
+    let mut capture = Capture::default();
+    let inner: Strict = deserialize_upfront(
+        json!({"before": 1.5, "integer": 10, "after": false}),
+        &mut capture,
+    )
+    .expect("captured keys shouldn't reach the inner type");
+
+    assert_eq!(inner.integer, 10);
+    assert_eq!(capture.after, Some(false));
+}
+
+#[test]
+fn not_a_map() {
+    let err = deserialize_upfront::<Inner, _, _>(json!([1, 2]), &mut Capture::default())
+        .expect_err("a sequence isn't a map");
+
+    assert_eq!(err.to_string(), "invalid type: sequence, expected a map");
+}
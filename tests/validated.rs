@@ -0,0 +1,52 @@
+mod common;
+
+use common::{Capture, Field, Inner};
+use serde::Deserialize;
+use serde_bufferless::{capture::Validated, private::flatten::FlattenDeserializer};
+
+fn validate(capture: &&mut Capture, field: &Field) -> Result<(), &'static str> {
+    match field {
+        Field::before => match capture.before {
+            Some(before) if !(0.0..=100.0).contains(&before) => {
+                Err("field `before` must be between 0 and 100")
+            }
+            _ => Ok(()),
+        },
+        Field::after => Ok(()),
+    }
+}
+
+fn deserialize(input: &str) -> Result<(Capture, Inner), serde_json::Error> {
+    let mut capture = Capture::default();
+    let mut de = serde_json::Deserializer::from_str(input);
+    let inner = Inner::deserialize(FlattenDeserializer::new(
+        &mut de,
+        Validated::new(&mut capture, validate),
+    ))?;
+
+    Ok((capture, inner))
+}
+
+#[test]
+fn passes() {
+    let (capture, inner) =
+        deserialize(r#"{"before": 50, "integer": 10, "after": false, "string": "hello"}"#)
+            .expect("failed to deserialize");
+
+    assert_eq!(capture.before, Some(50.0));
+    assert_eq!(capture.after, Some(false));
+    assert_eq!(inner.integer, 10);
+}
+
+#[test]
+fn fails() {
+    let err = deserialize(r#"{"integer": 10, "before": 150, "after": false, "string": "hello"}"#)
+        .expect_err("validation should have failed");
+
+    assert!(
+        err.to_string()
+            .contains("field `before` must be between 0 and 100"),
+        "{}",
+        err
+    );
+}
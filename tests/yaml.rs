@@ -0,0 +1,86 @@
+//! YAML mappings deliver keys as owned or borrowed strings depending on the
+//! document, and support anchors and merge keys, none of which should affect
+//! capture routing.
+
+mod common;
+
+use common::{Inner, Outer};
+
+fn expected() -> Outer {
+    Outer {
+        before: 10.0,
+        after: true,
+        inner: Inner {
+            integer: 10,
+            string: "hello".to_owned(),
+        },
+    }
+}
+
+#[test]
+fn one_field() {
+    let data: Outer = serde_yaml::from_str(
+        "
+        integer: 10
+        before: 10
+        string: hello
+        after: true
+        ",
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data, expected());
+}
+
+/// Aliases are replayed by the YAML deserializer, so captured values may be
+/// aliases of inner values, and vice versa.
+#[test]
+fn anchors() {
+    let data: Outer = serde_yaml::from_str(
+        "
+        integer: &n 10
+        after: &t true
+        before: *n
+        string: hello
+        ",
+    )
+    .expect("failed to deserialize");
+
+    assert_eq!(data, expected());
+}
+
+/// `serde_yaml::Deserializer` doesn't apply merge keys by itself, so `<<` is
+/// just another key, which the capture rejects and the inner type ignores.
+/// Merges must be applied on a `serde_yaml::Value` first.
+#[test]
+fn merge_keys() {
+    let document = "
+        defaults: &defaults
+          before: 10
+          after: true
+        outer:
+          <<: *defaults
+          integer: 10
+          string: hello
+        ";
+
+    #[derive(serde::Deserialize)]
+    struct Document {
+        outer: Outer,
+    }
+
+    let err = serde_yaml::from_str::<Document>(document)
+        .err()
+        .expect("merge keys aren't applied by the deserializer");
+    assert!(
+        err.to_string().contains("missing field `before`"),
+        "{}",
+        err
+    );
+
+    let mut value: serde_yaml::Value = serde_yaml::from_str(document).expect("invalid yaml");
+    value.apply_merge().expect("failed to apply merge keys");
+    let data: Document = serde_yaml::from_value(value).expect("failed to deserialize");
+
+    assert_eq!(data.outer, expected());
+}